@@ -127,6 +127,17 @@ else
     echo "+Skipping utils vector generation"
 fi
 
+# Conditionally run Rust bins to output into vector JSON files
+if ! git diff-index --quiet HEAD -- ./rust/optics-primitives/src/xapps; then
+    echo "+Running xApps vector generation"
+    cd ./rust/optics-core
+    echo '+cargo run --bin xapps_test_output --features output'
+    cargo run --bin xapps_test_output --features output
+    cd ../..
+else
+    echo "+Skipping xApps vector generation"
+fi
+
 # Run rust tests, clippy, and formatting
 if ! git diff-index --quiet HEAD -- ./rust; then
     echo "+Running rust tests"
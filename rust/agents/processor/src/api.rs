@@ -0,0 +1,297 @@
+//! HTTP API serving proofs and message status from the processor's DB, for
+//! self-relayers
+//!
+//! - `GET /root`: the prover's root and leaf count
+//! - `GET /proofs/index/<leaf index>`
+//! - `GET /proofs/leaf/<leaf hash>`
+//! - `GET /proofs/nonce/<destination>/<nonce>`
+//! - `GET /status/<destination>/<nonce>`: how far the processor got with a
+//!   message
+//!
+//! Proofs are served as the `ProvenMessage` JSON the `Pusher` uploads to S3.
+//! Unknown messages get a 404.
+use color_eyre::Result;
+use ethers::core::types::H256;
+use serde::Serialize;
+use tokio::task::JoinHandle;
+use tracing::{info, info_span, instrument::Instrumented, Instrument};
+use warp::{http::StatusCode, reply, Filter, Rejection, Reply};
+
+use optics_core::{db::HomeDB, Encode};
+
+use crate::push::ProvenMessage;
+
+/// The prover's current tree
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProverRoot {
+    root: H256,
+    count: usize,
+}
+
+/// How far the processor got with a message
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+enum Status {
+    /// Not yet processed
+    Pending,
+    /// Processed, by this processor or another
+    Processed,
+    /// Skipped for good by a rule
+    Denied,
+    /// Skipped by a rule until the rules change
+    Deferred,
+    /// Failed to process, and in the dead-letter queue
+    DeadLettered,
+}
+
+/// A message's status, and whether the prover has a proof of it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StatusReport {
+    leaf_index: u32,
+    leaf: H256,
+    proven: bool,
+    status: Status,
+    /// The rule that skipped the message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rule: Option<String>,
+    /// Why the latest attempt to process the message failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
+
+/// Serves proofs from a `HomeDB`
+#[derive(Debug, Clone)]
+pub(crate) struct ProofApi {
+    db: HomeDB,
+}
+
+impl ProofApi {
+    /// Instantiate a new API over `db`
+    pub(crate) fn new(db: HomeDB) -> Self {
+        Self { db }
+    }
+
+    fn root(&self) -> Result<Option<ProverRoot>> {
+        let tree = self.db.merkle()?;
+        Ok(Some(ProverRoot {
+            root: tree.root(),
+            count: tree.count(),
+        }))
+    }
+
+    fn proven(&self, leaf: Option<H256>) -> Result<Option<ProvenMessage>> {
+        let message = match leaf {
+            Some(leaf) => self.db.message_by_leaf(leaf)?,
+            None => None,
+        };
+        let message = match message {
+            Some(message) => message,
+            None => return Ok(None),
+        };
+        Ok(self
+            .db
+            .proof_by_leaf_index(message.leaf_index)?
+            .map(|proof| ProvenMessage {
+                message: message.to_vec(),
+                proof,
+            }))
+    }
+
+    fn status(&self, destination: u32, nonce: u32) -> Result<Option<StatusReport>> {
+        let message = match self.db.message_by_nonce(destination, nonce)? {
+            Some(message) => message,
+            None => return Ok(None),
+        };
+
+        let mut status = StatusReport {
+            leaf_index: message.leaf_index,
+            leaf: message.leaf(),
+            proven: self.db.proof_by_leaf_index(message.leaf_index)?.is_some(),
+            status: Status::Pending,
+            rule: None,
+            reason: None,
+        };
+        if let Some(dead_letter) = self.db.dead_letter(destination, nonce)? {
+            status.status = Status::DeadLettered;
+            status.reason = Some(dead_letter.reason);
+        } else if let Some(skipped) = self.db.skipped_message(destination, nonce)? {
+            status.status = if skipped.deferred {
+                Status::Deferred
+            } else {
+                Status::Denied
+            };
+            status.rule = Some(skipped.rule);
+        } else if self
+            .db
+            .retrieve_latest_nonce(destination)?
+            .map_or(false, |latest| nonce <= latest)
+        {
+            status.status = Status::Processed;
+        }
+        Ok(Some(status))
+    }
+
+    /// The API's routes
+    pub(crate) fn routes(self) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+        let api = warp::any().map(move || self.clone());
+
+        let root = warp::path!("root")
+            .and(api.clone())
+            .map(|api: ProofApi| respond(api.root()));
+        let by_index = warp::path!("proofs" / "index" / u32).and(api.clone()).map(
+            |leaf_index, api: ProofApi| {
+                respond(
+                    api.db
+                        .leaf_by_leaf_index(leaf_index)
+                        .map_err(Into::into)
+                        .and_then(|leaf| api.proven(leaf)),
+                )
+            },
+        );
+        let by_leaf = warp::path!("proofs" / "leaf" / H256)
+            .and(api.clone())
+            .map(|leaf, api: ProofApi| respond(api.proven(Some(leaf))));
+        let by_nonce = warp::path!("proofs" / "nonce" / u32 / u32)
+            .and(api.clone())
+            .map(|destination, nonce, api: ProofApi| {
+                respond(
+                    api.db
+                        .leaf_by_nonce(destination, nonce)
+                        .map_err(Into::into)
+                        .and_then(|leaf| api.proven(leaf)),
+                )
+            });
+        let status = warp::path!("status" / u32 / u32)
+            .and(api)
+            .map(|destination, nonce, api: ProofApi| respond(api.status(destination, nonce)));
+
+        warp::get().and(root.or(by_index).or(by_leaf).or(by_nonce).or(status))
+    }
+
+    /// Spawn the task serving the API on `port`
+    pub(crate) fn spawn(self, port: u16) -> Instrumented<JoinHandle<Result<()>>> {
+        tokio::spawn(async move {
+            info!(port, "Serving proofs on 0.0.0.0:{}", port);
+            warp::serve(self.routes()).run(([0, 0, 0, 0], port)).await;
+            Ok(())
+        })
+        .instrument(info_span!("ProofApi", port))
+    }
+}
+
+fn respond<T: Serialize>(result: Result<Option<T>>) -> reply::WithStatus<reply::Json> {
+    match result {
+        Ok(Some(value)) => reply::with_status(reply::json(&value), StatusCode::OK),
+        Ok(None) => reply::with_status(
+            reply::json(&serde_json::json!({ "error": "not found" })),
+            StatusCode::NOT_FOUND,
+        ),
+        Err(e) => reply::with_status(
+            reply::json(&serde_json::json!({ "error": e.to_string() })),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use optics_core::db::{DeadLetter, SkippedMessage};
+    use optics_test::test_utils;
+    use serde_json::Value;
+
+    async fn get(api: &ProofApi, path: &str) -> (StatusCode, Value) {
+        let resp = warp::test::request()
+            .path(path)
+            .reply(&api.clone().routes())
+            .await;
+        (resp.status(), serde_json::from_slice(resp.body()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn it_serves_proofs_and_statuses() {
+        test_utils::run_test_db(|db| async move {
+            let db = HomeDB::new(db, "home_1".to_owned());
+            let messages: Vec<_> = (0..4).map(test_utils::raw_message).collect();
+            let mut tree = db.merkle().unwrap();
+            for message in messages.iter() {
+                db.store_raw_committed_message(message).unwrap();
+                tree.ingest(message.leaf()).unwrap();
+            }
+            for leaf_index in 0..3 {
+                db.store_proof(leaf_index, &tree.prove(leaf_index as usize).unwrap())
+                    .unwrap();
+            }
+            db.store_latest_nonce(2000, 1).unwrap();
+            db.store_skipped_message(
+                2000,
+                1,
+                &SkippedMessage {
+                    leaf_index: 1,
+                    rule: "spam".to_owned(),
+                    deferred: false,
+                    timestamp: 0,
+                },
+            )
+            .unwrap();
+            db.store_dead_letter(
+                2000,
+                2,
+                &DeadLetter {
+                    leaf_index: 2,
+                    reason: "execution reverted".to_owned(),
+                    attempts: 1,
+                    first_failed: 0,
+                    last_failed: 0,
+                    retry_at: Some(60),
+                    deferred: false,
+                },
+            )
+            .unwrap();
+            let api = ProofApi::new(db);
+
+            let (status, root) = get(&api, "/root").await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(root["count"], 4);
+            assert_eq!(root["root"], serde_json::to_value(tree.root()).unwrap());
+
+            let expected = serde_json::to_value(ProvenMessage {
+                message: messages[1].to_vec(),
+                proof: tree.prove(1).unwrap(),
+            })
+            .unwrap();
+            let paths = [
+                "/proofs/index/1".to_owned(),
+                format!("/proofs/leaf/{:?}", messages[1].leaf()),
+                "/proofs/nonce/2000/1".to_owned(),
+            ];
+            for path in paths.iter() {
+                assert_eq!(get(&api, path).await, (StatusCode::OK, expected.clone()));
+            }
+
+            // known but unproven, and unknown messages
+            assert_eq!(get(&api, "/proofs/index/3").await.0, StatusCode::NOT_FOUND);
+            assert_eq!(
+                get(&api, "/proofs/nonce/2000/4").await.0,
+                StatusCode::NOT_FOUND
+            );
+            assert_eq!(get(&api, "/status/3000/0").await.0, StatusCode::NOT_FOUND);
+
+            let mut statuses = vec![];
+            for nonce in 0..4 {
+                let (_, status) = get(&api, &format!("/status/2000/{}", nonce)).await;
+                statuses.push(status);
+            }
+            assert_eq!(statuses[0]["status"], "processed");
+            assert_eq!(statuses[1]["status"], "denied");
+            assert_eq!(statuses[1]["rule"], "spam");
+            assert_eq!(statuses[2]["status"], "deadLettered");
+            assert_eq!(statuses[2]["reason"], "execution reverted");
+            assert_eq!(statuses[3]["status"], "pending");
+            assert_eq!(statuses[3]["proven"], false);
+        })
+        .await
+    }
+}
@@ -0,0 +1,159 @@
+//! Gas budgets for the messages the processor sponsors
+//!
+//! The gas used by each process transaction counts against its message's
+//! sender and recipient on the destination chain. Once either has used up
+//! its budget over the rolling window, their messages are deferred until
+//! enough of their spending leaves the window.
+//!
+//! ```json
+//! "budgets": {
+//!   "sender": { "gas": "0x989680", "window": 86400 },
+//!   "recipients": {
+//!     "0x...": { "gas": "0x5f5e100", "window": 3600 }
+//!   }
+//! }
+//! ```
+//!
+//! Budgets are in units of gas, not wei. They limit how much of the
+//! destination chain's capacity an account can use, whatever the gas price.
+//!
+//! Budgets are checked before a message is dispatched, and its gas is known
+//! once it is processed, so the messages in flight at once can overshoot a
+//! budget.
+//!
+//! Spending is exported as metrics for the addresses with their own budget.
+//! Addresses under a default budget are not, as there is no bound on how
+//! many there are.
+use ethers::prelude::{H256, U256};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use optics_core::db::GasAccount;
+
+/// The prefix of the rule names recorded for messages deferred by a budget
+const BUDGET_RULE_PREFIX: &str = "budget:";
+
+/// A gas limit over a rolling window
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub(crate) struct GasBudget {
+    /// The most gas to spend in the window, in units of gas
+    pub(crate) gas: U256,
+    /// The window's length, in seconds
+    pub(crate) window: u64,
+}
+
+/// Gas budgets for message senders and recipients
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub(crate) struct Budgets {
+    /// The budget for each sender without its own
+    sender: Option<GasBudget>,
+    /// The budget for each recipient without its own
+    recipient: Option<GasBudget>,
+    /// Budgets for particular senders
+    #[serde(default)]
+    senders: HashMap<H256, GasBudget>,
+    /// Budgets for particular recipients
+    #[serde(default)]
+    recipients: HashMap<H256, GasBudget>,
+}
+
+impl Budgets {
+    /// True if no message has a budget
+    pub(crate) fn is_empty(&self) -> bool {
+        self.sender.is_none()
+            && self.recipient.is_none()
+            && self.senders.is_empty()
+            && self.recipients.is_empty()
+    }
+
+    /// The budget of the `account` at `address`, if it has one
+    pub(crate) fn budget(&self, account: GasAccount, address: H256) -> Option<GasBudget> {
+        let (budgets, default) = match account {
+            GasAccount::Sender => (&self.senders, self.sender),
+            GasAccount::Recipient => (&self.recipients, self.recipient),
+        };
+        budgets.get(&address).copied().or(default)
+    }
+
+    /// True if the `account` at `address` has a budget of its own, rather
+    /// than the default
+    pub(crate) fn is_configured(&self, account: GasAccount, address: H256) -> bool {
+        match account {
+            GasAccount::Sender => self.senders.contains_key(&address),
+            GasAccount::Recipient => self.recipients.contains_key(&address),
+        }
+    }
+
+    /// The longest window of any of the `account`'s budgets, if it has any
+    pub(crate) fn longest_window(&self, account: GasAccount) -> Option<u64> {
+        let (budgets, default) = match account {
+            GasAccount::Sender => (&self.senders, self.sender),
+            GasAccount::Recipient => (&self.recipients, self.recipient),
+        };
+        budgets
+            .values()
+            .chain(default.iter())
+            .map(|budget| budget.window)
+            .max()
+    }
+}
+
+/// The rule name recorded for messages deferred by the `account`'s budget
+pub(crate) fn rule_name(account: GasAccount) -> String {
+    format!("{}{}", BUDGET_RULE_PREFIX, account)
+}
+
+/// True if the rule named `rule` is a budget
+pub(crate) fn is_budget_rule(rule: &str) -> bool {
+    rule.starts_with(BUDGET_RULE_PREFIX)
+}
+
+/// An amount of gas as a metric value, capped at `i64::MAX`
+pub(crate) fn gauge_value(gas: U256) -> i64 {
+    gas.min(U256::from(i64::MAX as u64)).as_u64() as i64
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_prefers_address_budgets_to_defaults() {
+        let budgets: Budgets = serde_json::from_value(serde_json::json!({
+            "sender": { "gas": "0x64", "window": 60 },
+            "senders": {
+                "0x0101010101010101010101010101010101010101010101010101010101010101":
+                    { "gas": "0xc8", "window": 120 }
+            }
+        }))
+        .unwrap();
+        assert!(!budgets.is_empty());
+
+        let budget = |account, address| budgets.budget(account, address);
+        assert_eq!(
+            budget(GasAccount::Sender, H256::repeat_byte(1)),
+            Some(GasBudget {
+                gas: 200.into(),
+                window: 120
+            })
+        );
+        assert_eq!(
+            budget(GasAccount::Sender, H256::repeat_byte(2)),
+            Some(GasBudget {
+                gas: 100.into(),
+                window: 60
+            })
+        );
+        assert_eq!(budget(GasAccount::Recipient, H256::repeat_byte(1)), None);
+
+        assert!(budgets.is_configured(GasAccount::Sender, H256::repeat_byte(1)));
+        assert!(!budgets.is_configured(GasAccount::Sender, H256::repeat_byte(2)));
+        assert_eq!(budgets.longest_window(GasAccount::Sender), Some(120));
+        assert_eq!(budgets.longest_window(GasAccount::Recipient), None);
+
+        assert!(Budgets::default().is_empty());
+        assert!(is_budget_rule(&rule_name(GasAccount::Recipient)));
+        assert!(!is_budget_rule("spam"));
+        assert_eq!(gauge_value(U256::MAX), i64::MAX);
+    }
+}
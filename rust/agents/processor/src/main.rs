@@ -8,10 +8,14 @@
 #![warn(missing_docs)]
 #![warn(unused_extern_crates)]
 
+mod api;
+mod budget;
+mod pipeline;
 mod processor;
 mod prover;
 mod prover_sync;
 mod push;
+mod rules;
 mod settings;
 
 use color_eyre::Result;
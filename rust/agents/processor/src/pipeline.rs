@@ -0,0 +1,193 @@
+//! Bookkeeping for processing a replica's messages concurrently
+//!
+//! Up to a window of messages are in flight at once. The nonce checkpoint
+//! only advances once every earlier message is resolved, so that a restart
+//! never skips a message that was still in flight. Messages whose process
+//! transactions revert leave the window for the dead-letter queue, and are
+//! retried with exponential backoff.
+use std::collections::{BTreeMap, BTreeSet};
+
+use optics_core::{db::DeadLetter, RawCommittedMessage};
+
+/// A message dispatched for processing
+#[derive(Debug, Clone)]
+pub(crate) struct Pending {
+    /// The message's nonce on its destination
+    pub(crate) nonce: u32,
+    /// The message
+    pub(crate) message: RawCommittedMessage,
+    /// True if the message was deferred by a rule and is being replayed. The
+    /// checkpoint has already passed replayed messages
+    pub(crate) deferred: bool,
+}
+
+/// How a dispatched message ended up
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Dispatched {
+    /// The message was processed, or already had been
+    Processed,
+    /// The process transaction reverted, or could not be sent
+    Reverted(String),
+}
+
+/// The nonces resolved so far. A message is resolved once it is processed,
+/// skipped or dead-lettered
+#[derive(Debug, Clone)]
+pub(crate) struct Checkpoint {
+    next: u32,
+    resolved: BTreeSet<u32>,
+}
+
+impl Checkpoint {
+    /// Start checkpointing at nonce `next`
+    pub(crate) fn new(next: u32) -> Self {
+        Self {
+            next,
+            resolved: Default::default(),
+        }
+    }
+
+    /// The first unresolved nonce
+    pub(crate) fn next(&self) -> u32 {
+        self.next
+    }
+
+    /// Mark `nonce` resolved. Returns the latest nonce below which every
+    /// message is resolved, if it advanced
+    pub(crate) fn resolve(&mut self, nonce: u32) -> Option<u32> {
+        if nonce < self.next {
+            return None;
+        }
+        self.resolved.insert(nonce);
+
+        let before = self.next;
+        while self.resolved.remove(&self.next) {
+            self.next += 1;
+        }
+        if self.next > before {
+            Some(self.next - 1)
+        } else {
+            None
+        }
+    }
+}
+
+/// A replica's dead-letter queue, read from the DB once at startup and kept
+/// in step with it after, so that finding the due dead letter doesn't decode
+/// the whole queue on every loop
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DeadLetters(BTreeMap<u32, DeadLetter>);
+
+impl DeadLetters {
+    /// Cache the queue read from the DB
+    pub(crate) fn new(dead_letters: Vec<(u32, DeadLetter)>) -> Self {
+        Self(dead_letters.into_iter().collect())
+    }
+
+    /// The number of messages in the queue
+    pub(crate) fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// The dead letter of the message with `nonce`, if any
+    pub(crate) fn get(&self, nonce: u32) -> Option<&DeadLetter> {
+        self.0.get(&nonce)
+    }
+
+    /// Add or replace the dead letter of the message with `nonce`
+    pub(crate) fn insert(&mut self, nonce: u32, dead_letter: DeadLetter) {
+        self.0.insert(nonce, dead_letter);
+    }
+
+    /// Remove the dead letter of the message with `nonce`, if any
+    pub(crate) fn remove(&mut self, nonce: u32) -> Option<DeadLetter> {
+        self.0.remove(&nonce)
+    }
+
+    /// The dead letters due to be retried at `now`, in nonce order
+    pub(crate) fn due(&self, now: u64) -> impl Iterator<Item = (u32, &DeadLetter)> {
+        self.0
+            .iter()
+            .filter(move |(_, d)| d.retry_at.map_or(false, |at| at <= now))
+            .map(|(nonce, d)| (*nonce, d))
+    }
+}
+
+/// When to retry dead-lettered messages
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct RetryPolicy {
+    /// Seconds to wait before the first retry. Doubles with each attempt
+    pub(crate) base_delay: u64,
+    /// Cap on the wait between retries, in seconds
+    pub(crate) max_delay: u64,
+    /// Stop retrying after this many failed attempts. The message stays in
+    /// the queue until it is retried or dropped by hand
+    pub(crate) max_attempts: Option<u32>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            base_delay: 60,
+            max_delay: 60 * 60,
+            max_attempts: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// When to retry a message that failed for the `attempts`th time at
+    /// `now`, in seconds since the unix epoch. None once out of attempts
+    pub(crate) fn retry_at(&self, attempts: u32, now: u64) -> Option<u64> {
+        if self.max_attempts.map_or(false, |max| attempts >= max) {
+            return None;
+        }
+        let backoff = 2u64.saturating_pow(attempts.saturating_sub(1));
+        let delay = self.base_delay.saturating_mul(backoff).min(self.max_delay);
+        Some(now.saturating_add(delay))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_checkpoints_once_earlier_nonces_resolve() {
+        let mut checkpoint = Checkpoint::new(5);
+
+        assert_eq!(checkpoint.resolve(7), None);
+        assert_eq!(checkpoint.resolve(6), None);
+        assert_eq!(checkpoint.next(), 5);
+
+        assert_eq!(checkpoint.resolve(5), Some(7));
+        assert_eq!(checkpoint.next(), 8);
+
+        // nonces below the checkpoint are already resolved
+        assert_eq!(checkpoint.resolve(3), None);
+        assert_eq!(checkpoint.resolve(8), Some(8));
+    }
+
+    #[test]
+    fn it_backs_off_exponentially_up_to_the_cap() {
+        let policy = RetryPolicy {
+            base_delay: 10,
+            max_delay: 100,
+            max_attempts: None,
+        };
+        let delays: Vec<_> = (1..=6)
+            .map(|attempts| policy.retry_at(attempts, 1000).unwrap() - 1000)
+            .collect();
+        assert_eq!(delays, vec![10, 20, 40, 80, 100, 100]);
+
+        // no overflow after many attempts
+        assert_eq!(policy.retry_at(u32::MAX, 1000), Some(1100));
+
+        let policy = RetryPolicy {
+            max_attempts: Some(3),
+            ..policy
+        };
+        assert_eq!(policy.retry_at(2, 1000), Some(1020));
+        assert_eq!(policy.retry_at(3, 1000), None);
+    }
+}
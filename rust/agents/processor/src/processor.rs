@@ -3,32 +3,82 @@ use color_eyre::{
     eyre::{bail, eyre},
     Result,
 };
-use ethers::prelude::H256;
-use futures_util::future::select_all;
+use ethers::core::types::{H256, U256};
+use futures_util::{future::select_all, stream::FuturesUnordered, StreamExt};
 use std::{
     collections::{HashMap, HashSet},
+    convert::TryFrom,
+    future::Future,
+    pin::Pin,
     sync::Arc,
-    time::Duration,
+    task::{Context, Poll},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use tokio::{sync::RwLock, task::JoinHandle, time::sleep};
-use tracing::{debug, error, info, info_span, instrument, instrument::Instrumented, Instrument};
+use tracing::{
+    debug, error, info, info_span, instrument, instrument::Instrumented, warn, Instrument,
+};
 
 use optics_base::{cancel_task, decl_agent, AgentCore, Homes, OpticsAgent, Replicas};
 use optics_core::{
-    accumulator::merkle::Proof, db::HomeDB, CommittedMessage, Common, Home, MessageStatus,
+    accumulator::{merkle::Proof, persistent::PersistentMerkleError},
+    db::{DeadLetter, GasAccount, HomeDB, SkippedMessage},
+    CommittedMessage, Common, Home, MessageStatus, RawCommittedMessage,
 };
 
 use crate::{
+    api::ProofApi,
+    budget::{self, Budgets, GasBudget},
+    pipeline::{Checkpoint, DeadLetters, Dispatched, Pending, RetryPolicy},
     prover_sync::ProverSync,
     push::Pusher,
-    settings::{ProcessorSettings as Settings, S3Config},
+    rules::{RuleAction, RuleReloader, RuleSet, SharedRules, Verdict},
+    settings::{ProcessorSettings as Settings, SinkConfig},
 };
 
 const AGENT_NAME: &str = "processor";
 
-enum Flow {
-    Advance,
-    Repeat,
+/// The default number of messages per replica in flight at once
+const DEFAULT_WINDOW: usize = 8;
+
+/// Seconds since the unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time before unix epoch")
+        .as_secs()
+}
+
+/// A dispatched message, and how it ended up
+#[derive(Debug)]
+struct Finished {
+    pending: Pending,
+    retry: bool,
+    dispatched: Dispatched,
+}
+
+/// A spawned dispatch. A panic in the task surfaces as an error, and the
+/// task is aborted if dropped before it finishes, so that no submission
+/// outlives the loop that started it
+#[derive(Debug)]
+struct DispatchTask(JoinHandle<Result<Finished>>);
+
+impl Future for DispatchTask {
+    type Output = Result<Finished>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0).poll(cx).map(|joined| {
+            joined
+                .map_err(|e| eyre!("dispatch task failed: {}", e))
+                .and_then(|finished| finished)
+        })
+    }
+}
+
+impl Drop for DispatchTask {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
 }
 
 /// The replica processor is responsible for polling messages and waiting until they validate
@@ -36,20 +86,26 @@ enum Flow {
 #[derive(Debug)]
 pub(crate) struct Replica {
     interval: u64,
+    window: usize,
+    retry: RetryPolicy,
     replica: Arc<Replicas>,
     home: Arc<Homes>,
     home_db: HomeDB,
-    allowed: Option<Arc<HashSet<H256>>>,
-    denied: Option<Arc<HashSet<H256>>>,
+    rules: SharedRules,
+    budgets: Arc<Budgets>,
     next_message_nonce: Arc<prometheus::IntGaugeVec>,
+    dead_letters: Arc<prometheus::IntGaugeVec>,
+    gas_spent: Arc<prometheus::IntGaugeVec>,
+    gas_budget: Arc<prometheus::IntGaugeVec>,
+    budget_deferred: Arc<prometheus::IntGaugeVec>,
 }
 
 impl std::fmt::Display for Replica {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "ReplicaProcessor: {{ home: {:?}, replica: {:?}, allowed: {:?}, denied: {:?} }}",
-            self.home, self.replica, self.allowed, self.denied
+            "ReplicaProcessor: {{ home: {:?}, replica: {:?} }}",
+            self.home, self.replica
         )
     }
 }
@@ -61,202 +117,670 @@ impl Replica {
             async move {
                 use optics_core::Replica;
 
-                let domain = self.replica.local_domain();
+                let this = Arc::new(self);
+                let domain = this.replica.local_domain();
 
                 // The basic structure of this loop is as follows:
-                // 1. Get the last processed index
-                // 2. Check if the Home knows of a message above that index
-                //      - If not, wait and poll again
-                // 3. Check if we have a proof for that message
-                //      - If not, wait and poll again
-                // 4. Check if the proof is valid under the replica
-                // 5. Submit the proof to the replica
-                let mut next_message_nonce: u32 = self
+                // 1. Replay deferred messages if the rules changed, or if
+                //    a gas budget may have refilled
+                // 2. Fill the window with the messages after the last one
+                //    dispatched, skipping the ones the rules do not allow
+                // 3. Retry the first dead-lettered message that is due
+                // 4. Wait for a dispatched message to finish, and checkpoint
+                //    the nonce once every earlier message is resolved
+                //
+                // Each dispatched message waits for its proof to be valid
+                // under the replica, then submits it. Submissions from the
+                // same signer are serialized by its `TxManager`. Messages
+                // that fail are recorded in the dead-letter queue, which
+                // counts as resolved
+                let mut next_message_nonce: u32 = this
                     .home_db
                     .retrieve_latest_nonce(domain)?
                     .map(|n: u32| n + 1)
                     .unwrap_or_default();
+                let mut checkpoint = Checkpoint::new(next_message_nonce);
 
-                self.next_message_nonce
-                    .with_label_values(&[self.home.name(), self.replica.name(), AGENT_NAME])
+                this.next_message_nonce
+                    .with_label_values(&[this.home.name(), this.replica.name(), AGENT_NAME])
                     .set(next_message_nonce as i64);
 
                 info!(
                     domain,
                     nonce = next_message_nonce,
-                    replica = self.replica.name(),
+                    replica = this.replica.name(),
+                    window = this.window,
                     "Starting processor for {} {} at nonce {}",
                     domain,
-                    self.replica.name(),
+                    this.replica.name(),
                     next_message_nonce
                 );
 
+                // The rules deferred messages were last checked against. None
+                // until checked, so that rule changes across restarts are seen
+                let mut checked_rules: Option<Arc<RuleSet>> = None;
+                // When messages deferred by a gas budget were last checked
+                let mut budgets_checked = 0;
+                // Deferred messages dispatched and not yet finished
+                let mut replaying: HashSet<u32> = HashSet::new();
+
+                // Edits made with `optics-cli dlq` while the processor was
+                // stopped apply from here
+                let mut dead_letters = DeadLetters::new(this.home_db.dead_letters(domain)?);
+
+                let mut in_flight = FuturesUnordered::new();
+                let mut retrying = false;
+
                 loop {
-                    use optics_core::Replica;
-                    let seq_span = tracing::trace_span!(
-                        "ReplicaProcessor",
-                        name = self.replica.name(),
-                        nonce = next_message_nonce,
-                        replica_domain = self.replica.local_domain(),
-                        home_domain = self.home.local_domain(),
-                    );
+                    let rules = this.rules.current();
+                    let rules_changed =
+                        !matches!(&checked_rules, Some(checked) if Arc::ptr_eq(checked, &rules));
+                    let budgets_due =
+                        !this.budgets.is_empty() && now() >= budgets_checked + this.interval;
+                    if rules_changed || budgets_due {
+                        this.prune_gas_spent(domain)?;
+                        for pending in
+                            this.replay_deferred(domain, &rules, &replaying, &dead_letters)?
+                        {
+                            replaying.insert(pending.nonce);
+                            in_flight.push(this.dispatch(pending, false));
+                        }
+                        checked_rules = Some(rules);
+                        budgets_checked = now();
+                    }
 
-                    match self
-                        .try_msg_by_domain_and_nonce(domain, next_message_nonce)
-                        .instrument(seq_span)
-                        .await
-                    {
-                        Ok(Flow::Advance) => {
-                            self.home_db
-                                .store_latest_nonce(domain, next_message_nonce)?;
-                            next_message_nonce += 1;
-
-                            self.next_message_nonce
-                                .with_label_values(&[
-                                    self.home.name(),
-                                    self.replica.name(),
-                                    AGENT_NAME,
-                                ])
-                                .set(next_message_nonce as i64);
+                    while in_flight.len() - (retrying as usize) < this.window {
+                        let seq_span = tracing::trace_span!(
+                            "ReplicaProcessor",
+                            name = this.replica.name(),
+                            nonce = next_message_nonce,
+                            replica_domain = this.replica.local_domain(),
+                            home_domain = this.home.local_domain(),
+                        );
+
+                        let message = match this
+                            .next_message(domain, next_message_nonce)
+                            .instrument(seq_span)
+                            .await?
+                        {
+                            Some(message) => message,
+                            None => break,
+                        };
+
+                        let pending = Pending {
+                            nonce: next_message_nonce,
+                            message,
+                            deferred: false,
+                        };
+                        if this.allowed(domain, &pending)? {
+                            in_flight.push(this.dispatch(pending, false));
+                        } else {
+                            this.resolve(domain, &mut checkpoint, pending.nonce)?;
                         }
-                        Ok(Flow::Repeat) => {
-                            // there was some fault, let's wait and then try again later when state may have moved
-                            debug!(
-                                domain,
-                                nonce = next_message_nonce,
-                                replica = self.replica.name(),
-                                "Failed to find message_by_nonce or proof_by_leaf_index. Processor retrying message. Replica: {}. Nonce: {}. Domain: {}.",
-                                self.replica.name(),
-                                next_message_nonce,
-                                domain,
-                            );
-                            sleep(Duration::from_secs(self.interval)).await
+
+                        next_message_nonce += 1;
+                        this.next_message_nonce
+                            .with_label_values(&[this.home.name(), this.replica.name(), AGENT_NAME])
+                            .set(next_message_nonce as i64);
+                    }
+
+                    // Dead letters are retried one at a time, outside the
+                    // window, so that they can't crowd it out
+                    this.dead_letters
+                        .with_label_values(&[this.home.name(), this.replica.name(), AGENT_NAME])
+                        .set(dead_letters.len() as i64);
+                    if !retrying {
+                        if let Some(pending) = this.due_dead_letter(domain, &dead_letters)? {
+                            retrying = true;
+                            in_flight.push(this.dispatch(pending, true));
                         }
-                        Err(e) => {
+                    }
+
+                    if in_flight.is_empty() {
+                        sleep(Duration::from_secs(this.interval)).await;
+                        continue;
+                    }
+
+                    // Wake up every interval to look for new messages, even if
+                    // nothing finishes
+                    let finished = tokio::select! {
+                        finished = in_flight.next() => finished,
+                        _ = sleep(Duration::from_secs(this.interval)) => None,
+                    };
+                    let finished = match finished {
+                        Some(Ok(finished)) => finished,
+                        Some(Err(e)) => {
                             error!("fatal error in processor::Replica: {}", e);
                             bail!(e)
                         }
+                        None => continue,
+                    };
+                    if finished.retry {
+                        retrying = false;
                     }
+                    if finished.pending.deferred {
+                        replaying.remove(&finished.pending.nonce);
+                    }
+                    this.finish(domain, &mut checkpoint, &mut dead_letters, finished)?;
                 }
             }
             .in_current_span(),
         )
     }
 
-    /// Attempt to process a message.
-    ///
-    /// Postcondition: ```match retval? {
-    ///   Advance => message skipped ⊻ message was processed
-    ///   Repeat => try again later
-    /// }```
-    ///
-    /// In case of error: send help?
+    /// Retrieve the message with `nonce`, if it has been indexed yet. Reads
+    /// the DB directly, as the home waits for messages that are not indexed,
+    /// which would stall the loop once it has caught up
     #[instrument(err, skip(self), fields(self = %self))]
-    async fn try_msg_by_domain_and_nonce(&self, domain: u32, nonce: u32) -> Result<Flow> {
-        use optics_core::Replica;
-
-        let message = match self.home.message_by_nonce(domain, nonce).await {
-            Ok(Some(m)) => m,
+    async fn next_message(&self, domain: u32, nonce: u32) -> Result<Option<RawCommittedMessage>> {
+        match self.home_db.message_by_nonce(domain, nonce) {
+            Ok(Some(message)) => {
+                info!(target: "seen_committed_messages", leaf_index = message.leaf_index);
+                Ok(Some(message))
+            }
             Ok(None) => {
-                info!(
+                debug!(
                     domain = domain,
                     sequence = nonce,
                     "Message not yet found {}:{}",
                     domain,
                     nonce,
                 );
-                return Ok(Flow::Repeat);
+                Ok(None)
             }
             Err(e) => bail!(e),
-        };
+        }
+    }
 
-        info!(target: "seen_committed_messages", leaf_index = message.leaf_index);
-        let sender = message.message.sender;
+    /// Check a message against the rules and the gas budgets. Records it as
+    /// skipped if they do not allow it
+    fn allowed(&self, domain: u32, pending: &Pending) -> Result<bool> {
+        let rules = self.rules.current();
+        let verdict = rules.evaluate(&pending.message.view()?);
+        if verdict.action != RuleAction::Allow {
+            self.skip(domain, pending.nonce, &pending.message, verdict)?;
+            return Ok(false);
+        }
 
-        // if we have an allow list, filter senders not on it
-        if let Some(false) = self.allowed.as_ref().map(|set| set.contains(&sender)) {
-            info!(
-                sender = ?sender,
-                nonce = nonce,
-                "Skipping message because sender not on allow list. Sender: {}. Domain: {}. Nonce: {}",
-                sender,
+        if let Some(account) = self.over_budget(domain, &pending.message)? {
+            let rule = budget::rule_name(account);
+            self.skip(
                 domain,
-                nonce
-            );
-            return Ok(Flow::Advance);
+                pending.nonce,
+                &pending.message,
+                Verdict {
+                    action: RuleAction::Defer,
+                    rule: &rule,
+                },
+            )?;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// The sender and recipient of a message that have gas budgets, with
+    /// their budgets
+    fn budgeted(&self, sender: H256, recipient: H256) -> Vec<(GasAccount, H256, GasBudget)> {
+        [
+            (GasAccount::Sender, sender),
+            (GasAccount::Recipient, recipient),
+        ]
+        .iter()
+        .filter_map(|&(account, address)| {
+            self.budgets
+                .budget(account, address)
+                .map(|budget| (account, address, budget))
+        })
+        .collect()
+    }
+
+    /// The first of a message's sender and recipient to have used up its gas
+    /// budget, if any
+    fn over_budget(
+        &self,
+        domain: u32,
+        message: &RawCommittedMessage,
+    ) -> Result<Option<GasAccount>> {
+        let view = message.view()?;
+        let now = now();
+        for (account, address, budget) in self.budgeted(view.sender(), view.recipient()) {
+            let spent = self.home_db.gas_spent_since(
+                domain,
+                account,
+                address,
+                now.saturating_sub(budget.window),
+            )?;
+
+            // Only addresses with their own budget are exported, to bound
+            // the number of series
+            if self.budgets.is_configured(account, address) {
+                let account_label = account.to_string();
+                let address_label = format!("{:?}", address);
+                let labels = [
+                    self.home.name(),
+                    self.replica.name(),
+                    account_label.as_str(),
+                    address_label.as_str(),
+                    AGENT_NAME,
+                ];
+                self.gas_spent
+                    .with_label_values(&labels)
+                    .set(budget::gauge_value(spent));
+                self.gas_budget
+                    .with_label_values(&labels)
+                    .set(budget::gauge_value(budget.gas));
+            }
+
+            if spent >= budget.gas {
+                return Ok(Some(account));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Remove records of gas spent before any budget's window
+    fn prune_gas_spent(&self, domain: u32) -> Result<()> {
+        let now = now();
+        for account in [GasAccount::Sender, GasAccount::Recipient] {
+            if let Some(window) = self.budgets.longest_window(account) {
+                self.home_db
+                    .prune_gas_spent_before(domain, account, now.saturating_sub(window))?;
+            }
         }
+        Ok(())
+    }
+
+    /// Record the gas a processed message used against its sender's and
+    /// recipient's budgets
+    fn spend(&self, message: &CommittedMessage, gas: U256) -> Result<()> {
+        let now = now();
+        for (account, address, _) in
+            self.budgeted(message.message.sender, message.message.recipient)
+        {
+            self.home_db.store_gas_spent(
+                message.message.destination,
+                message.message.nonce,
+                account,
+                address,
+                now,
+                gas,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Mark `nonce` resolved, and store the checkpoint if it advanced
+    fn resolve(&self, domain: u32, checkpoint: &mut Checkpoint, nonce: u32) -> Result<()> {
+        if let Some(latest) = checkpoint.resolve(nonce) {
+            self.home_db.store_latest_nonce(domain, latest)?;
+        }
+        Ok(())
+    }
+
+    /// Record how a dispatched message ended up. Processed messages leave
+    /// the dead-letter queue, and failed ones enter it. Either way, a
+    /// message dispatched from the window is resolved
+    fn finish(
+        &self,
+        domain: u32,
+        checkpoint: &mut Checkpoint,
+        dead_letters: &mut DeadLetters,
+        finished: Finished,
+    ) -> Result<()> {
+        let Finished {
+            pending,
+            retry,
+            dispatched,
+        } = finished;
+
+        match dispatched {
+            Dispatched::Processed => {
+                // A message dispatched again after a restart may also be in
+                // the queue
+                if dead_letters.remove(pending.nonce).is_some() {
+                    self.home_db.remove_dead_letter(domain, pending.nonce)?;
+                }
+                if pending.deferred {
+                    self.home_db.remove_skipped_message(domain, pending.nonce)?;
+                } else if !retry {
+                    self.resolve(domain, checkpoint, pending.nonce)?;
+                }
+            }
+            Dispatched::Reverted(reason) => {
+                self.dead_letter(domain, dead_letters, &pending, reason, checkpoint.next())?;
+                if !pending.deferred && !retry {
+                    self.resolve(domain, checkpoint, pending.nonce)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Take the first dead letter due to be retried, with its message
+    fn due_dead_letter(&self, domain: u32, dead_letters: &DeadLetters) -> Result<Option<Pending>> {
+        for (nonce, dead_letter) in dead_letters.due(now()) {
+            let message = match self.home_db.message_by_nonce(domain, nonce)? {
+                Some(message) => message,
+                None => continue,
+            };
 
-        // if we have a deny list, filter senders on it
-        if let Some(true) = self.denied.as_ref().map(|set| set.contains(&sender)) {
             info!(
-                sender = ?sender,
-                nonce = nonce,
-                "Skipping message because sender on deny list. Sender: {}. Domain: {}. Nonce: {}",
-                sender,
+                nonce,
+                attempts = dead_letter.attempts,
+                reason = %dead_letter.reason,
+                "Retrying dead-lettered message. Domain: {}. Nonce: {}",
                 domain,
                 nonce
             );
-            return Ok(Flow::Advance);
+            return Ok(Some(Pending {
+                nonce,
+                message,
+                deferred: dead_letter.deferred,
+            }));
         }
+        Ok(None)
+    }
 
-        let proof = match self.home_db.proof_by_leaf_index(message.leaf_index) {
-            Ok(Some(p)) => p,
-            Ok(None) => {
-                info!(
-                    leaf_hash = ?message.to_leaf(),
-                    leaf_index = message.leaf_index,
-                    "Proof not yet found"
-                );
-                return Ok(Flow::Repeat);
+    /// Record a failed message in the dead-letter queue, and schedule its
+    /// next retry
+    fn dead_letter(
+        &self,
+        domain: u32,
+        dead_letters: &mut DeadLetters,
+        pending: &Pending,
+        reason: String,
+        checkpoint: u32,
+    ) -> Result<()> {
+        let now = now();
+        let previous = dead_letters.get(pending.nonce);
+        let attempts = previous.map_or(0, |d| d.attempts) + 1;
+        let dead_letter = DeadLetter {
+            leaf_index: pending.message.leaf_index,
+            reason,
+            attempts,
+            first_failed: previous.map_or(now, |d| d.first_failed),
+            last_failed: now,
+            retry_at: self.retry.retry_at(attempts, now),
+            deferred: pending.deferred,
+        };
+
+        warn!(
+            nonce = pending.nonce,
+            attempts,
+            reason = %dead_letter.reason,
+            retry_at = ?dead_letter.retry_at,
+            checkpoint,
+            "Failed to process message. Moved it to the dead-letter queue. Domain: {}. Nonce: {}",
+            domain,
+            pending.nonce
+        );
+        self.home_db
+            .store_dead_letter(domain, pending.nonce, &dead_letter)?;
+        dead_letters.insert(pending.nonce, dead_letter);
+        Ok(())
+    }
+
+    /// Spawn a task proving and processing a message
+    fn dispatch(self: &Arc<Self>, pending: Pending, retry: bool) -> DispatchTask {
+        let this = self.clone();
+        DispatchTask(tokio::spawn(
+            async move {
+                let dispatched = this.prove_and_dispatch(pending.message.clone()).await?;
+                Ok(Finished {
+                    pending,
+                    retry,
+                    dispatched,
+                })
+            }
+            .in_current_span(),
+        ))
+    }
+
+    /// Record a message skipped by a rule, so that it can be replayed later
+    fn skip(
+        &self,
+        domain: u32,
+        nonce: u32,
+        message: &RawCommittedMessage,
+        verdict: Verdict<'_>,
+    ) -> Result<()> {
+        info!(
+            sender = ?message.view()?.sender(),
+            nonce = nonce,
+            rule = verdict.rule,
+            action = ?verdict.action,
+            "Skipping message by rule {}. Domain: {}. Nonce: {}",
+            verdict.rule,
+            domain,
+            nonce
+        );
+        self.home_db.store_skipped_message(
+            domain,
+            nonce,
+            &SkippedMessage {
+                leaf_index: message.leaf_index,
+                rule: verdict.rule.to_owned(),
+                deferred: verdict.action == RuleAction::Defer,
+                timestamp: now(),
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Check deferred messages against the current rules and the gas
+    /// budgets. Returns the ones they allow, to be dispatched, and records
+    /// the rules now skipping the others. Messages in `replaying` are already
+    /// dispatched, and messages in the dead-letter queue are retried from
+    /// there.
+    ///
+    /// The gas of a message deferred by a budget is only known once it is
+    /// processed, so one such message per budgeted account is replayed at a
+    /// time.
+    fn replay_deferred(
+        &self,
+        domain: u32,
+        rules: &RuleSet,
+        replaying: &HashSet<u32>,
+        dead_letters: &DeadLetters,
+    ) -> Result<Vec<Pending>> {
+        let mut allowed = vec![];
+        let mut over_budget = 0;
+        let mut budgets_replayed: HashSet<(GasAccount, H256)> = HashSet::new();
+        for (nonce, skipped) in self.home_db.skipped_messages(domain)? {
+            if !skipped.deferred || replaying.contains(&nonce) || dead_letters.get(nonce).is_some()
+            {
+                continue;
+            }
+            let message = match self.home_db.message_by_nonce(domain, nonce)? {
+                Some(message) => message,
+                None => continue,
+            };
+
+            let verdict = rules.evaluate(&message.view()?);
+            match verdict.action {
+                RuleAction::Allow => {
+                    if let Some(account) = self.over_budget(domain, &message)? {
+                        over_budget += 1;
+                        let rule = budget::rule_name(account);
+                        if rule != skipped.rule {
+                            let verdict = Verdict {
+                                action: RuleAction::Defer,
+                                rule: &rule,
+                            };
+                            self.skip(domain, nonce, &message, verdict)?;
+                        }
+                        continue;
+                    }
+                    if budget::is_budget_rule(&skipped.rule) {
+                        let view = message.view()?;
+                        let accounts: Vec<_> = self
+                            .budgeted(view.sender(), view.recipient())
+                            .into_iter()
+                            .map(|(account, address, _)| (account, address))
+                            .collect();
+                        if accounts.iter().any(|a| budgets_replayed.contains(a)) {
+                            over_budget += 1;
+                            continue;
+                        }
+                        budgets_replayed.extend(accounts);
+                    }
+
+                    info!(
+                        nonce,
+                        rule = %skipped.rule,
+                        "Replaying deferred message. Domain: {}. Nonce: {}",
+                        domain,
+                        nonce
+                    );
+                    allowed.push(Pending {
+                        nonce,
+                        message,
+                        deferred: true,
+                    });
+                }
+                RuleAction::Deny => self.skip(domain, nonce, &message, verdict)?,
+                RuleAction::Defer if verdict.rule != skipped.rule => {
+                    self.skip(domain, nonce, &message, verdict)?
+                }
+                RuleAction::Defer => {}
+            }
+        }
+
+        self.budget_deferred
+            .with_label_values(&[self.home.name(), self.replica.name(), AGENT_NAME])
+            .set(over_budget);
+        Ok(allowed)
+    }
+
+    /// Prove and process a message the rules allow. Waits until the prover
+    /// has a proof of the message valid under the replica
+    async fn prove_and_dispatch(&self, message: RawCommittedMessage) -> Result<Dispatched> {
+        let proof = loop {
+            match self.home_db.proof_by_leaf_index(message.leaf_index) {
+                Ok(Some(p)) => break p,
+                Ok(None) => {
+                    info!(
+                        leaf_hash = ?message.leaf(),
+                        leaf_index = message.leaf_index,
+                        "Proof not yet found"
+                    );
+                    sleep(Duration::from_secs(self.interval)).await;
+                }
+                Err(e) => bail!(e),
             }
-            Err(e) => bail!(e),
         };
 
-        if proof.leaf != message.to_leaf() {
+        if proof.leaf != message.leaf() {
             let msg =
-                eyre!("Leaf in prover does not match retrieved message. Index: {}. Calculated: {}. Prover: {}.", message.leaf_index, message.to_leaf(), proof.leaf);
+                eyre!("Leaf in prover does not match retrieved message. Index: {}. Calculated: {}. Prover: {}.", message.leaf_index, message.leaf(), proof.leaf);
             error!("{}", msg);
             bail!(msg);
         }
 
-        while !self.replica.acceptable_root(proof.root()).await? {
+        // Target the newest root the replica accepts, rather than the root
+        // the prover happened to have when it stored the proof
+        let proof = loop {
+            if let Some(proof) = self.proof_for_replica(message.leaf_index).await? {
+                break proof;
+            }
             info!(
-                leaf_hash = ?message.to_leaf(),
+                leaf_hash = ?message.leaf(),
                 leaf_index = message.leaf_index,
-                "Proof under {root} not yet valid here, waiting until Replica confirms",
+                "Latest proof is under {root}, and no proof is valid here yet. Waiting until Replica confirms",
                 root = proof.root(),
             );
             sleep(Duration::from_secs(self.interval)).await;
-        }
+        };
 
+        let message = CommittedMessage::try_from(message)?;
         info!(
             leaf_hash = ?message.to_leaf(),
             leaf_index = message.leaf_index,
             "Dispatching a message for processing {}:{}",
-            domain,
-            nonce
+            message.message.destination,
+            message.message.nonce
         );
 
-        self.process(message, proof).await?;
+        // A failure here is specific to the message, so it is retried on its
+        // own rather than stopping the replica
+        match self.process(message, proof).await {
+            Ok(dispatched) => Ok(dispatched),
+            Err(e) => Ok(Dispatched::Reverted(e.to_string())),
+        }
+    }
+
+    /// Find a proof of the leaf at `leaf_index` under a root the replica
+    /// accepts. Walks back from the replica's committed root through the
+    /// home's updates, stopping at the first root that does not contain the
+    /// leaf.
+    ///
+    /// Failures to query the replica or to read an update are logged and
+    /// return `None`, so that the caller waits and tries again rather than
+    /// failing the replica's task.
+    async fn proof_for_replica(&self, leaf_index: u32) -> Result<Option<Proof>> {
+        use optics_core::Replica;
+
+        let mut root = match self.replica.committed_root().await {
+            Ok(root) => root,
+            Err(e) => {
+                warn!(
+                    error = %e,
+                    leaf_index,
+                    "Failed to fetch the replica's committed root"
+                );
+                return Ok(None);
+            }
+        };
+        loop {
+            match self.home_db.prove_under_root(leaf_index, root) {
+                Ok(Some(proof)) => match self.replica.acceptable_root(root).await {
+                    Ok(true) => return Ok(Some(proof)),
+                    Ok(false) => {}
+                    Err(e) => {
+                        warn!(
+                            error = %e,
+                            leaf_index,
+                            root = ?root,
+                            "Failed to check whether the replica accepts the root"
+                        );
+                        return Ok(None);
+                    }
+                },
+                Ok(None) => return Ok(None),
+                // The prover has not reached the root yet
+                Err(PersistentMerkleError::UnknownRoot(_)) => {}
+                Err(e) => bail!(e),
+            }
 
-        Ok(Flow::Advance)
+            root = match self.home_db.update_by_new_root(root) {
+                Ok(Some(update)) => update.update.previous_root,
+                Ok(None) => return Ok(None),
+                Err(e) => {
+                    warn!(
+                        error = %e,
+                        leaf_index,
+                        root = ?root,
+                        "Failed to read the update to the root"
+                    );
+                    return Ok(None);
+                }
+            };
+        }
     }
 
     #[instrument(err, level = "trace", skip(self), fields(self = %self))]
     /// Dispatch a message for processing. If the message is already proven, process only.
-    async fn process(&self, message: CommittedMessage, proof: Proof) -> Result<()> {
+    async fn process(&self, message: CommittedMessage, proof: Proof) -> Result<Dispatched> {
         use optics_core::Replica;
         let status = self.replica.message_status(message.to_leaf()).await?;
 
-        match status {
+        let outcome = match status {
             MessageStatus::None => {
                 self.replica
                     .prove_and_process(message.as_ref(), &proof)
-                    .await?;
-            }
-            MessageStatus::Proven => {
-                self.replica.process(message.as_ref()).await?;
+                    .await?
             }
+            MessageStatus::Proven => self.replica.process(message.as_ref()).await?,
             MessageStatus::Processed => {
                 info!(
                     domain = message.message.destination,
@@ -267,8 +791,25 @@ impl Replica {
                     message.message.destination,
                     message.message.nonce
                 );
-                return Ok(());
+                return Ok(Dispatched::Processed);
             }
+        };
+
+        // Reverted transactions use gas too. The message is dispatched
+        // either way, so failing to record the gas must not fail it
+        if let Err(e) = self.spend(&message, outcome.gas_used) {
+            error!(
+                txid = ?outcome.txid,
+                "Failed to record gas spent against budgets: {}",
+                e
+            );
+        }
+
+        if !outcome.executed {
+            return Ok(Dispatched::Reverted(format!(
+                "transaction {:?} reverted",
+                outcome.txid
+            )));
         }
 
         info!(
@@ -281,7 +822,7 @@ impl Replica {
             message.message.nonce,
             message.leaf_index,
         );
-        Ok(())
+        Ok(Dispatched::Processed)
     }
 }
 
@@ -289,24 +830,35 @@ decl_agent!(
     /// A processor agent
     Processor {
         interval: u64,
+        window: usize,
+        retry: RetryPolicy,
         replica_tasks: RwLock<HashMap<String, JoinHandle<Result<()>>>>,
-        allowed: Option<Arc<HashSet<H256>>>,
-        denied: Option<Arc<HashSet<H256>>>,
+        rules: SharedRules,
+        budgets: Arc<Budgets>,
         index_only: bool,
         next_message_nonce: Arc<prometheus::IntGaugeVec>,
-        config: Option<S3Config>,
+        dead_letters: Arc<prometheus::IntGaugeVec>,
+        gas_spent: Arc<prometheus::IntGaugeVec>,
+        gas_budget: Arc<prometheus::IntGaugeVec>,
+        budget_deferred: Arc<prometheus::IntGaugeVec>,
+        sinks: Vec<SinkConfig>,
+        api_port: Option<u16>,
     }
 );
 
 impl Processor {
     /// Instantiate a new processor
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         interval: u64,
+        window: usize,
+        retry: RetryPolicy,
         core: AgentCore,
-        allowed: Option<HashSet<H256>>,
-        denied: Option<HashSet<H256>>,
+        rules: RuleSet,
+        budgets: Budgets,
         index_only: bool,
-        config: Option<S3Config>,
+        sinks: Vec<SinkConfig>,
+        api_port: Option<u16>,
     ) -> Self {
         let next_message_nonce = Arc::new(
             core.metrics
@@ -317,16 +869,59 @@ impl Processor {
                 )
                 .expect("processor metric already registered -- should have be a singleton"),
         );
+        let dead_letters = Arc::new(
+            core.metrics
+                .new_int_gauge(
+                    "dead_letter_queue_size",
+                    "Number of messages in the dead-letter queue",
+                    &["home", "replica", "agent"],
+                )
+                .expect("processor metric already registered -- should have be a singleton"),
+        );
+        let gas_spent = Arc::new(
+            core.metrics
+                .new_int_gauge(
+                    "gas_budget_spent",
+                    "Gas spent on the messages of an account with its own budget, in its budget window",
+                    &["home", "replica", "account", "address", "agent"],
+                )
+                .expect("processor metric already registered -- should have be a singleton"),
+        );
+        let gas_budget = Arc::new(
+            core.metrics
+                .new_int_gauge(
+                    "gas_budget_limit",
+                    "Gas budget of an account with its own budget, over its budget window",
+                    &["home", "replica", "account", "address", "agent"],
+                )
+                .expect("processor metric already registered -- should have be a singleton"),
+        );
+        let budget_deferred = Arc::new(
+            core.metrics
+                .new_int_gauge(
+                    "gas_budget_deferred_messages",
+                    "Number of messages deferred until a gas budget refills",
+                    &["home", "replica", "agent"],
+                )
+                .expect("processor metric already registered -- should have be a singleton"),
+        );
 
         Self {
             interval,
+            window: window.max(1),
+            retry,
             core,
             replica_tasks: Default::default(),
-            allowed: allowed.map(Arc::new),
-            denied: denied.map(Arc::new),
+            rules: SharedRules::new(rules),
+            budgets: Arc::new(budgets),
             next_message_nonce,
+            dead_letters,
+            gas_spent,
+            gas_budget,
+            budget_deferred,
             index_only,
-            config,
+            sinks,
+            api_port,
         }
     }
 }
@@ -344,37 +939,62 @@ impl OpticsAgent for Processor {
     {
         Ok(Self::new(
             settings.interval.parse().expect("invalid integer"),
+            settings
+                .window
+                .map(|w| w.parse().expect("invalid integer"))
+                .unwrap_or(DEFAULT_WINDOW),
+            settings.retry.unwrap_or_default().policy(),
             settings.as_ref().try_into_core(AGENT_NAME).await?,
-            settings.allowed,
-            settings.denied,
+            RuleSet::from_settings(settings.rules, settings.allowed, settings.denied),
+            settings.budgets.unwrap_or_default(),
             settings.indexon.is_some(),
-            settings.s3,
+            settings
+                .s3
+                .map(SinkConfig::S3)
+                .into_iter()
+                .chain(settings.sinks.unwrap_or_default())
+                .collect(),
+            settings
+                .api_port
+                .map(|p| p.parse().expect("api port must be u16")),
         ))
     }
 
     fn run(&self, name: &str) -> Instrumented<JoinHandle<Result<()>>> {
         let home = self.home();
         let next_message_nonce = self.next_message_nonce.clone();
+        let dead_letters = self.dead_letters.clone();
+        let gas_spent = self.gas_spent.clone();
+        let gas_budget = self.gas_budget.clone();
+        let budget_deferred = self.budget_deferred.clone();
         let interval = self.interval;
+        let window = self.window;
+        let retry = self.retry;
         let home_db = self.home_db();
 
         let replica_opt = self.replica_by_name(name);
         let name = name.to_owned();
 
-        let allowed = self.allowed.clone();
-        let denied = self.denied.clone();
+        let rules = self.rules.clone();
+        let budgets = self.budgets.clone();
 
         tokio::spawn(async move {
             let replica = replica_opt.ok_or_else(|| eyre!("No replica named {}", name))?;
 
             Replica {
                 interval,
+                window,
+                retry,
                 replica,
                 home,
                 home_db,
-                allowed,
-                denied,
+                rules,
+                budgets,
                 next_message_nonce,
+                dead_letters,
+                gas_spent,
+                gas_budget,
+                budget_deferred,
             }
             .main()
             .await?
@@ -391,7 +1011,7 @@ impl OpticsAgent for Processor {
 
             // tree sync
             info!("Starting ProverSync");
-            let sync = ProverSync::from_disk(self.home_db());
+            let sync = ProverSync::from_disk(self.home_db())?;
             let sync_task = sync.spawn();
 
             info!("Starting indexer");
@@ -407,9 +1027,12 @@ impl OpticsAgent for Processor {
                 .expect("failed to register block_height metric")
                 .with_label_values(&[self.home().name(), Self::AGENT_NAME]);
             let indexer = &self.as_ref().indexer;
-            let index_task = self
-                .home()
-                .index(indexer.from(), indexer.chunk_size(), block_height);
+            let index_task = self.home().index(
+                indexer.from(),
+                indexer.chunk_size(),
+                indexer.confirmations(),
+                block_height,
+            );
 
             info!("started indexer and sync");
 
@@ -420,22 +1043,25 @@ impl OpticsAgent for Processor {
                 // this is the unused must use
                 let names: Vec<&str> = self.replicas().keys().map(|k| k.as_str()).collect();
                 tasks.push(self.run_many(&names));
+
+                // re-read the rules from the config as it changes
+                tasks.push(RuleReloader::new(self.rules.clone(), self.interval).spawn());
             }
 
-            // if we have a bucket, add a task to push to it
-            if let Some(config) = &self.config {
-                info!(bucket = %config.bucket, "Starting S3 push tasks");
+            // add a task to publish proofs to each sink
+            for config in self.sinks.iter() {
+                let sink = config.sink();
+                info!(sink = %sink.name(), "Starting proof push task");
                 tasks.push(
-                    Pusher::new(
-                        self.core.home.name(),
-                        &config.bucket,
-                        config.region.parse().expect("invalid s3 region"),
-                        self.home_db(),
-                    )
-                    .spawn(),
+                    Pusher::new(self.core.home.name(), sink, self.home_db(), self.interval).spawn(),
                 )
             }
 
+            if let Some(port) = self.api_port {
+                info!(port, "Starting proof API");
+                tasks.push(ProofApi::new(self.home_db()).spawn(port));
+            }
+
             // find the first task to shut down. Then cancel all others
             debug!(tasks = tasks.len(), "Selecting across Processor tasks");
             let (res, _, remaining) = select_all(tasks).await;
@@ -448,3 +1074,297 @@ impl OpticsAgent for Processor {
         .instrument(info_span!("Processor::run_all"))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use optics_core::ChainCommunicationError;
+    use optics_test::{
+        mocks::{MockHomeContract, MockReplicaContract},
+        test_utils,
+    };
+
+    const DOMAIN: u32 = 2000;
+
+    fn finished(nonce: u32, deferred: bool, retry: bool, dispatched: Dispatched) -> Finished {
+        Finished {
+            pending: Pending {
+                nonce,
+                message: test_utils::raw_message(nonce),
+                deferred,
+            },
+            retry,
+            dispatched,
+        }
+    }
+
+    fn gauge(name: &str, labels: &[&str]) -> Arc<prometheus::IntGaugeVec> {
+        Arc::new(prometheus::IntGaugeVec::new(prometheus::Opts::new(name, name), labels).unwrap())
+    }
+
+    fn processor(home_db: HomeDB) -> Replica {
+        let labels = ["home", "replica", "agent"];
+        let budget_labels = ["home", "replica", "account", "address", "agent"];
+        Replica {
+            interval: 1,
+            window: DEFAULT_WINDOW,
+            retry: RetryPolicy::default(),
+            replica: Arc::new(MockReplicaContract::new().into()),
+            home: Arc::new(MockHomeContract::new().into()),
+            home_db,
+            rules: SharedRules::new(RuleSet::default()),
+            budgets: Arc::new(Budgets::default()),
+            next_message_nonce: gauge("next_message_nonce", &labels),
+            dead_letters: gauge("dead_letter_queue_size", &labels),
+            gas_spent: gauge("gas_budget_spent", &budget_labels),
+            gas_budget: gauge("gas_budget_limit", &budget_labels),
+            budget_deferred: gauge("gas_budget_deferred_messages", &labels),
+        }
+    }
+
+    #[tokio::test]
+    async fn it_dead_letters_reverted_messages_and_checkpoints_past_them() {
+        test_utils::run_test_db(|db| async move {
+            let home_db = HomeDB::new(db, "home_1".to_owned());
+            let processor = processor(home_db.clone());
+            let mut checkpoint = Checkpoint::new(0);
+            let mut dead_letters = DeadLetters::default();
+
+            processor
+                .finish(
+                    DOMAIN,
+                    &mut checkpoint,
+                    &mut dead_letters,
+                    finished(1, false, false, Dispatched::Processed),
+                )
+                .unwrap();
+            assert_eq!(checkpoint.next(), 0);
+            assert_eq!(home_db.retrieve_latest_nonce(DOMAIN).unwrap(), None);
+
+            processor
+                .finish(
+                    DOMAIN,
+                    &mut checkpoint,
+                    &mut dead_letters,
+                    finished(0, false, false, Dispatched::Reverted("reverted".into())),
+                )
+                .unwrap();
+
+            // the dead letter resolves the message, so the checkpoint moves
+            // past both
+            assert_eq!(checkpoint.next(), 2);
+            assert_eq!(home_db.retrieve_latest_nonce(DOMAIN).unwrap(), Some(1));
+
+            let dead_letter = home_db.dead_letter(DOMAIN, 0).unwrap().unwrap();
+            assert_eq!(dead_letter.reason, "reverted");
+            assert_eq!(dead_letter.attempts, 1);
+            assert!(dead_letter.retry_at.unwrap() > now());
+            assert_eq!(dead_letters.get(0), Some(&dead_letter));
+
+            // not due yet
+            home_db
+                .store_raw_committed_message(&test_utils::raw_message(0))
+                .unwrap();
+            assert!(processor
+                .due_dead_letter(DOMAIN, &dead_letters)
+                .unwrap()
+                .is_none());
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_removes_dead_letters_once_a_retry_succeeds() {
+        test_utils::run_test_db(|db| async move {
+            let home_db = HomeDB::new(db, "home_1".to_owned());
+            let processor = processor(home_db.clone());
+            let mut checkpoint = Checkpoint::new(5);
+
+            // a deferred message whose replay failed
+            home_db
+                .store_raw_committed_message(&test_utils::raw_message(3))
+                .unwrap();
+            home_db
+                .store_skipped_message(
+                    DOMAIN,
+                    3,
+                    &SkippedMessage {
+                        leaf_index: 3,
+                        rule: "later".to_owned(),
+                        deferred: true,
+                        timestamp: 0,
+                    },
+                )
+                .unwrap();
+            home_db
+                .store_dead_letter(
+                    DOMAIN,
+                    3,
+                    &DeadLetter {
+                        leaf_index: 3,
+                        reason: "reverted".to_owned(),
+                        attempts: 1,
+                        first_failed: 0,
+                        last_failed: 0,
+                        retry_at: Some(0),
+                        deferred: true,
+                    },
+                )
+                .unwrap();
+            let mut dead_letters = DeadLetters::new(home_db.dead_letters(DOMAIN).unwrap());
+
+            let pending = processor
+                .due_dead_letter(DOMAIN, &dead_letters)
+                .unwrap()
+                .unwrap();
+            assert_eq!(pending.nonce, 3);
+            assert!(pending.deferred);
+
+            // a failed retry counts another attempt
+            processor
+                .finish(
+                    DOMAIN,
+                    &mut checkpoint,
+                    &mut dead_letters,
+                    finished(3, true, true, Dispatched::Reverted("again".into())),
+                )
+                .unwrap();
+            let dead_letter = home_db.dead_letter(DOMAIN, 3).unwrap().unwrap();
+            assert_eq!(dead_letter.attempts, 2);
+            assert_eq!(dead_letter.first_failed, 0);
+            assert!(home_db.skipped_message(DOMAIN, 3).unwrap().is_some());
+
+            processor
+                .finish(
+                    DOMAIN,
+                    &mut checkpoint,
+                    &mut dead_letters,
+                    finished(3, true, true, Dispatched::Processed),
+                )
+                .unwrap();
+            assert_eq!(home_db.dead_letter(DOMAIN, 3).unwrap(), None);
+            assert_eq!(home_db.skipped_message(DOMAIN, 3).unwrap(), None);
+            assert_eq!(dead_letters.len(), 0);
+
+            // retries never move the checkpoint
+            assert_eq!(checkpoint.next(), 5);
+            assert_eq!(home_db.retrieve_latest_nonce(DOMAIN).unwrap(), None);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_surfaces_dispatch_panics_and_aborts_dropped_dispatches() {
+        let panicked = DispatchTask(tokio::spawn(async {
+            Ok(Option::<Finished>::None.expect("dispatch panicked"))
+        }));
+        assert!(panicked.await.is_err());
+
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        let dropped = DispatchTask(tokio::spawn(async move {
+            let _tx = tx;
+            sleep(Duration::from_secs(600)).await;
+            Err(eyre!("never finishes"))
+        }));
+        drop(dropped);
+        // aborting the task drops its sender
+        assert!(rx.await.is_err());
+    }
+
+    #[tokio::test]
+    async fn it_finishes_dispatches_once_caught_up() {
+        test_utils::run_test_db(|db| async move {
+            let home_db = HomeDB::new(db, "home_1".to_owned());
+
+            // the home has a single message, with its proof
+            let message = test_utils::raw_message(0);
+            home_db.store_raw_committed_message(&message).unwrap();
+            let mut tree = home_db.merkle().unwrap();
+            tree.ingest_all(std::iter::once(message.leaf())).unwrap();
+            home_db.store_proof(0, &tree.prove(0).unwrap()).unwrap();
+            let root = tree.root();
+
+            let mut mock_home = MockHomeContract::new();
+            mock_home.expect__name().return_const("home_1".to_owned());
+            mock_home.expect__local_domain().return_const(1000u32);
+
+            let mut mock_replica = MockReplicaContract::new();
+            mock_replica
+                .expect__name()
+                .return_const("replica_1".to_owned());
+            mock_replica.expect__local_domain().return_const(DOMAIN);
+            mock_replica
+                .expect__committed_root()
+                .returning(move || Ok(root));
+            mock_replica
+                .expect__acceptable_root()
+                .returning(move |r| Ok(r == root));
+            mock_replica
+                .expect__message_status()
+                .returning(|_| Ok(MessageStatus::Processed));
+
+            let task = Replica {
+                home: Arc::new(mock_home.into()),
+                replica: Arc::new(mock_replica.into()),
+                ..processor(home_db.clone())
+            }
+            .main();
+
+            // nonce 1 never arrives, but the dispatch of nonce 0 is still
+            // checkpointed
+            let checkpointed = tokio::time::timeout(Duration::from_secs(10), async {
+                while home_db.retrieve_latest_nonce(DOMAIN).unwrap().is_none() {
+                    sleep(Duration::from_millis(10)).await;
+                }
+            })
+            .await;
+            task.abort();
+            assert!(checkpointed.is_ok());
+            assert_eq!(home_db.retrieve_latest_nonce(DOMAIN).unwrap(), Some(0));
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_retries_replica_errors_while_waiting_for_a_proof() {
+        test_utils::run_test_db(|db| async move {
+            let home_db = HomeDB::new(db, "home_1".to_owned());
+            let message = test_utils::raw_message(0);
+            home_db.store_raw_committed_message(&message).unwrap();
+            let mut tree = home_db.merkle().unwrap();
+            tree.ingest_all(std::iter::once(message.leaf())).unwrap();
+            home_db.store_proof(0, &tree.prove(0).unwrap()).unwrap();
+            let root = tree.root();
+
+            let mut mock_replica = MockReplicaContract::new();
+            let mut failed = false;
+            // the first query fails
+            mock_replica
+                .expect__committed_root()
+                .times(2)
+                .returning(move || {
+                    if failed {
+                        return Ok(root);
+                    }
+                    failed = true;
+                    Err(ChainCommunicationError::CustomError("timed out".into()))
+                });
+            mock_replica
+                .expect__acceptable_root()
+                .returning(move |r| Ok(r == root));
+            mock_replica
+                .expect__message_status()
+                .returning(|_| Ok(MessageStatus::Processed));
+
+            let processor = Replica {
+                replica: Arc::new(mock_replica.into()),
+                ..processor(home_db)
+            };
+            assert!(matches!(
+                processor.prove_and_dispatch(message).await.unwrap(),
+                Dispatched::Processed
+            ));
+        })
+        .await
+    }
+}
@@ -5,16 +5,18 @@
 use ethers::core::types::H256;
 
 use optics_core::accumulator::{
-    merkle::{merkle_root_from_branch, MerkleTree, MerkleTreeError, Proof},
+    incremental::IncrementalMerkle,
+    merkle::{merkle_root_from_branch, Proof},
+    persistent::{PersistentMerkle, PersistentMerkleError},
     TREE_DEPTH,
 };
 
 /// A depth-32 sparse Merkle tree capable of producing proofs for arbitrary
-/// elements.
+/// elements. Backed by a `PersistentMerkle`, so that it can be reloaded
+/// without replaying every leaf.
 #[derive(Debug)]
 pub struct Prover {
-    count: usize,
-    tree: MerkleTree,
+    tree: PersistentMerkle,
 }
 
 /// Prover Errors
@@ -33,7 +35,7 @@ pub enum ProverError {
     },
     /// Bubbled up from underlying
     #[error(transparent)]
-    MerkleTreeError(#[from] MerkleTreeError),
+    PersistentMerkleError(#[from] PersistentMerkleError),
     /// Failed proof verification
     #[error("Proof verification failed. Root is {expected}, produced is {actual}")]
     #[allow(dead_code)]
@@ -47,32 +49,63 @@ pub enum ProverError {
 
 impl Default for Prover {
     fn default() -> Self {
-        let full = MerkleTree::create(&[], TREE_DEPTH);
         Self {
-            count: 0,
-            tree: full,
+            tree: PersistentMerkle::in_memory(),
         }
     }
 }
 
 impl Prover {
+    /// Instantiate a `Prover` over a (possibly non-empty) persistent tree
+    pub fn new(tree: PersistentMerkle) -> Self {
+        Self { tree }
+    }
+
     /// Push a leaf to the tree. Appends it to the first unoccupied slot
     ///
     /// This will fail if the underlying tree is full.
     pub fn ingest(&mut self, element: H256) -> Result<H256, ProverError> {
-        self.count += 1;
-        self.tree.push_leaf(element, TREE_DEPTH)?;
-        Ok(self.tree.hash())
+        Ok(self.tree.ingest(element)?)
+    }
+
+    /// Push leaves to the tree, persisting them atomically
+    ///
+    /// This will fail if the underlying tree is full.
+    pub fn ingest_all(
+        &mut self,
+        elements: impl IntoIterator<Item = H256>,
+    ) -> Result<H256, ProverError> {
+        Ok(self.tree.ingest_all(elements)?)
+    }
+
+    /// Drop every leaf at or after `count`
+    pub fn truncate(&mut self, count: usize) -> Result<(), ProverError> {
+        Ok(self.tree.truncate(count)?)
     }
 
     /// Return the current root hash of the tree
     pub fn root(&self) -> H256 {
-        self.tree.hash()
+        self.tree.root()
+    }
+
+    /// Return the root hash of the tree as it was at `count` leaves
+    pub fn root_at(&self, count: usize) -> Result<H256, ProverError> {
+        Ok(self.tree.root_at(count)?)
     }
 
     /// Return the number of leaves that have been ingested
     pub fn count(&self) -> usize {
-        self.count
+        self.tree.count()
+    }
+
+    /// Return the leaf at `index`
+    pub fn leaf(&self, index: usize) -> Result<H256, ProverError> {
+        Ok(self.tree.leaf(index)?)
+    }
+
+    /// Return the leading-edge branch of the tree
+    pub fn incremental(&self) -> IncrementalMerkle {
+        self.tree.incremental()
     }
 
     /// Create a proof of a leaf in this tree.
@@ -87,10 +120,7 @@ impl Prover {
             return Err(ProverError::ZeroProof { index, count });
         }
 
-        let (leaf, hashes) = self.tree.generate_proof(index, TREE_DEPTH);
-        let mut path = [H256::zero(); 32];
-        path.copy_from_slice(&hashes[..32]);
-        Ok(Proof { leaf, index, path })
+        Ok(self.tree.prove(index)?)
     }
 
     /// Verify a proof against this tree's root.
@@ -110,12 +140,9 @@ impl<T> From<T> for Prover
 where
     T: AsRef<[H256]>,
 {
+    /// Will panic if the tree fills
     fn from(t: T) -> Self {
-        let slice = t.as_ref();
-        Self {
-            count: slice.len(),
-            tree: MerkleTree::create(slice, TREE_DEPTH),
-        }
+        t.as_ref().iter().copied().collect()
     }
 }
 
@@ -131,9 +158,7 @@ impl std::iter::FromIterator<H256> for Prover {
 impl std::iter::Extend<H256> for Prover {
     /// Will panic if the tree fills
     fn extend<I: IntoIterator<Item = H256>>(&mut self, iter: I) {
-        for i in iter {
-            self.ingest(i).expect("!tree full");
-        }
+        self.ingest_all(iter).expect("!tree full");
     }
 }
 
@@ -3,12 +3,12 @@ use color_eyre::eyre::{bail, Result};
 use ethers::core::types::H256;
 use optics_core::{
     accumulator::{incremental::IncrementalMerkle, INITIAL_ROOT},
-    db::{DbError, HomeDB},
+    db::{DbError, HomeDB, HomeEvent},
     ChainCommunicationError,
 };
-use std::{fmt::Display, ops::Range, time::Duration};
-use tokio::{task::JoinHandle, time::sleep};
-use tracing::{debug, error, info, info_span, instrument, instrument::Instrumented, Instrument};
+use std::{fmt::Display, ops::Range};
+use tokio::task::JoinHandle;
+use tracing::{error, info, info_span, instrument, instrument::Instrumented, warn, Instrument};
 
 /// Struct to sync prover.
 #[derive(Debug)]
@@ -16,6 +16,7 @@ pub struct ProverSync {
     db: HomeDB,
     prover: Prover,
     incremental: IncrementalMerkle,
+    rollbacks: u64,
 }
 
 impl Display for ProverSync {
@@ -61,6 +62,10 @@ pub enum ProverSyncError {
     /// ProverSync receives ChainCommunicationError from chain API
     #[error(transparent)]
     ChainCommunicationError(#[from] ChainCommunicationError),
+    /// The indexer rolled back the HomeDB (e.g. due to a reorg) while the
+    /// prover was syncing
+    #[error("HomeDB was rolled back during sync")]
+    RolledBack,
     /// DB Error
     #[error("{0}")]
     DbError(#[from] DbError),
@@ -87,55 +92,115 @@ impl ProverSync {
         }
     }
 
-    /// Given rocksdb handle `db` containing merkle tree leaves,
-    /// instantiates new `ProverSync` and fills prover's merkle tree
+    /// Given rocksdb handle `db` containing merkle tree leaves, loads the
+    /// prover's persisted merkle tree and instantiates new `ProverSync`
     #[instrument(level = "debug", skip(db))]
-    pub fn from_disk(db: HomeDB) -> Self {
-        // Ingest all leaves in db into prover tree
-        let mut prover = Prover::default();
-        let mut incremental = IncrementalMerkle::default();
-
-        if let Some(root) = db.retrieve_latest_root().expect("db error") {
-            for i in 0.. {
-                match db.leaf_by_leaf_index(i) {
-                    Ok(Some(leaf)) => {
-                        debug!(leaf_index = i, "Ingesting leaf from_disk");
-                        prover.ingest(leaf).expect("!tree full");
-                        incremental.ingest(leaf);
-                        assert_eq!(prover.root(), incremental.root());
-                        if prover.root() == root {
-                            break;
-                        }
-                    }
-                    Ok(None) => break,
-                    Err(e) => {
-                        error!(error = %e, "Error in ProverSync::from_disk");
-                        panic!("Error in ProverSync::from_disk");
-                    }
-                }
+    pub fn from_disk(db: HomeDB) -> Result<Self, ProverSyncError> {
+        let rollbacks = db.retrieve_rollback_count()?;
+        let mut prover = Prover::new(db.merkle().map_err(ProverError::from)?);
+
+        // Drop leaves the indexer has since rolled back. Then drop leaves
+        // until the root is one produced by an update, so that sync resumes
+        // from a root the updater signed
+        let mut count = prover.count();
+        while count > 0 && db.leaf_by_leaf_index(count as u32 - 1)? != Some(prover.leaf(count - 1)?)
+        {
+            count -= 1;
+        }
+        if count < prover.count() {
+            while count > 0 && db.update_by_new_root(prover.root_at(count)?)?.is_none() {
+                count -= 1;
             }
-            info!(target_latest_root = ?root, root = ?incremental.root(), "Reloaded ProverSync from disk");
+            warn!(
+                count,
+                rolled_back = prover.count() - count,
+                "Truncating prover tree to match HomeDB"
+            );
+            prover.truncate(count)?;
         }
+        info!(root = ?prover.root(), count, "Reloaded ProverSync from disk");
 
         let sync = Self {
+            incremental: prover.incremental(),
             prover,
-            incremental,
             db,
+            rollbacks,
         };
 
-        // Ensure proofs exist for all leaves
-        for i in 0..sync.prover.count() as u32 {
-            match (
-                sync.db.leaf_by_leaf_index(i).expect("db error"),
-                sync.db.proof_by_leaf_index(i).expect("db error"),
-            ) {
-                (Some(_), None) => sync.store_proof(i).expect("db error"),
-                (None, _) => break,
+        // Ensure proofs exist for all leaves. Proofs are stored after leaves
+        // are committed to the tree, so only the latest leaves can lack one
+        for i in (0..count as u32).rev() {
+            if sync.db.proof_by_leaf_index(i)?.is_some() {
+                break;
+            }
+            sync.store_proof(i)?;
+        }
+
+        Ok(sync)
+    }
+
+    /// True if the HomeDB has been rolled back since this was loaded
+    fn rolled_back(&self) -> Result<bool, ProverSyncError> {
+        Ok(self.db.retrieve_rollback_count()? != self.rollbacks)
+    }
+
+    /// Reload the trees from disk after a rollback, and replace any proofs
+    /// that were generated under a root that has been rolled back.
+    #[instrument(err, skip(self), fields(self = %self))]
+    fn reset(&mut self) -> Result<(), ProverSyncError> {
+        warn!("HomeDB was rolled back. Reloading ProverSync from disk");
+        *self = Self::from_disk(self.db.clone())?;
+
+        let root = self.prover.root();
+        for i in 0..self.prover.count() as u32 {
+            match self.db.proof_by_leaf_index(i)? {
+                Some(proof) if proof.root() != root => self.store_proof(i)?,
                 _ => {}
             }
         }
+        Ok(())
+    }
+
+    /// Wait for a leaf to be stored. Bails if the DB is rolled back while
+    /// waiting, as the leaf may never arrive.
+    async fn wait_for_leaf(&self, leaf_index: u32) -> Result<H256, ProverSyncError> {
+        let leaf = self
+            .db
+            .wait_until(
+                || {
+                    if self.db.retrieve_rollback_count()? != self.rollbacks {
+                        return Ok(Some(None));
+                    }
+                    Ok(self.db.leaf_by_leaf_index(leaf_index)?.map(Some))
+                },
+                |event| match event {
+                    HomeEvent::Leaf { leaf_index: i, .. } => *i == leaf_index,
+                    HomeEvent::RolledBack => true,
+                    _ => false,
+                },
+            )
+            .await?;
+        leaf.ok_or(ProverSyncError::RolledBack)
+    }
 
-        sync
+    /// Wait until an update building off `local_root` is stored, or the DB
+    /// is rolled back
+    async fn wait_for_update(&self, local_root: H256) -> Result<(), ProverSyncError> {
+        self.db
+            .wait_until(
+                || {
+                    let ready = self.db.retrieve_rollback_count()? != self.rollbacks
+                        || self.db.update_by_previous_root(local_root)?.is_some();
+                    Ok(if ready { Some(()) } else { None })
+                },
+                |event| match event {
+                    HomeEvent::Update { previous_root, .. } => *previous_root == local_root,
+                    HomeEvent::RolledBack => true,
+                    _ => false,
+                },
+            )
+            .await?;
+        Ok(())
     }
 
     // The current canonical local root. This is the root that the full
@@ -154,11 +219,7 @@ impl ProverSync {
         let mut leaves = vec![];
 
         for i in range {
-            let leaf = self.db.wait_for_leaf(i as u32).await?;
-            if leaf.is_none() {
-                break;
-            }
-            leaves.push(leaf.unwrap());
+            leaves.push(self.wait_for_leaf(i as u32).await?);
         }
 
         Ok(leaves)
@@ -189,32 +250,35 @@ impl ProverSync {
             return Ok(());
         }
 
-        // Extend in-memory tree
+        // Check the leaves against the update on a copy of the tree's
+        // leading edge, so that leaves that don't produce its root are never
+        // persisted
         info!("Committing leaves {}..{} to prover.", start, end);
         let leaves = self.get_leaf_range(start..end).await?;
-        let num_leaves = leaves.len();
-
-        self.prover.extend(leaves.into_iter());
-        info!("Committed {} leaves to prover.", num_leaves);
-
-        if new_root != self.prover.root() {
+        let mut check = self.prover.incremental();
+        for leaf in leaves.iter() {
+            check.ingest(*leaf);
+        }
+        if new_root != check.root() {
             error!(
                 start = ?local_root,
                 expected = ?new_root,
-                actual = ?self.prover.root(),
-                "Prover in unexpected state after committing leaves"
+                actual = ?check.root(),
+                "Leaves do not produce the updated root"
             );
             return Err(ProverSyncError::MismatchedRoots {
-                local_root: self.prover.root(),
+                local_root: check.root(),
                 new_root,
             });
         }
 
-        // if we don't already have a proof in the DB
-        // calculate a proof under the current root for each leaf
+        let num_leaves = leaves.len();
+        self.prover.ingest_all(leaves)?;
+        info!("Committed {} leaves to prover.", num_leaves);
+
+        // calculate a proof under the current root for each new leaf
         // store all calculated proofs in the db
-        // TODO(luke): refactor prover_sync so we dont have to iterate over every leaf (match from_disk implementation)
-        for idx in 0..self.prover.count() {
+        for idx in start..end {
             if self.db.proof_by_leaf_index(idx as u32)?.is_none() {
                 self.store_proof(idx as u32)?;
             }
@@ -262,22 +326,17 @@ impl ProverSync {
             // As we fill the incremental merkle, its tree_size will always be
             // equal to the index of the next leaf we want (e.g. if tree_size
             // is 3, we want the 4th leaf, which is at index 3)
-            if let Some(leaf) = self.db.wait_for_leaf(tree_size as u32).await? {
-                info!(
-                    index = tree_size,
-                    leaf = ?leaf,
-                    "Leaf at index {} is {}",
-                    tree_size,
-                    leaf
-                );
-                incremental.ingest(leaf);
-                leaves.push(leaf);
-                current_root = incremental.root();
-            } else {
-                // break on no leaf (warn: path never taken)
-                current_root = incremental.root();
-                break;
-            }
+            let leaf = self.wait_for_leaf(tree_size as u32).await?;
+            info!(
+                index = tree_size,
+                leaf = ?leaf,
+                "Leaf at index {} is {}",
+                tree_size,
+                leaf
+            );
+            incremental.ingest(leaf);
+            leaves.push(leaf);
+            current_root = incremental.root();
             tree_size = incremental.count();
         }
 
@@ -304,6 +363,10 @@ impl ProverSync {
         let span = info_span!("ProverSync", self = %self);
         tokio::spawn(async move {
             loop {
+                if self.rolled_back()? {
+                    self.reset()?;
+                }
+
                 let local_root = self.local_root();
                 let signed_update_opt = self.db.update_by_previous_root(local_root)?;
 
@@ -319,15 +382,28 @@ impl ProverSync {
                         "Have signed update from {} to {}",
                         signed_update.update.previous_root, signed_update.update.new_root,
                     );
-                    self.update_full(local_root, signed_update.update.new_root)
-                        .await?;
-                } else if !local_root.is_zero() && self.db.update_by_new_root(local_root)?.is_none()
-                {
-                    bail!(ProverSyncError::InvalidLocalRoot { local_root });
+                    match self
+                        .update_full(local_root, signed_update.update.new_root)
+                        .await
+                    {
+                        Ok(()) => {}
+                        // The leaves or update we were syncing were removed
+                        // by the indexer. Reload on the next iteration
+                        Err(ProverSyncError::RolledBack) => continue,
+                        Err(e @ ProverSyncError::MismatchedRoots { .. }) => {
+                            if !self.rolled_back()? {
+                                bail!(e);
+                            }
+                            continue;
+                        }
+                        Err(e) => bail!(e),
+                    }
+                } else {
+                    if !local_root.is_zero() && self.db.update_by_new_root(local_root)?.is_none() {
+                        bail!(ProverSyncError::InvalidLocalRoot { local_root });
+                    }
+                    self.wait_for_update(local_root).await?;
                 }
-
-                // kludge
-                sleep(Duration::from_millis(100)).await;
             }
         })
         .instrument(span)
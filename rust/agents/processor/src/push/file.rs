@@ -0,0 +1,221 @@
+use async_trait::async_trait;
+use color_eyre::eyre::Result;
+use std::{
+    collections::HashMap,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use tracing::{info, warn};
+
+use super::{ProofSink, ProvenMessage};
+
+/// Writes each proof to `<home>_<leaf index>.json` in a directory, and
+/// appends a line to the `<home>_index.jsonl` index file with the proof's
+/// leaf index, leaf hash and file name. A proof already in the index is not
+/// indexed again, so the index has one line per leaf even if the processor
+/// stopped before recording its progress
+#[derive(Debug, Clone)]
+pub(crate) struct FileSink {
+    directory: PathBuf,
+    // The latest leaf index in each home's index file, once read
+    indexed: Arc<Mutex<HashMap<String, Option<usize>>>>,
+}
+
+impl FileSink {
+    /// Instantiate a new sink writing to `directory`. The directory is
+    /// created if missing
+    pub(crate) fn new(directory: &str) -> Self {
+        Self {
+            directory: directory.into(),
+            indexed: Default::default(),
+        }
+    }
+
+    fn index_path(&self, home: &str) -> PathBuf {
+        self.directory.join(format!("{}_index.jsonl", home))
+    }
+
+    /// The latest leaf index in `home`'s index file, read from the file the
+    /// first time
+    fn last_indexed(&self, home: &str) -> Result<Option<usize>> {
+        let mut indexed = self.indexed.lock().expect("poisoned");
+        if let Some(last) = indexed.get(home) {
+            return Ok(*last);
+        }
+        let last = read_last_indexed(&self.index_path(home))?;
+        indexed.insert(home.to_owned(), last);
+        Ok(last)
+    }
+
+    fn write(&self, home: &str, proven: &ProvenMessage) -> Result<()> {
+        fs::create_dir_all(&self.directory)?;
+
+        // Write the proof under a temporary name first, so that readers
+        // never see a partial file
+        let file = format!("{}_{}.json", home, proven.proof.index);
+        let tmp = self.directory.join(format!(".{}.tmp", file));
+        fs::write(&tmp, serde_json::to_string_pretty(proven)?)?;
+        fs::rename(&tmp, self.directory.join(&file))?;
+
+        // Proofs are published in leaf index order, so anything at or below
+        // the last line is already indexed
+        if matches!(self.last_indexed(home)?, Some(last) if last >= proven.proof.index) {
+            return Ok(());
+        }
+        let entry = serde_json::json!({
+            "leafIndex": proven.proof.index,
+            "leaf": proven.proof.leaf,
+            "file": file,
+        });
+        let mut index = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.index_path(home))?;
+        writeln!(index, "{}", entry)?;
+        self.indexed
+            .lock()
+            .expect("poisoned")
+            .insert(home.to_owned(), Some(proven.proof.index));
+        Ok(())
+    }
+}
+
+/// Read the leaf index of the last line of the index file at `path`. A
+/// partial last line, left by a crash mid-write, is truncated
+fn read_last_indexed(path: &Path) -> Result<Option<usize>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    let complete = contents.rfind('\n').map_or(0, |i| i + 1);
+    if complete < contents.len() {
+        warn!(
+            path = %path.display(),
+            "Truncating partial line at the end of the proof index",
+        );
+        OpenOptions::new()
+            .write(true)
+            .open(path)?
+            .set_len(complete as u64)?;
+    }
+
+    match contents[..complete].lines().last() {
+        Some(line) => {
+            let entry: serde_json::Value = serde_json::from_str(line)?;
+            Ok(entry["leafIndex"].as_u64().map(|i| i as usize))
+        }
+        None => Ok(None),
+    }
+}
+
+#[async_trait]
+impl ProofSink for FileSink {
+    fn name(&self) -> String {
+        format!("file_{}", self.directory.display())
+    }
+
+    async fn last_published(&self, home: &str) -> Result<Option<u32>> {
+        let sink = self.clone();
+        let home = home.to_owned();
+        let last = tokio::task::spawn_blocking(move || sink.last_indexed(&home)).await??;
+        Ok(last.map(|i| i as u32))
+    }
+
+    async fn publish(&self, home: &str, proven: &ProvenMessage) -> Result<()> {
+        info!(
+            leaf = ?proven.proof.leaf,
+            leaf_index = proven.proof.index,
+            directory = %self.directory.display(),
+            "Writing proof to file",
+        );
+        let sink = self.clone();
+        let home = home.to_owned();
+        let proven = proven.clone();
+        tokio::task::spawn_blocking(move || sink.write(&home, &proven)).await?
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethers::core::types::H256;
+    use optics_core::accumulator::merkle::Proof;
+
+    fn proven(index: usize) -> ProvenMessage {
+        ProvenMessage {
+            message: vec![index as u8],
+            proof: Proof {
+                leaf: H256::repeat_byte(index as u8),
+                index,
+                path: Default::default(),
+            },
+        }
+    }
+
+    fn read_index(directory: &Path) -> Vec<serde_json::Value> {
+        fs::read_to_string(directory.join("home_index.jsonl"))
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn it_writes_proofs_and_an_index() {
+        let directory =
+            std::env::temp_dir().join(format!("optics-file-sink-{}", std::process::id()));
+        let sink = FileSink::new(directory.to_str().unwrap());
+        assert_eq!(sink.last_published("home").await.unwrap(), None);
+
+        for index in 0..2 {
+            sink.publish("home", &proven(index)).await.unwrap();
+        }
+
+        let written: ProvenMessage =
+            serde_json::from_slice(&fs::read(directory.join("home_1.json")).unwrap()).unwrap();
+        assert_eq!(written, proven(1));
+
+        let entries = read_index(&directory);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1]["leafIndex"], 1);
+        assert_eq!(entries[1]["file"], "home_1.json");
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_indexes_each_proof_once_across_restarts() {
+        let directory =
+            std::env::temp_dir().join(format!("optics-file-sink-restart-{}", std::process::id()));
+        let sink = FileSink::new(directory.to_str().unwrap());
+        for index in 0..2 {
+            sink.publish("home", &proven(index)).await.unwrap();
+        }
+
+        // a crash mid-write leaves a partial line
+        let mut index = OpenOptions::new()
+            .append(true)
+            .open(directory.join("home_index.jsonl"))
+            .unwrap();
+        write!(index, "{{\"leafIndex\":2,").unwrap();
+
+        // the proof at 1 was published again after a restart
+        let sink = FileSink::new(directory.to_str().unwrap());
+        assert_eq!(sink.last_published("home").await.unwrap(), Some(1));
+        for index in 1..3 {
+            sink.publish("home", &proven(index)).await.unwrap();
+        }
+
+        let leaf_indices: Vec<_> = read_index(&directory)
+            .iter()
+            .map(|entry| entry["leafIndex"].as_u64().unwrap())
+            .collect();
+        assert_eq!(leaf_indices, vec![0, 1, 2]);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,196 @@
+//! Publishing proofs to where self-relayers can find them
+use async_trait::async_trait;
+use color_eyre::eyre::{eyre, Result};
+use std::time::Duration;
+use tokio::{task::JoinHandle, time::sleep};
+use tracing::{debug, info, info_span, instrument::Instrumented, warn, Instrument};
+
+use optics_core::{accumulator::merkle::Proof, db::HomeDB, Encode};
+
+mod file;
+mod s3;
+mod webhook;
+
+pub(crate) use file::FileSink;
+pub(crate) use s3::S3Sink;
+pub(crate) use webhook::WebhookSink;
+
+/// A message and a proof of it, as published to sinks and served by the API
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub(crate) struct ProvenMessage {
+    /// The encoded `RawCommittedMessage`
+    pub(crate) message: Vec<u8>,
+    /// The latest proof of the message
+    pub(crate) proof: Proof,
+}
+
+/// Somewhere to publish proofs
+#[async_trait]
+pub(crate) trait ProofSink: std::fmt::Debug + Send + Sync {
+    /// Identifies the sink and its destination. Publishing progress is
+    /// tracked in the DB under this name, so it must change if the
+    /// destination does
+    fn name(&self) -> String;
+
+    /// The latest leaf index the sink already holds a proof for, if it can
+    /// tell. Consulted only when the DB has no record of publishing to the
+    /// sink, e.g. for sinks filled before the DB kept one
+    async fn last_published(&self, _home: &str) -> Result<Option<u32>> {
+        Ok(None)
+    }
+
+    /// Publish the proof of a message from the home named `home`. Publishing
+    /// the same proof twice must be harmless
+    async fn publish(&self, home: &str, proven: &ProvenMessage) -> Result<()>;
+}
+
+/// Publishes every proof in the DB to a sink, in leaf index order
+#[derive(Debug)]
+pub(crate) struct Pusher {
+    home: String,
+    sink: Box<dyn ProofSink>,
+    db: HomeDB,
+    interval: u64,
+}
+
+impl Pusher {
+    /// Instantiate a new pusher for the home named `home`. Failed publishes
+    /// are retried every `interval` seconds
+    pub(crate) fn new(home: &str, sink: Box<dyn ProofSink>, db: HomeDB, interval: u64) -> Self {
+        Self {
+            home: home.to_owned(),
+            sink,
+            db,
+            interval,
+        }
+    }
+
+    /// Spawn the pusher task and return a joinhandle
+    ///
+    /// The pusher task waits on the DB for new proofs and publishes them to
+    /// the sink, recording the latest published leaf index in the DB so that
+    /// it picks up where it left off after a restart
+    pub(crate) fn spawn(self) -> Instrumented<JoinHandle<Result<()>>> {
+        let span = info_span!("ProofPusher", sink = %self.sink.name(), home = %self.home);
+        tokio::spawn(async move {
+            let name = self.sink.name();
+            let pushed = match self.db.retrieve_pushed_leaf_index(&name)? {
+                Some(pushed) => Some(pushed),
+                None => {
+                    let pushed = self.sink.last_published(&self.home).await?;
+                    if let Some(pushed) = pushed {
+                        info!(
+                            leaf_index = pushed,
+                            "Sink already holds proofs up to leaf index {}", pushed
+                        );
+                        self.db.store_pushed_leaf_index(&name, pushed)?;
+                    }
+                    pushed
+                }
+            };
+            let mut index = pushed.map(|i| i + 1).unwrap_or_default();
+            info!(index, "Publishing proofs from leaf index {}", index);
+
+            loop {
+                let proof = self.db.wait_for_proof(index).await?;
+                let message = self
+                    .db
+                    .message_by_leaf_index(index)?
+                    .ok_or_else(|| eyre!("Missing message for known proof"))?;
+                let proven = ProvenMessage {
+                    proof,
+                    message: message.to_vec(),
+                };
+
+                while let Err(e) = self.sink.publish(&self.home, &proven).await {
+                    warn!(
+                        leaf_index = index,
+                        error = %e,
+                        "Failed to publish proof. Retrying in {} seconds",
+                        self.interval
+                    );
+                    sleep(Duration::from_secs(self.interval)).await;
+                }
+                debug!(leaf = ?proven.proof.leaf, leaf_index = index, "Published proof");
+                self.db.store_pushed_leaf_index(&name, index)?;
+
+                index += 1;
+            }
+        })
+        .instrument(span)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use optics_test::test_utils;
+    use std::sync::{Arc, Mutex};
+
+    /// Records the leaf indices of the proofs published to it
+    #[derive(Debug)]
+    struct RecordingSink {
+        name: String,
+        last_published: Option<u32>,
+        published: Arc<Mutex<Vec<usize>>>,
+    }
+
+    #[async_trait]
+    impl ProofSink for RecordingSink {
+        fn name(&self) -> String {
+            self.name.clone()
+        }
+
+        async fn last_published(&self, _home: &str) -> Result<Option<u32>> {
+            Ok(self.last_published)
+        }
+
+        async fn publish(&self, _home: &str, proven: &ProvenMessage) -> Result<()> {
+            self.published.lock().unwrap().push(proven.proof.index);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn it_resumes_after_the_last_pushed_proof() {
+        test_utils::run_test_db(|db| async move {
+            let db = HomeDB::new(db, "home_1".to_owned());
+            let mut tree = db.merkle().unwrap();
+            for leaf_index in 0..4 {
+                let message = test_utils::raw_message(leaf_index);
+                db.store_raw_committed_message(&message).unwrap();
+                tree.ingest(message.leaf()).unwrap();
+            }
+            for leaf_index in 0..4 {
+                db.store_proof(leaf_index, &tree.prove(leaf_index as usize).unwrap())
+                    .unwrap();
+            }
+
+            // progress recorded in the DB, and progress only the sink knows
+            // of, from before the DB kept a record
+            db.store_pushed_leaf_index("recorded", 1).unwrap();
+            let sinks = [("recorded", None), ("unrecorded", Some(1))];
+
+            for (name, last_published) in sinks.iter() {
+                let published: Arc<Mutex<Vec<usize>>> = Default::default();
+                let sink = RecordingSink {
+                    name: name.to_string(),
+                    last_published: *last_published,
+                    published: published.clone(),
+                };
+                let task = Pusher::new("home_1", Box::new(sink), db.clone(), 1).spawn();
+                for _ in 0..100 {
+                    if published.lock().unwrap().len() >= 2 {
+                        break;
+                    }
+                    sleep(Duration::from_millis(10)).await;
+                }
+                task.into_inner().abort();
+
+                assert_eq!(*published.lock().unwrap(), vec![2, 3]);
+                assert_eq!(db.retrieve_pushed_leaf_index(name).unwrap(), Some(3));
+            }
+        })
+        .await
+    }
+}
@@ -0,0 +1,113 @@
+use async_trait::async_trait;
+use color_eyre::eyre::{bail, Result};
+use rusoto_core::{credential::EnvironmentProvider, HttpClient, Region, RusotoError};
+use rusoto_s3::{GetObjectError, GetObjectRequest, PutObjectRequest, S3Client, S3};
+use tracing::{debug, info};
+
+use super::{ProofSink, ProvenMessage};
+
+/// Uploads proofs to an S3 bucket, keyed `<home>_<leaf index>`
+pub(crate) struct S3Sink {
+    bucket: String,
+    region: Region,
+    client: S3Client,
+}
+
+impl std::fmt::Debug for S3Sink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("S3Sink")
+            .field("region", &self.region)
+            .field("bucket", &self.bucket)
+            .finish()
+    }
+}
+
+impl S3Sink {
+    /// Instantiate a new sink for `bucket` in `region`
+    pub(crate) fn new(bucket: &str, region: Region) -> Self {
+        let client = S3Client::new_with(
+            HttpClient::new().unwrap(),
+            EnvironmentProvider::default(),
+            region.clone(),
+        );
+        Self {
+            bucket: bucket.to_owned(),
+            region,
+            client,
+        }
+    }
+
+    fn key(home: &str, leaf_index: u64) -> String {
+        format!("{}_{}", home, leaf_index)
+    }
+
+    async fn exists(&self, key: String) -> Result<bool> {
+        let req = GetObjectRequest {
+            key,
+            bucket: self.bucket.clone(),
+            ..Default::default()
+        };
+        match self.client.get_object(req).await {
+            Ok(_) => Ok(true),
+            Err(RusotoError::Service(GetObjectError::NoSuchKey(_))) => Ok(false),
+            Err(e) => bail!(e),
+        }
+    }
+}
+
+#[async_trait]
+impl ProofSink for S3Sink {
+    fn name(&self) -> String {
+        format!("s3_{}_{}", self.region.name(), self.bucket)
+    }
+
+    /// Earlier processors uploaded every proof in leaf index order without
+    /// recording their progress, so the bucket holds a prefix of the proofs.
+    /// Find its end by doubling, then bisecting
+    async fn last_published(&self, home: &str) -> Result<Option<u32>> {
+        if !self.exists(Self::key(home, 0)).await? {
+            return Ok(None);
+        }
+        // `low` is uploaded, `high` is not
+        let mut low = 0u64;
+        let mut high = 1u64;
+        while high <= u32::MAX as u64 && self.exists(Self::key(home, high)).await? {
+            low = high;
+            high *= 2;
+        }
+        while high - low > 1 {
+            let mid = low + (high - low) / 2;
+            if self.exists(Self::key(home, mid)).await? {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        debug!(
+            bucket = %self.bucket,
+            leaf_index = low,
+            "Found proofs uploaded to the bucket"
+        );
+        Ok(Some(low as u32))
+    }
+
+    async fn publish(&self, home: &str, proven: &ProvenMessage) -> Result<()> {
+        let key = Self::key(home, proven.proof.index as u64);
+        let proof_json = Vec::from(serde_json::to_string_pretty(proven)?);
+        info!(
+            leaf = ?proven.proof.leaf,
+            leaf_index = proven.proof.index,
+            key = %key,
+            "Storing proof in s3 bucket",
+        );
+        let req = PutObjectRequest {
+            key,
+            bucket: self.bucket.clone(),
+            body: Some(proof_json.into()),
+            content_type: Some("application/json".to_owned()),
+            ..Default::default()
+        };
+        self.client.put_object(req).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,131 @@
+use async_trait::async_trait;
+use color_eyre::eyre::{bail, Result};
+use reqwest::{header::CONTENT_TYPE, Client};
+use std::time::Duration;
+use tracing::info;
+
+use super::{ProofSink, ProvenMessage};
+
+/// POSTs each proof as JSON to a URL. The home's name is sent in the
+/// `X-Optics-Home` header. Any status other than 2xx is a failure, and the
+/// proof is sent again. So is a proof the endpoint doesn't answer within
+/// the timeout
+#[derive(Debug, Clone)]
+pub(crate) struct WebhookSink {
+    url: String,
+    client: Client,
+}
+
+impl WebhookSink {
+    /// Instantiate a new sink posting to `url`, giving up on each request
+    /// after `timeout`
+    pub(crate) fn new(url: &str, timeout: Duration) -> Self {
+        Self {
+            url: url.to_owned(),
+            client: Client::builder()
+                .timeout(timeout)
+                .build()
+                .expect("failed to build http client"),
+        }
+    }
+}
+
+#[async_trait]
+impl ProofSink for WebhookSink {
+    fn name(&self) -> String {
+        format!("webhook_{}", self.url)
+    }
+
+    async fn publish(&self, home: &str, proven: &ProvenMessage) -> Result<()> {
+        info!(
+            leaf = ?proven.proof.leaf,
+            leaf_index = proven.proof.index,
+            url = %self.url,
+            "Posting proof to webhook",
+        );
+        let resp = self
+            .client
+            .post(&self.url)
+            .header(CONTENT_TYPE, "application/json")
+            .header("X-Optics-Home", home)
+            .body(serde_json::to_vec(proven)?)
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            bail!("Webhook responded with {}", resp.status());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethers::core::types::H256;
+    use optics_core::accumulator::merkle::Proof;
+    use std::{
+        net::SocketAddr,
+        sync::{Arc, Mutex},
+    };
+    use warp::{http::StatusCode, hyper::body::Bytes, Filter};
+
+    fn proven() -> ProvenMessage {
+        ProvenMessage {
+            message: vec![1, 2, 3],
+            proof: Proof {
+                leaf: H256::repeat_byte(1),
+                index: 7,
+                path: Default::default(),
+            },
+        }
+    }
+
+    /// Serve an endpoint that records what is posted to it, then responds
+    /// with `status` after `delay`
+    fn serve(
+        status: StatusCode,
+        delay: Duration,
+    ) -> (SocketAddr, Arc<Mutex<Vec<(String, ProvenMessage)>>>) {
+        let received: Arc<Mutex<Vec<(String, ProvenMessage)>>> = Default::default();
+        let recorded = received.clone();
+        let route = warp::post()
+            .and(warp::header::<String>("x-optics-home"))
+            .and(warp::body::bytes())
+            .and_then(move |home: String, body: Bytes| {
+                let recorded = recorded.clone();
+                async move {
+                    let proven = serde_json::from_slice(&body).unwrap();
+                    recorded.lock().unwrap().push((home, proven));
+                    tokio::time::sleep(delay).await;
+                    Ok::<_, warp::Rejection>(warp::reply::with_status("", status))
+                }
+            });
+        let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        (addr, received)
+    }
+
+    #[tokio::test]
+    async fn it_posts_proofs() {
+        let (addr, received) = serve(StatusCode::OK, Duration::from_secs(0));
+        let sink = WebhookSink::new(&format!("http://{}/proofs", addr), Duration::from_secs(5));
+
+        sink.publish("home", &proven()).await.unwrap();
+        assert_eq!(
+            *received.lock().unwrap(),
+            vec![("home".to_owned(), proven())]
+        );
+    }
+
+    #[tokio::test]
+    async fn it_fails_on_error_statuses_and_timeouts() {
+        let (addr, _) = serve(StatusCode::INTERNAL_SERVER_ERROR, Duration::from_secs(0));
+        let sink = WebhookSink::new(&format!("http://{}", addr), Duration::from_secs(5));
+        assert!(sink.publish("home", &proven()).await.is_err());
+
+        let (addr, received) = serve(StatusCode::OK, Duration::from_secs(5));
+        let sink = WebhookSink::new(&format!("http://{}", addr), Duration::from_millis(100));
+        assert!(sink.publish("home", &proven()).await.is_err());
+        assert_eq!(received.lock().unwrap().len(), 1);
+    }
+}
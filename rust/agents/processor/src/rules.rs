@@ -0,0 +1,450 @@
+//! Rules deciding which messages the processor processes
+//!
+//! Rules are evaluated in order, and the first rule matching a message
+//! decides its action. Messages matching no rule get the default action.
+//!
+//! ```json
+//! "rules": {
+//!   "default": "allow",
+//!   "rules": [
+//!     { "name": "spam", "action": "deny", "sender": ["0x..."] },
+//!     {
+//!       "name": "large-transfers",
+//!       "action": "defer",
+//!       "bridge": {
+//!         "routers": { "1000": "0x..." },
+//!         "tokenDomain": [1000],
+//!         "minAmount": "0x3635c9adc5dea00000"
+//!       }
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Denied messages are not looked at again unless replayed with
+//! `optics-cli skipped replay`.
+use color_eyre::Result;
+use ethers::prelude::{H256, U256};
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::{task::JoinHandle, time::sleep};
+use tracing::{info, info_span, instrument::Instrumented, warn, Instrument};
+
+use optics_core::{
+    xapps::bridge::{BridgeAction, BridgeMessage},
+    Decode, OpticsMessageView,
+};
+
+use crate::settings::ProcessorSettings;
+
+/// The name reported for messages matching no rule
+pub(crate) const DEFAULT_RULE: &str = "default";
+
+/// What the processor does with a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum RuleAction {
+    /// Process the message
+    Allow,
+    /// Skip the message, unless it is replayed by hand
+    Deny,
+    /// Skip the message until the rules change to allow it
+    Defer,
+}
+
+impl Default for RuleAction {
+    fn default() -> Self {
+        RuleAction::Allow
+    }
+}
+
+/// Conditions on the decoded body of a bridge message. Only messages sent by
+/// the BridgeRouter of their origin domain match, so that a message that just
+/// looks like a bridge message can't pass for one
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BridgeMatcher {
+    /// The BridgeRouter on each origin domain
+    routers: HashMap<u32, H256>,
+    /// Home domains of the token
+    token_domain: Option<HashSet<u32>>,
+    /// Addresses of the token on its home domain
+    token_id: Option<HashSet<H256>>,
+    /// Minimum transfer amount. Only transfers match
+    min_amount: Option<U256>,
+    /// Maximum transfer amount. Only transfers match
+    max_amount: Option<U256>,
+}
+
+impl BridgeMatcher {
+    fn matches(&self, message: &OpticsMessageView<'_>) -> bool {
+        if self.routers.get(&message.origin()) != Some(&message.sender()) {
+            return false;
+        }
+        let message = match BridgeMessage::read_from(&mut message.body()) {
+            Ok(message) => message,
+            Err(_) => return false,
+        };
+
+        if let Some(domains) = &self.token_domain {
+            if !domains.contains(&message.token_id.domain) {
+                return false;
+            }
+        }
+        if let Some(ids) = &self.token_id {
+            if !ids.contains(&message.token_id.id) {
+                return false;
+            }
+        }
+
+        if self.min_amount.is_none() && self.max_amount.is_none() {
+            return true;
+        }
+        match message.action {
+            BridgeAction::Transfer { amount, .. } => {
+                self.min_amount.map_or(true, |min| amount >= min)
+                    && self.max_amount.map_or(true, |max| amount <= max)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Conditions on a message. A message matches if it meets every condition
+/// that is set
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct MessageMatcher {
+    /// Message senders
+    sender: Option<HashSet<H256>>,
+    /// Message recipients
+    recipient: Option<HashSet<H256>>,
+    /// Destination domains
+    destination: Option<HashSet<u32>>,
+    /// Minimum body length in bytes
+    min_body_len: Option<usize>,
+    /// Maximum body length in bytes
+    max_body_len: Option<usize>,
+    /// Conditions on the decoded bridge message
+    bridge: Option<BridgeMatcher>,
+}
+
+impl MessageMatcher {
+    fn matches(&self, message: &OpticsMessageView<'_>) -> bool {
+        let body = message.body();
+        self.sender
+            .as_ref()
+            .map_or(true, |senders| senders.contains(&message.sender()))
+            && self
+                .recipient
+                .as_ref()
+                .map_or(true, |recipients| recipients.contains(&message.recipient()))
+            && self.destination.as_ref().map_or(true, |destinations| {
+                destinations.contains(&message.destination())
+            })
+            && self.min_body_len.map_or(true, |min| body.len() >= min)
+            && self.max_body_len.map_or(true, |max| body.len() <= max)
+            && self
+                .bridge
+                .as_ref()
+                .map_or(true, |bridge| bridge.matches(message))
+    }
+}
+
+/// A named rule
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub(crate) struct Rule {
+    /// The name recorded for messages the rule skips
+    pub(crate) name: String,
+    /// What to do with matching messages
+    pub(crate) action: RuleAction,
+    /// Apply the rule to the messages that do not match its conditions
+    #[serde(default)]
+    pub(crate) negate: bool,
+    /// The messages the rule applies to
+    #[serde(flatten)]
+    pub(crate) matcher: MessageMatcher,
+}
+
+/// An ordered list of rules, and the action for messages matching none
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub(crate) struct RuleSet {
+    #[serde(default)]
+    rules: Vec<Rule>,
+    #[serde(default)]
+    default: RuleAction,
+}
+
+/// The action for a message, and the name of the rule that decided it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Verdict<'a> {
+    pub(crate) action: RuleAction,
+    pub(crate) rule: &'a str,
+}
+
+impl RuleSet {
+    /// Build a rule set from the configured rules, and the older sender
+    /// allow and deny lists. The lists are checked before the rules: senders
+    /// on the deny list are denied, and if there is an allow list, senders
+    /// not on it are denied.
+    pub(crate) fn from_settings(
+        rules: Option<RuleSet>,
+        allowed: Option<HashSet<H256>>,
+        denied: Option<HashSet<H256>>,
+    ) -> Self {
+        let mut lists = vec![];
+        if let Some(denied) = denied {
+            lists.push(Rule {
+                name: "denied".to_owned(),
+                action: RuleAction::Deny,
+                negate: false,
+                matcher: MessageMatcher {
+                    sender: Some(denied),
+                    ..Default::default()
+                },
+            });
+        }
+        if let Some(allowed) = allowed {
+            lists.push(Rule {
+                name: "allowed".to_owned(),
+                action: RuleAction::Deny,
+                negate: true,
+                matcher: MessageMatcher {
+                    sender: Some(allowed),
+                    ..Default::default()
+                },
+            });
+        }
+
+        let mut rules = rules.unwrap_or_default();
+        lists.append(&mut rules.rules);
+        Self {
+            rules: lists,
+            default: rules.default,
+        }
+    }
+
+    /// Decide what to do with a message
+    pub(crate) fn evaluate(&self, message: &OpticsMessageView<'_>) -> Verdict<'_> {
+        self.rules
+            .iter()
+            .find(|rule| rule.matcher.matches(message) != rule.negate)
+            .map(|rule| Verdict {
+                action: rule.action,
+                rule: &rule.name,
+            })
+            .unwrap_or(Verdict {
+                action: self.default,
+                rule: DEFAULT_RULE,
+            })
+    }
+}
+
+/// The current rules, shared by every replica task and replaced when the
+/// config is reloaded
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SharedRules(Arc<RwLock<Arc<RuleSet>>>);
+
+impl SharedRules {
+    /// Share `rules`
+    pub(crate) fn new(rules: RuleSet) -> Self {
+        Self(Arc::new(RwLock::new(Arc::new(rules))))
+    }
+
+    /// The current rules. Compare with `Arc::ptr_eq` to tell if they were
+    /// replaced
+    pub(crate) fn current(&self) -> Arc<RuleSet> {
+        self.0.read().expect("poisoned").clone()
+    }
+
+    /// Replace the rules, if they differ from the current rules. Returns
+    /// true if they were replaced
+    pub(crate) fn replace(&self, rules: RuleSet) -> bool {
+        let mut current = self.0.write().expect("poisoned");
+        if **current == rules {
+            return false;
+        }
+        *current = Arc::new(rules);
+        true
+    }
+}
+
+/// Re-reads the processor config on an interval, and replaces the shared
+/// rules when they change
+#[derive(Debug)]
+pub(crate) struct RuleReloader {
+    rules: SharedRules,
+    interval: u64,
+}
+
+impl RuleReloader {
+    /// Instantiate a new reloader
+    pub(crate) fn new(rules: SharedRules, interval: u64) -> Self {
+        Self { rules, interval }
+    }
+
+    /// Spawn the task re-reading the config
+    pub(crate) fn spawn(self) -> Instrumented<JoinHandle<Result<()>>> {
+        tokio::spawn(async move {
+            loop {
+                sleep(Duration::from_secs(self.interval)).await;
+
+                // A bad edit should not take the processor down, so keep the
+                // current rules until the config parses again
+                match ProcessorSettings::new() {
+                    Ok(settings) => {
+                        let rules = RuleSet::from_settings(
+                            settings.rules,
+                            settings.allowed,
+                            settings.denied,
+                        );
+                        if self.rules.replace(rules) {
+                            info!("Reloaded processor rules");
+                        }
+                    }
+                    Err(e) => warn!(
+                        error = %e,
+                        "Failed to reload processor config. Keeping the current rules"
+                    ),
+                }
+            }
+        })
+        .instrument(info_span!("RuleReloader"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use optics_core::{
+        xapps::bridge::{BridgeAction, TokenId},
+        Encode, OpticsMessage,
+    };
+
+    fn message_with_body(sender: u64, body: Vec<u8>) -> Vec<u8> {
+        OpticsMessage {
+            origin: 1000,
+            sender: H256::from_low_u64_be(sender),
+            nonce: 0,
+            destination: 2000,
+            recipient: H256::from_low_u64_be(5),
+            body,
+        }
+        .to_vec()
+    }
+
+    fn transfer(domain: u32, amount: u64) -> Vec<u8> {
+        BridgeMessage {
+            token_id: TokenId {
+                domain,
+                id: H256::repeat_byte(0x11),
+            },
+            action: BridgeAction::Transfer {
+                recipient: H256::repeat_byte(0x22),
+                amount: amount.into(),
+            },
+        }
+        .to_vec()
+    }
+
+    fn evaluate(rules: &RuleSet, message: &[u8]) -> (RuleAction, String) {
+        let verdict = rules.evaluate(&OpticsMessageView::new(message).unwrap());
+        (verdict.action, verdict.rule.to_owned())
+    }
+
+    #[test]
+    fn it_applies_the_first_matching_rule() {
+        let routers = serde_json::json!({ "1000": H256::from_low_u64_be(1) });
+        let rules: RuleSet = serde_json::from_value(serde_json::json!({
+            "default": "deny",
+            "rules": [
+                {
+                    "name": "large-transfers",
+                    "action": "defer",
+                    "bridge": { "routers": routers, "tokenDomain": [1000], "minAmount": "0x3e8" }
+                },
+                { "name": "bridge", "action": "allow", "bridge": { "routers": routers } },
+                { "name": "short", "action": "allow", "maxBodyLen": 4 },
+            ]
+        }))
+        .unwrap();
+
+        let large = message_with_body(1, transfer(1000, 1000));
+        assert_eq!(
+            evaluate(&rules, &large),
+            (RuleAction::Defer, "large-transfers".to_owned())
+        );
+
+        let small = message_with_body(1, transfer(1000, 999));
+        assert_eq!(
+            evaluate(&rules, &small),
+            (RuleAction::Allow, "bridge".to_owned())
+        );
+
+        let other_domain = message_with_body(1, transfer(3000, 1000));
+        assert_eq!(
+            evaluate(&rules, &other_domain),
+            (RuleAction::Allow, "bridge".to_owned())
+        );
+
+        // not sent by the origin's BridgeRouter
+        let impostor = message_with_body(2, transfer(1000, 1000));
+        assert_eq!(
+            evaluate(&rules, &impostor),
+            (RuleAction::Deny, DEFAULT_RULE.to_owned())
+        );
+
+        let short = message_with_body(1, vec![1, 2, 3, 4]);
+        assert_eq!(
+            evaluate(&rules, &short),
+            (RuleAction::Allow, "short".to_owned())
+        );
+
+        let long = message_with_body(1, vec![0; 5]);
+        assert_eq!(
+            evaluate(&rules, &long),
+            (RuleAction::Deny, DEFAULT_RULE.to_owned())
+        );
+    }
+
+    #[test]
+    fn it_checks_sender_lists_before_rules() {
+        let rules: RuleSet = serde_json::from_value(serde_json::json!({
+            "rules": [{ "name": "defer-all", "action": "defer", "minBodyLen": 0 }]
+        }))
+        .unwrap();
+        let allowed = [1, 2].iter().copied().map(H256::from_low_u64_be).collect();
+        let denied = [2].iter().copied().map(H256::from_low_u64_be).collect();
+        let rules = RuleSet::from_settings(Some(rules), Some(allowed), Some(denied));
+
+        assert_eq!(
+            evaluate(&rules, &message_with_body(1, vec![])),
+            (RuleAction::Defer, "defer-all".to_owned())
+        );
+        assert_eq!(
+            evaluate(&rules, &message_with_body(2, vec![])),
+            (RuleAction::Deny, "denied".to_owned())
+        );
+        assert_eq!(
+            evaluate(&rules, &message_with_body(3, vec![])),
+            (RuleAction::Deny, "allowed".to_owned())
+        );
+    }
+
+    #[test]
+    fn it_replaces_shared_rules_only_when_they_change() {
+        let shared = SharedRules::default();
+        let before = shared.current();
+
+        assert!(!shared.replace(RuleSet::default()));
+        assert!(Arc::ptr_eq(&before, &shared.current()));
+
+        let denied = [1].iter().copied().map(H256::from_low_u64_be).collect();
+        assert!(shared.replace(RuleSet::from_settings(None, None, Some(denied))));
+        assert!(!Arc::ptr_eq(&before, &shared.current()));
+    }
+}
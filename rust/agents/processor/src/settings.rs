@@ -1,25 +1,121 @@
 //! Configuration
 use ethers::prelude::H256;
 use serde::Deserialize;
-use std::collections::HashSet;
+use std::{collections::HashSet, time::Duration};
 
 use optics_base::decl_settings;
 
+use crate::{
+    budget::Budgets,
+    pipeline::RetryPolicy,
+    push::{FileSink, ProofSink, S3Sink, WebhookSink},
+    rules::RuleSet,
+};
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct S3Config {
     pub bucket: String,
     pub region: String,
 }
 
+/// Seconds to wait for a webhook sink's endpoint to respond, by default
+const DEFAULT_WEBHOOK_TIMEOUT: u64 = 30;
+
+/// A place to publish proofs to
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SinkConfig {
+    /// An amazon aws s3 bucket
+    S3(S3Config),
+    /// A local directory
+    File {
+        /// Path to the directory
+        directory: String,
+    },
+    /// An HTTP endpoint to POST proofs to
+    Webhook {
+        /// The endpoint's URL
+        url: String,
+        /// Seconds to wait for the endpoint to respond. Defaults to 30
+        timeout: Option<String>,
+    },
+}
+
+impl SinkConfig {
+    /// Instantiate the sink
+    pub(crate) fn sink(&self) -> Box<dyn ProofSink> {
+        match self {
+            SinkConfig::S3(config) => Box::new(S3Sink::new(
+                &config.bucket,
+                config.region.parse().expect("invalid s3 region"),
+            )),
+            SinkConfig::File { directory } => Box::new(FileSink::new(directory)),
+            SinkConfig::Webhook { url, timeout } => Box::new(WebhookSink::new(
+                url,
+                Duration::from_secs(
+                    timeout
+                        .as_ref()
+                        .map(|t| t.parse().expect("invalid integer"))
+                        .unwrap_or(DEFAULT_WEBHOOK_TIMEOUT),
+                ),
+            )),
+        }
+    }
+}
+
+/// Dead-letter queue retry settings
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RetrySettings {
+    /// Seconds to wait before the first retry. Doubles with each attempt
+    base_delay: Option<String>,
+    /// Cap on the wait between retries, in seconds
+    max_delay: Option<String>,
+    /// Stop retrying after this many failed attempts
+    max_attempts: Option<String>,
+}
+
+impl RetrySettings {
+    /// Get the `RetryPolicy` for these settings
+    pub(crate) fn policy(&self) -> RetryPolicy {
+        let default = RetryPolicy::default();
+        let parse = |s: &Option<String>| s.as_ref().map(|s| s.parse().expect("invalid integer"));
+        RetryPolicy {
+            base_delay: parse(&self.base_delay).unwrap_or(default.base_delay),
+            max_delay: parse(&self.max_delay).unwrap_or(default.max_delay),
+            max_attempts: self
+                .max_attempts
+                .as_ref()
+                .map(|s| s.parse().expect("invalid integer")),
+        }
+    }
+}
+
 decl_settings!(Processor {
     /// The polling interval (in seconds)
     interval: String,
-    /// An allow list of message senders
+    /// The most messages per replica to process at once. Defaults to 8
+    window: Option<String>,
+    /// When to retry messages in the dead-letter queue
+    retry: Option<RetrySettings>,
+    /// An allow list of message senders. Checked before `rules`
     allowed: Option<HashSet<H256>>,
-    /// A deny list of message senders
+    /// A deny list of message senders. Checked before `rules`
     denied: Option<HashSet<H256>>,
+    /// Rules deciding which messages to process. Re-read from the config
+    /// every `interval`
+    rules: Option<RuleSet>,
+    /// Gas budgets for message senders and recipients. Messages from or to
+    /// accounts over budget are deferred until the budget refills
+    budgets: Option<Budgets>,
     /// Only index transactions if this key is set
     indexon: Option<String>,
     /// An amazon aws s3 bucket to push proofs to
     s3: Option<S3Config>,
+    /// Other places to publish proofs to, e.g.
+    /// `[{ "type": "file", "directory": "./proofs" }]`
+    sinks: Option<Vec<SinkConfig>>,
+    /// Port to serve proofs and message status over HTTP on. Not served if
+    /// unset
+    api_port: Option<String>,
 });
@@ -1,7 +1,8 @@
 //! The relayer forwards signed updates from the home to chain to replicas
 //!
-//! At a regular interval, the relayer polls Home for signed updates and
-//! submits them as updates with a pending timelock on the replica.
+//! At a regular interval, the relayer walks the signed updates indexed from
+//! Home, starting at each replica's committed root, and submits them
+//! back-to-back as updates with a pending timelock on the replica.
 
 #![forbid(unsafe_code)]
 #![warn(missing_docs)]
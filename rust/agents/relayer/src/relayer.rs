@@ -1,20 +1,75 @@
 use async_trait::async_trait;
 use color_eyre::{eyre::bail, Result};
-use std::{sync::Arc, time::Duration};
-use tokio::{sync::Mutex, task::JoinHandle, time::sleep};
-use tracing::{info, instrument::Instrumented, Instrument};
+use ethers::core::types::H256;
+use prometheus::{Histogram, HistogramVec, IntGauge, IntGaugeVec};
+use std::{
+    collections::HashSet,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{task::JoinHandle, time::sleep};
+use tracing::{info, instrument::Instrumented, warn, Instrument};
 
 use optics_base::{AgentCore, Homes, OpticsAgent, Replicas};
-use optics_core::Common;
+use optics_core::{
+    db::{HomeDB, RelayedUpdate, ReplicaDB},
+    Common, Replica, SignedUpdate, TxOutcome,
+};
 
 use crate::settings::RelayerSettings as Settings;
 
+const AGENT_NAME: &str = "relayer";
+
+/// The default number of updates to submit to a replica before re-reading
+/// its root
+const DEFAULT_BATCH_SIZE: usize = 16;
+
+/// Seconds after first seeing an update to stop waiting for its root to be
+/// acceptable on the replica, e.g. if the replica never took the update
+const MAX_TRACKED_AGE: u64 = 2 * 24 * 60 * 60;
+
+/// Seconds since the unix epoch
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time before unix epoch")
+        .as_secs()
+}
+
+/// The chain of signed updates in `home_db` building off of `root`, oldest
+/// first.
+///
+/// The replica only accepts an update building off of its committed root,
+/// and the updater signs each pair of roots, so every update in the chain
+/// must be submitted in turn. None can be skipped.
+fn update_chain(home_db: &HomeDB, root: H256) -> Result<Vec<SignedUpdate>> {
+    let mut chain = vec![];
+    let mut root = root;
+    while let Some(update) = home_db.update_by_previous_root(root)? {
+        root = update.update.new_root;
+        chain.push(update);
+    }
+    Ok(chain)
+}
+
+/// A replica's relay metrics
+#[derive(Debug)]
+struct RelayMetrics {
+    lag_updates: IntGauge,
+    lag_seconds: IntGauge,
+    relay_latency: Histogram,
+    confirmation_latency: Histogram,
+}
+
 #[derive(Debug)]
 struct UpdatePoller {
     duration: Duration,
+    batch_size: usize,
     home: Arc<Homes>,
     replica: Arc<Replicas>,
-    semaphore: Mutex<()>,
+    home_db: HomeDB,
+    replica_db: ReplicaDB,
+    metrics: RelayMetrics,
 }
 
 impl std::fmt::Display for UpdatePoller {
@@ -28,17 +83,147 @@ impl std::fmt::Display for UpdatePoller {
 }
 
 impl UpdatePoller {
-    fn new(home: Arc<Homes>, replica: Arc<Replicas>, duration: u64) -> Self {
+    fn new(
+        home: Arc<Homes>,
+        replica: Arc<Replicas>,
+        home_db: HomeDB,
+        replica_db: ReplicaDB,
+        duration: u64,
+        batch_size: usize,
+        metrics: RelayMetrics,
+    ) -> Self {
         Self {
             home,
             replica,
+            home_db,
+            replica_db,
             duration: Duration::from_secs(duration),
-            semaphore: Mutex::new(()),
+            batch_size,
+            metrics,
+        }
+    }
+
+    /// Report how many updates the replica is missing, and how long ago the
+    /// oldest was included on the home. Updates indexed before the home DB
+    /// stored block times are timed from when the relayer first saw them
+    fn record_lag(&self, chain: &[SignedUpdate]) -> Result<()> {
+        let behind_since = match chain.first() {
+            Some(oldest) => {
+                let new_root = oldest.update.new_root;
+                match self.home_db.update_timestamp(new_root)? {
+                    Some(timestamp) => Some(timestamp),
+                    None => self
+                        .replica_db
+                        .relayed_update(new_root)?
+                        .map(|relayed| relayed.seen_at),
+                }
+            }
+            None => None,
+        };
+        self.metrics.lag_updates.set(chain.len() as i64);
+        self.metrics
+            .lag_seconds
+            .set(behind_since.map_or(0, |since| now().saturating_sub(since) as i64));
+        Ok(())
+    }
+
+    /// Start timing the updates the replica is missing, from when they are
+    /// first seen
+    fn track_seen(&self, chain: &[SignedUpdate]) -> Result<()> {
+        let now = now();
+        for update in chain {
+            let new_root = update.update.new_root;
+            if self.replica_db.relayed_update(new_root)?.is_none() {
+                self.replica_db.store_relayed_update(
+                    new_root,
+                    &RelayedUpdate {
+                        seen_at: now,
+                        submitted_at: None,
+                        submission_block: None,
+                    },
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Record the relayer's submission of an update at `submitted_at`,
+    /// once it is included
+    fn track_submitted(
+        &self,
+        update: &SignedUpdate,
+        submitted_at: u64,
+        outcome: &TxOutcome,
+    ) -> Result<()> {
+        let new_root = update.update.new_root;
+        let now = now();
+        let seen_at = self
+            .replica_db
+            .relayed_update(new_root)?
+            .map_or(submitted_at, |relayed| relayed.seen_at);
+        self.replica_db.store_relayed_update(
+            new_root,
+            &RelayedUpdate {
+                seen_at,
+                submitted_at: Some(submitted_at),
+                submission_block: outcome.block_number,
+            },
+        )?;
+        self.metrics
+            .relay_latency
+            .observe(now.saturating_sub(seen_at) as f64);
+        Ok(())
+    }
+
+    /// Check whether the relayed updates the replica has are acceptable yet.
+    /// Once one is, report how long it took from submission, and stop
+    /// tracking it. The latency is only as precise as the polling interval.
+    /// Updates not acceptable within `MAX_TRACKED_AGE` of being seen are no
+    /// longer tracked
+    async fn check_confirmations(&self, chain: &[SignedUpdate]) -> Result<()> {
+        let missing: HashSet<H256> = chain.iter().map(|u| u.update.new_root).collect();
+        let now = now();
+        for (new_root, relayed) in self.replica_db.relayed_updates()? {
+            if missing.contains(&new_root) {
+                continue;
+            }
+            if now.saturating_sub(relayed.seen_at) > MAX_TRACKED_AGE {
+                warn!(
+                    new_root = ?new_root,
+                    seen_at = relayed.seen_at,
+                    "Root {} is still not acceptable on the replica. No longer tracking it",
+                    new_root
+                );
+                self.replica_db.remove_relayed_update(new_root)?;
+                continue;
+            }
+            if !self.replica.acceptable_root(new_root).await? {
+                continue;
+            }
+
+            if let Some(submitted_at) = relayed.submitted_at {
+                let latency = now.saturating_sub(submitted_at);
+                info!(
+                    new_root = ?new_root,
+                    submission_block = ?relayed.submission_block,
+                    latency,
+                    "Root {} is acceptable on the replica after {} seconds",
+                    new_root,
+                    latency
+                );
+                self.metrics.confirmation_latency.observe(latency as f64);
+            }
+            self.replica_db.remove_relayed_update(new_root)?;
         }
+        Ok(())
     }
 
+    /// Submit the signed updates the replica is missing back-to-back, oldest
+    /// first, up to the batch size. Each submission waits for its
+    /// transaction to be included before the next. Returns true if there
+    /// are more updates to submit right away
     #[tracing::instrument(err, skip(self), fields(self = %self))]
-    async fn poll_and_relay_update(&self) -> Result<()> {
+    async fn poll_and_relay_updates(&mut self) -> Result<bool> {
         // Get replica's current root.
         let old_root = self.replica.committed_root().await?;
 
@@ -48,11 +233,29 @@ impl UpdatePoller {
             old_root
         );
 
-        // Check for first signed update building off of the replica's current root
-        let signed_update_opt = self.home.signed_update_by_old_root(old_root).await?;
+        // Walk the signed updates building off of the replica's current root
+        let chain = update_chain(&self.home_db, old_root)?;
+        self.track_seen(&chain)?;
+        self.record_lag(&chain)?;
+        self.check_confirmations(&chain).await?;
+
+        if chain.is_empty() {
+            info!(
+                "No update. Current root for replica {} is {}",
+                self.replica.name(),
+                old_root
+            );
+            return Ok(false);
+        }
+
+        info!(
+            behind = chain.len(),
+            "Replica {} is {} updates behind",
+            self.replica.name(),
+            chain.len()
+        );
 
-        // If signed update exists, update replica's current root
-        if let Some(signed_update) = signed_update_opt {
+        for signed_update in chain.iter().take(self.batch_size) {
             info!(
                 "Update for replica {}. Root {} to {}",
                 self.replica.name(),
@@ -60,29 +263,46 @@ impl UpdatePoller {
                 &signed_update.update.new_root,
             );
 
-            let lock = self.semaphore.try_lock();
-            if lock.is_err() {
-                return Ok(()); // tx in flight. just do nothing
+            // Each update builds off of the one before, so stop at the first
+            // that fails. It may have been submitted by another relayer
+            let submitted_at = now();
+            match self.replica.update(signed_update).await {
+                Ok(outcome) if outcome.executed => {
+                    self.track_submitted(signed_update, submitted_at, &outcome)?
+                }
+                Ok(outcome) => {
+                    warn!(
+                        txid = ?outcome.txid,
+                        "Update for replica {} reverted. Root {} to {}",
+                        self.replica.name(),
+                        &signed_update.update.previous_root,
+                        &signed_update.update.new_root,
+                    );
+                    return Ok(false);
+                }
+                Err(e) => {
+                    warn!(
+                        error = %e,
+                        "Failed to submit update for replica {}. Root {} to {}",
+                        self.replica.name(),
+                        &signed_update.update.previous_root,
+                        &signed_update.update.new_root,
+                    );
+                    return Ok(false);
+                }
             }
-            // don't care if it succeeds
-            let _ = self.replica.update(&signed_update).await;
-            // lock dropped here
-        } else {
-            info!(
-                "No update. Current root for replica {} is {}",
-                self.replica.name(),
-                old_root
-            );
         }
 
-        Ok(())
+        Ok(chain.len() > self.batch_size)
     }
 
-    fn spawn(self) -> JoinHandle<Result<()>> {
+    fn spawn(mut self) -> JoinHandle<Result<()>> {
         tokio::spawn(async move {
             loop {
-                self.poll_and_relay_update().await?;
-                sleep(self.duration).await;
+                // Keep going without waiting while catching up
+                if !self.poll_and_relay_updates().await? {
+                    sleep(self.duration).await;
+                }
             }
         })
     }
@@ -92,6 +312,11 @@ impl UpdatePoller {
 #[derive(Debug)]
 pub struct Relayer {
     duration: u64,
+    batch_size: usize,
+    lag_updates: Arc<IntGaugeVec>,
+    lag_seconds: Arc<IntGaugeVec>,
+    relay_latency: Arc<HistogramVec>,
+    confirmation_latency: Arc<HistogramVec>,
     core: AgentCore,
 }
 
@@ -104,15 +329,65 @@ impl AsRef<AgentCore> for Relayer {
 #[allow(clippy::unit_arg)]
 impl Relayer {
     /// Instantiate a new relayer
-    pub fn new(duration: u64, core: AgentCore) -> Self {
-        Self { duration, core }
+    pub fn new(duration: u64, batch_size: usize, core: AgentCore) -> Self {
+        let lag_updates = Arc::new(
+            core.metrics
+                .new_int_gauge(
+                    "replica_lag_updates",
+                    "Number of signed updates the replica is missing",
+                    &["home", "replica", "agent"],
+                )
+                .expect("relayer metric already registered -- should have be a singleton"),
+        );
+        let lag_seconds = Arc::new(
+            core.metrics
+                .new_int_gauge(
+                    "replica_lag_seconds",
+                    "Seconds since the oldest signed update the replica is missing was included on the home",
+                    &["home", "replica", "agent"],
+                )
+                .expect("relayer metric already registered -- should have be a singleton"),
+        );
+        // 1 second to ~9 hours, to cover optimistic windows
+        let buckets =
+            prometheus::exponential_buckets(1.0, 2.0, 16).expect("invalid histogram buckets");
+        let relay_latency = Arc::new(
+            core.metrics
+                .new_histogram(
+                    "relay_latency_seconds",
+                    "Seconds from first seeing a signed update to its inclusion on the replica",
+                    &["home", "replica", "agent"],
+                    buckets.clone(),
+                )
+                .expect("relayer metric already registered -- should have be a singleton"),
+        );
+        let confirmation_latency = Arc::new(
+            core.metrics
+                .new_histogram(
+                    "confirmation_latency_seconds",
+                    "Seconds from submitting a relayed update until its root is acceptable on the replica",
+                    &["home", "replica", "agent"],
+                    buckets,
+                )
+                .expect("relayer metric already registered -- should have be a singleton"),
+        );
+
+        Self {
+            duration,
+            batch_size: batch_size.max(1),
+            lag_updates,
+            lag_seconds,
+            relay_latency,
+            confirmation_latency,
+            core,
+        }
     }
 }
 
 #[async_trait]
 #[allow(clippy::unit_arg)]
 impl OpticsAgent for Relayer {
-    const AGENT_NAME: &'static str = "relayer";
+    const AGENT_NAME: &'static str = AGENT_NAME;
 
     type Settings = Settings;
 
@@ -122,7 +397,11 @@ impl OpticsAgent for Relayer {
     {
         Ok(Self::new(
             settings.interval.parse().expect("invalid uint"),
-            settings.as_ref().try_into_core("relayer").await?,
+            settings
+                .batch_size
+                .map(|b| b.parse().expect("invalid uint"))
+                .unwrap_or(DEFAULT_BATCH_SIZE),
+            settings.as_ref().try_into_core(AGENT_NAME).await?,
         ))
     }
 
@@ -130,9 +409,19 @@ impl OpticsAgent for Relayer {
     fn run(&self, name: &str) -> Instrumented<JoinHandle<Result<()>>> {
         let replica_opt = self.replica_by_name(name);
         let home = self.home();
+        let home_db = self.home_db();
+        let replica_db = ReplicaDB::new(self.db(), name.to_owned());
         let name = name.to_owned();
 
         let duration = self.duration;
+        let batch_size = self.batch_size;
+        let labels = [home.name(), name.as_str(), AGENT_NAME];
+        let metrics = RelayMetrics {
+            lag_updates: self.lag_updates.with_label_values(&labels),
+            lag_seconds: self.lag_seconds.with_label_values(&labels),
+            relay_latency: self.relay_latency.with_label_values(&labels),
+            confirmation_latency: self.confirmation_latency.with_label_values(&labels),
+        };
 
         tokio::spawn(async move {
             if replica_opt.is_none() {
@@ -140,7 +429,15 @@ impl OpticsAgent for Relayer {
             }
             let replica = replica_opt.unwrap();
 
-            let update_poller = UpdatePoller::new(home, replica.clone(), duration);
+            let update_poller = UpdatePoller::new(
+                home,
+                replica.clone(),
+                home_db,
+                replica_db,
+                duration,
+                batch_size,
+                metrics,
+            );
             update_poller.spawn().await?
         })
         .in_current_span()
@@ -148,4 +445,194 @@ impl OpticsAgent for Relayer {
 }
 
 #[cfg(test)]
-mod test {}
+mod test {
+    use ethers::signers::LocalWallet;
+    use optics_core::{SignUpdate, Update};
+    use optics_test::{
+        mocks::{MockHomeContract, MockReplicaContract},
+        test_utils,
+    };
+    use prometheus::HistogramOpts;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn it_walks_the_update_chain_from_a_root() {
+        test_utils::run_test_db(|db| async move {
+            let signer: LocalWallet =
+                "1111111111111111111111111111111111111111111111111111111111111111"
+                    .parse()
+                    .unwrap();
+            let home_db = HomeDB::new(db, "home_1".to_owned());
+
+            let roots: Vec<_> = (0..4).map(H256::repeat_byte).collect();
+            for pair in roots.windows(2) {
+                let update = Update {
+                    home_domain: 1,
+                    previous_root: pair[0],
+                    new_root: pair[1],
+                }
+                .sign_with(&signer)
+                .await
+                .expect("!sign");
+                home_db.store_latest_update(&update).unwrap();
+            }
+
+            let new_roots = |root| -> Vec<H256> {
+                update_chain(&home_db, root)
+                    .unwrap()
+                    .iter()
+                    .map(|update| update.update.new_root)
+                    .collect()
+            };
+            assert_eq!(new_roots(roots[0]), roots[1..].to_vec());
+            assert_eq!(new_roots(roots[2]), vec![roots[3]]);
+            assert!(new_roots(roots[3]).is_empty());
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_observes_confirmations_and_expires_stale_roots() {
+        test_utils::run_test_db(|db| async move {
+            let roots: Vec<_> = (0..5).map(H256::repeat_byte).collect();
+            let (submitted, seen, missing, pending, stale) =
+                (roots[0], roots[1], roots[2], roots[3], roots[4]);
+
+            let mut mock_replica = MockReplicaContract::new();
+            // neither the missing root nor the stale root is checked
+            mock_replica
+                .expect__acceptable_root()
+                .times(3)
+                .returning(move |root| Ok(root == submitted || root == seen));
+
+            let metric = |name: &str| IntGauge::new(name, name).unwrap();
+            let confirmation_latency =
+                Histogram::with_opts(HistogramOpts::new("confirmation", "confirmation")).unwrap();
+            let poller = UpdatePoller::new(
+                Arc::new(MockHomeContract::new().into()),
+                Arc::new(mock_replica.into()),
+                HomeDB::new(db.clone(), "home_1".to_owned()),
+                ReplicaDB::new(db, "replica_1".to_owned()),
+                1,
+                DEFAULT_BATCH_SIZE,
+                RelayMetrics {
+                    lag_updates: metric("lag_updates"),
+                    lag_seconds: metric("lag_seconds"),
+                    relay_latency: Histogram::with_opts(HistogramOpts::new("relay", "relay"))
+                        .unwrap(),
+                    confirmation_latency: confirmation_latency.clone(),
+                },
+            );
+
+            let now = now();
+            let track = |root, seen_at, submitted_at| {
+                poller
+                    .replica_db
+                    .store_relayed_update(
+                        root,
+                        &RelayedUpdate {
+                            seen_at,
+                            submitted_at,
+                            submission_block: None,
+                        },
+                    )
+                    .unwrap()
+            };
+            track(submitted, now - 20, Some(now - 10));
+            track(seen, now - 20, None);
+            track(missing, now - 20, None);
+            track(pending, now - 20, Some(now - 10));
+            track(stale, now - MAX_TRACKED_AGE - 1, None);
+
+            let signer: LocalWallet =
+                "1111111111111111111111111111111111111111111111111111111111111111"
+                    .parse()
+                    .unwrap();
+            let missing_update = Update {
+                home_domain: 1,
+                previous_root: submitted,
+                new_root: missing,
+            }
+            .sign_with(&signer)
+            .await
+            .expect("!sign");
+            poller.check_confirmations(&[missing_update]).await.unwrap();
+
+            // only the relayer's own submission is observed
+            assert_eq!(confirmation_latency.get_sample_count(), 1);
+            assert!(confirmation_latency.get_sample_sum() >= 10.0);
+
+            let tracked: HashSet<_> = poller
+                .replica_db
+                .relayed_updates()
+                .unwrap()
+                .into_iter()
+                .map(|(root, _)| root)
+                .collect();
+            assert_eq!(tracked, [missing, pending].iter().copied().collect());
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn it_measures_lag_from_the_home_block_time() {
+        test_utils::run_test_db(|db| async move {
+            let signer: LocalWallet =
+                "1111111111111111111111111111111111111111111111111111111111111111"
+                    .parse()
+                    .unwrap();
+            let metric = |name: &str| IntGauge::new(name, name).unwrap();
+            let histogram =
+                |name: &str| Histogram::with_opts(HistogramOpts::new(name, name)).unwrap();
+            let poller = UpdatePoller::new(
+                Arc::new(MockHomeContract::new().into()),
+                Arc::new(MockReplicaContract::new().into()),
+                HomeDB::new(db.clone(), "home_1".to_owned()),
+                ReplicaDB::new(db, "replica_1".to_owned()),
+                1,
+                DEFAULT_BATCH_SIZE,
+                RelayMetrics {
+                    lag_updates: metric("lag_updates"),
+                    lag_seconds: metric("lag_seconds"),
+                    relay_latency: histogram("relay"),
+                    confirmation_latency: histogram("confirmation"),
+                },
+            );
+
+            let roots: Vec<_> = (0..3).map(H256::repeat_byte).collect();
+            let mut chain = vec![];
+            for pair in roots.windows(2) {
+                let update = Update {
+                    home_domain: 1,
+                    previous_root: pair[0],
+                    new_root: pair[1],
+                }
+                .sign_with(&signer)
+                .await
+                .expect("!sign");
+                chain.push(update);
+            }
+
+            // a restarted relayer has no record of when it first saw the
+            // chain, but the home block time survives
+            let now = now();
+            poller
+                .home_db
+                .store_update_timestamp(roots[1], now - 60)
+                .unwrap();
+            poller
+                .home_db
+                .store_update_timestamp(roots[2], now - 5)
+                .unwrap();
+            poller.record_lag(&chain).unwrap();
+            assert_eq!(poller.metrics.lag_updates.get(), 2);
+            assert!(poller.metrics.lag_seconds.get() >= 60);
+
+            poller.record_lag(&[]).unwrap();
+            assert_eq!(poller.metrics.lag_updates.get(), 0);
+            assert_eq!(poller.metrics.lag_seconds.get(), 0);
+        })
+        .await
+    }
+}
@@ -5,4 +5,7 @@ use optics_base::decl_settings;
 decl_settings!(Relayer {
     /// The polling interval (in seconds)
     interval: String,
+    /// The most updates to submit to a replica back-to-back before
+    /// re-reading its root. Defaults to 16
+    batch_size: Option<String>,
 });
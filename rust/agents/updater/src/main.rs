@@ -50,9 +50,12 @@ async fn _main() -> Result<()> {
         .expect("failed to register block_height metric")
         .with_label_values(&[agent.home().name(), Updater::AGENT_NAME]);
 
-    let index_task = agent
-        .home()
-        .index(indexer.from(), indexer.chunk_size(), block_height);
+    let index_task = agent.home().index(
+        indexer.from(),
+        indexer.chunk_size(),
+        indexer.confirmations(),
+        block_height,
+    );
     let run_task = agent.run("");
 
     let futs = vec![index_task, run_task];
@@ -20,7 +20,10 @@ use tracing::{error, info, instrument::Instrumented, Instrument};
 
 use crate::settings::UpdaterSettings as Settings;
 use optics_base::{AgentCore, Homes, OpticsAgent};
-use optics_core::{db::HomeDB, Common, Home, SignedUpdate, Signers, Update};
+use optics_core::{
+    db::{HomeDB, SigningDB},
+    Common, Home, SignUpdate, SignedUpdate, Signers, Update,
+};
 
 #[derive(Debug)]
 struct UpdateHandler {
@@ -30,6 +33,7 @@ struct UpdateHandler {
     update_pause: u64,
     signer: Arc<Signers>,
     home_db: HomeDB,
+    signing_db: SigningDB,
     mutex: Arc<Mutex<()>>,
     signed_attestation_count: IntCounterVec,
 }
@@ -51,6 +55,7 @@ impl UpdateHandler {
         update_pause: u64,
         signer: Arc<Signers>,
         home_db: HomeDB,
+        signing_db: SigningDB,
         mutex: Arc<Mutex<()>>,
         signed_attestation_count: IntCounterVec,
     ) -> Self {
@@ -60,6 +65,7 @@ impl UpdateHandler {
             update_pause,
             signer,
             home_db,
+            signing_db,
             mutex,
             signed_attestation_count,
         }
@@ -135,8 +141,21 @@ impl UpdateHandler {
             bail!("Found conflicting update in DB");
         }
 
-        // If we have a conflict, we grab that one instead
-        let signed = update.sign_with(self.signer.as_ref()).await.unwrap();
+        // If we already signed this update, resubmit the same signature.
+        // Otherwise sign, and record the signature in the signing history
+        // before releasing it. Recording refuses conflicting signatures
+        let signed = match self.signing_db.signed_update(
+            self.signer.address(),
+            update.home_domain,
+            update.previous_root,
+        )? {
+            Some(existing) if existing.update == update => existing,
+            _ => {
+                let signed = update.sign_with(self.signer.as_ref()).await.unwrap();
+                self.signing_db.record(&signed)?;
+                signed
+            }
+        };
 
         // If successfully submitted update, record in db
         info!(
@@ -267,6 +286,7 @@ impl OpticsAgent for Updater {
             self.update_pause,
             self.signer.clone(),
             HomeDB::new(self.db(), self.home().name().to_owned()),
+            SigningDB::new(self.db()),
             Default::default(),
             self.signed_attestation_count.clone(),
         );
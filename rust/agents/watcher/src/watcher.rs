@@ -1,21 +1,28 @@
 use async_trait::async_trait;
-use color_eyre::{eyre::bail, Report, Result};
+use color_eyre::{
+    eyre::{bail, eyre},
+    Report, Result,
+};
 use thiserror::Error;
 
 use ethers::core::types::H256;
 use futures_util::future::{join, join_all, select_all};
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
 use tokio::{
     sync::{mpsc, oneshot, RwLock},
     task::JoinHandle,
     time::sleep,
 };
-use tracing::{info, info_span, instrument::Instrumented, Instrument};
+use tracing::{error, info, info_span, instrument::Instrumented, Instrument};
 
 use optics_base::{cancel_task, AgentCore, ConnectionManagers, Homes, OpticsAgent};
 use optics_core::{
-    db::HomeDB, ChainCommunicationError, Common, ConnectionManager, DoubleUpdate,
-    FailureNotification, Home, SignedUpdate, Signers, TxOutcome,
+    accumulator::incremental::IncrementalMerkle, db::HomeDB, ChainCommunicationError, Common,
+    ConnectionManager, DoubleUpdate, FailureNotification, Home, SignedUpdate, Signers, TxOutcome,
 };
 
 use crate::settings::WatcherSettings as Settings;
@@ -112,6 +119,14 @@ where
         }
     }
 
+    /// Send the update producing `committed_root`, and step back to its
+    /// previous root. Returns `WatcherError::SyncingFinished` once the zero
+    /// root is reached.
+    ///
+    /// Histories are served from the indexed DB, so a missing update means
+    /// it has not been indexed yet rather than that the history is
+    /// exhausted. Returns `Ok(())` without stepping back, to retry on the
+    /// next tick.
     async fn update_history(&mut self) -> Result<()> {
         let previous_update = self
             .contract
@@ -119,8 +134,9 @@ where
             .await?;
 
         if previous_update.is_none() {
-            // Task finished
-            return Err(Report::new(WatcherError::SyncingFinished));
+            // The contract's history may not be indexed yet. Try again on
+            // the next tick
+            return Ok(());
         }
 
         // Dispatch to the handler
@@ -155,6 +171,165 @@ where
     }
 }
 
+/// Evidence of updater fraud
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fraud {
+    /// Two conflicting updates building off the same root
+    DoubleUpdate(DoubleUpdate),
+    /// An update to a root the home never produced
+    ImproperUpdate(SignedUpdate),
+}
+
+/// Rebuilds the sequence of roots produced by the home from the leaves
+/// indexed into the `HomeDB`. Requires the indexer to start from the home's
+/// first dispatch, and fails if it did not.
+#[derive(Debug)]
+pub struct RootTracker {
+    home_db: HomeDB,
+    tree: IncrementalMerkle,
+    roots: HashSet<H256>,
+    rollbacks: u64,
+}
+
+impl RootTracker {
+    pub fn new(home_db: HomeDB) -> Self {
+        Self {
+            home_db,
+            tree: Default::default(),
+            roots: Default::default(),
+            rollbacks: 0,
+        }
+    }
+
+    /// Ingest any leaves indexed since the last sync. If the DB was rolled
+    /// back in the meantime, the roots are rebuilt from scratch.
+    ///
+    /// Fails if a later leaf has been indexed than the next one to ingest,
+    /// as the roots cannot be rebuilt past the gap.
+    pub fn sync(&mut self) -> Result<()> {
+        let rollbacks = self.home_db.retrieve_rollback_count()?;
+        if rollbacks != self.rollbacks {
+            self.tree = Default::default();
+            self.roots.clear();
+            self.rollbacks = rollbacks;
+        }
+
+        while let Some(leaf) = self.home_db.leaf_by_leaf_index(self.count())? {
+            self.tree.ingest(leaf);
+            self.roots.insert(self.tree.root());
+        }
+
+        if let Some(latest) = self.home_db.retrieve_latest_leaf_index()? {
+            if latest >= self.count() {
+                bail!(
+                    "Leaf {} is missing from the DB, but leaf {} was indexed. The home's roots \
+                    can only be rebuilt if the indexer starts from its first dispatch",
+                    self.count(),
+                    latest
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// The number of leaves ingested
+    pub fn count(&self) -> u32 {
+        self.tree.count() as u32
+    }
+
+    /// True if the home produced `root` after dispatching a message
+    pub fn contains(&self, root: H256) -> bool {
+        self.roots.contains(&root)
+    }
+}
+
+/// Checks whether updates commit to roots the home never produced.
+///
+/// This may have to wait for the indexer to catch up with the home, so it
+/// runs in its own task alongside the `UpdateHandler`.
+#[derive(Debug)]
+pub struct ImproperUpdateChecker {
+    rx: mpsc::UnboundedReceiver<SignedUpdate>,
+    home_db: HomeDB,
+    home: Arc<Homes>,
+    roots: RootTracker,
+}
+
+impl ImproperUpdateChecker {
+    pub fn new(
+        rx: mpsc::UnboundedReceiver<SignedUpdate>,
+        home_db: HomeDB,
+        home: Arc<Homes>,
+    ) -> Self {
+        let roots = RootTracker::new(home_db.clone());
+        Self {
+            rx,
+            home_db,
+            home,
+            roots,
+        }
+    }
+
+    /// Check whether the update's new root was ever produced by the home.
+    ///
+    /// A root missing from the locally rebuilt sequence may simply not have
+    /// been indexed yet. We only declare an update improper once the indexer
+    /// has caught up with the home's committed root and the root is not in
+    /// the home's queue.
+    async fn check_improper_update(&mut self, update: &SignedUpdate) -> Result<bool> {
+        let new_root = update.update.new_root;
+
+        loop {
+            self.roots.sync()?;
+            if self.roots.contains(new_root) {
+                return Ok(false);
+            }
+
+            // The home fails itself when it receives an improper update, so
+            // an update it has accepted was proper
+            if self.home_db.retrieve_update_metadata(new_root)?.is_some() {
+                return Ok(false);
+            }
+
+            // Every dequeued root must have been indexed before we can tell
+            // whether it was produced
+            let committed_root = self.home.committed_root().await?;
+            if !committed_root.is_zero() && !self.roots.contains(committed_root) {
+                self.home_db.wait_for_leaf(self.roots.count()).await?;
+                continue;
+            }
+
+            if self.home.queue_contains(new_root).await? {
+                return Ok(false);
+            }
+
+            // If the committed root moved, the root may have been dequeued
+            // between the two calls, so check again
+            if self.home.committed_root().await? == committed_root {
+                return Ok(true);
+            }
+        }
+    }
+
+    #[tracing::instrument]
+    fn spawn(mut self) -> JoinHandle<Result<Fraud>> {
+        tokio::spawn(async move {
+            loop {
+                let update = self.rx.recv().await;
+                // channel is closed
+                if update.is_none() {
+                    bail!("Channel closed.")
+                }
+
+                let update = update.unwrap();
+                if self.check_improper_update(&update).await? {
+                    return Ok(Fraud::ImproperUpdate(update));
+                }
+            }
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct UpdateHandler {
     rx: mpsc::Receiver<SignedUpdate>,
@@ -189,28 +364,47 @@ impl UpdateHandler {
         Ok(())
     }
 
+    /// Check each update for double updates, and hand it to an
+    /// `ImproperUpdateChecker` running alongside. Resolves with the first
+    /// fraud either of them finds.
     #[tracing::instrument]
-    fn spawn(mut self) -> JoinHandle<Result<DoubleUpdate>> {
+    fn spawn(mut self) -> JoinHandle<Result<Fraud>> {
         tokio::spawn(async move {
-            loop {
-                let update = self.rx.recv().await;
+            let (improper_tx, improper_rx) = mpsc::unbounded_channel();
+            let mut improper =
+                ImproperUpdateChecker::new(improper_rx, self.home_db.clone(), self.home.clone())
+                    .spawn();
+
+            let fraud = loop {
+                let update = tokio::select! {
+                    res = &mut improper => return res?,
+                    update = self.rx.recv() => update,
+                };
                 // channel is closed
                 if update.is_none() {
-                    bail!("Channel closed.")
+                    break Err(eyre!("Channel closed."));
                 }
 
                 let update = update.unwrap();
                 let old_root = update.update.previous_root;
 
+                if improper_tx.send(update.clone()).is_err() {
+                    // The checker has exited, with fraud or an error
+                    return improper.await?;
+                }
+
                 if old_root == self.home.committed_root().await? {
                     // It is okay if tx reverts
                     let _ = self.home.update(&update).await;
                 }
 
                 if let Err(double_update) = self.check_double_update(&update) {
-                    return Ok(double_update);
+                    break Ok(Fraud::DoubleUpdate(double_update));
                 }
-            }
+            };
+
+            improper.abort();
+            fraud
         })
     }
 }
@@ -261,20 +455,27 @@ impl Watcher {
         }
     }
 
-    // Handle a double-update once it has been detected.
+    // Handle fraud once it has been detected.
     #[tracing::instrument]
     async fn handle_failure(
         &self,
-        double: &DoubleUpdate,
+        fraud: &Fraud,
     ) -> Vec<Result<TxOutcome, ChainCommunicationError>> {
-        // Create vector of double update futures
-        let mut double_update_futs: Vec<_> = self
-            .core
-            .replicas
-            .values()
-            .map(|replica| replica.double_update(double))
-            .collect();
-        double_update_futs.push(self.core.home.double_update(double));
+        // Create vector of fraud proof futures. Double updates can be proven
+        // on every contract. Improper updates can only be proven on the home
+        let fraud_futs: Vec<_> = match fraud {
+            Fraud::DoubleUpdate(double) => {
+                let mut futs: Vec<_> = self
+                    .core
+                    .replicas
+                    .values()
+                    .map(|replica| replica.double_update(double))
+                    .collect();
+                futs.push(self.core.home.double_update(double));
+                futs
+            }
+            Fraud::ImproperUpdate(update) => vec![self.core.home.improper_update(update)],
+        };
 
         // Created signed failure notification
         let signed_failure = FailureNotification {
@@ -292,11 +493,10 @@ impl Watcher {
             unenroll_futs.push(connection_manager.unenroll_replica(&signed_failure));
         }
 
-        // Join both vectors of double update and unenroll futures and
+        // Join both vectors of fraud proof and unenroll futures and
         // return vector containing all results
-        let (double_update_res, unenroll_res) =
-            join(join_all(double_update_futs), join_all(unenroll_futs)).await;
-        double_update_res
+        let (fraud_res, unenroll_res) = join(join_all(fraud_futs), join_all(unenroll_futs)).await;
+        fraud_res
             .into_iter()
             .chain(unenroll_res.into_iter())
             .collect()
@@ -304,7 +504,7 @@ impl Watcher {
 
     fn run_watch_tasks(
         &self,
-        double_update_tx: oneshot::Sender<DoubleUpdate>,
+        fraud_tx: oneshot::Sender<Fraud>,
     ) -> Instrumented<JoinHandle<Result<()>>> {
         let home_db = HomeDB::new(self.db(), self.home().name().to_owned());
         let home = self.home();
@@ -347,16 +547,16 @@ impl Watcher {
                 .in_current_span();
 
             // Wait for update handler to encounter error OR fraud
-            let double_update_res = handler.await?;
+            let fraud_res = handler.await?;
 
             // Cancel running tasks
             tracing::info!("Update handler has resolved. Cancelling all other tasks");
             cancel_task!(home_watcher);
             cancel_task!(home_sync);
 
-            // If double update found, send through oneshot
-            if let Err(e) = double_update_tx.send(double_update_res?) {
-                bail!("Failed to send double update through oneshot: {:?}", e);
+            // If fraud found, send through oneshot
+            if let Err(e) = fraud_tx.send(fraud_res?) {
+                bail!("Failed to send fraud through oneshot: {:?}", e);
             }
 
             Ok(())
@@ -377,10 +577,14 @@ impl OpticsAgent for Watcher {
     where
         Self: Sized,
     {
+        let core = settings.as_ref().try_into_core("watcher").await?;
+
         let mut connection_managers = vec![];
         for chain_setup in settings.connection_managers.iter() {
             let signer = settings.base.get_signer(&chain_setup.name).await;
-            let manager = chain_setup.try_into_connection_manager(signer).await;
+            let manager = chain_setup
+                .try_into_connection_manager(signer, core.db.clone(), &core.metrics)
+                .await;
             connection_managers.push(manager);
         }
 
@@ -398,8 +602,6 @@ impl OpticsAgent for Watcher {
             .map(Result::unwrap)
             .collect();
 
-        let core = settings.as_ref().try_into_core("watcher").await?;
-
         Ok(Self::new(
             settings.watcher.try_into_signer().await?,
             settings.interval.parse().expect("invalid uint"),
@@ -433,20 +635,24 @@ impl OpticsAgent for Watcher {
                     "Height of a recently observed block",
                     &["network", "agent"],
                 )
-                .expect("failed to register block_height metric")
-                .with_label_values(&[self.home().name(), Self::AGENT_NAME]);
+                .expect("failed to register block_height metric");
             let indexer = &self.as_ref().indexer;
-            let index_task = self
-                .home()
-                .index(indexer.from(), indexer.chunk_size(), block_height);
+            let index_task = self.home().index(
+                indexer.from(),
+                indexer.chunk_size(),
+                indexer.confirmations(),
+                block_height.with_label_values(&[self.home().name(), Self::AGENT_NAME]),
+            );
+            let replica_index_tasks = self.index_replicas(&block_height);
 
             // Watcher watch tasks setup
-            let (double_update_tx, mut double_update_rx) = oneshot::channel::<DoubleUpdate>();
-            let watch_tasks = self.run_watch_tasks(double_update_tx);
+            let (fraud_tx, mut fraud_rx) = oneshot::channel::<Fraud>();
+            let watch_tasks = self.run_watch_tasks(fraud_tx);
 
             // Race index and run tasks
             info!("selecting");
-            let tasks = vec![index_task, watch_tasks];
+            let mut tasks = vec![index_task, watch_tasks];
+            tasks.extend(replica_index_tasks);
             let (_, _, remaining) = select_all(tasks).await;
 
             // Cancel lagging task and watcher polling/syncing tasks
@@ -455,22 +661,29 @@ impl OpticsAgent for Watcher {
             }
             self.shutdown().await;
 
-            // Check if double update was sent during run task
-            match double_update_rx.try_recv() {
-                Ok(double_update) => {
-                    tracing::error!(
-                        double_update = ?double_update,
-                        "Double update detected! Notifying all contracts and unenrolling replicas! Double update: {:?}",
-                        double_update
-                    );
-                    self.handle_failure(&double_update)
+            // Check if fraud was sent during run task
+            match fraud_rx.try_recv() {
+                Ok(fraud) => {
+                    match &fraud {
+                        Fraud::DoubleUpdate(double_update) => error!(
+                            double_update = ?double_update,
+                            "Double update detected! Notifying all contracts and unenrolling replicas! Double update: {:?}",
+                            double_update
+                        ),
+                        Fraud::ImproperUpdate(update) => error!(
+                            improper_update = ?update,
+                            "Improper update detected! Notifying home and unenrolling replicas! Improper update: {:?}",
+                            update
+                        ),
+                    }
+                    self.handle_failure(&fraud)
                         .await
                         .iter()
                         .for_each(|res| tracing::info!("{:#?}", res));
 
                     bail!(
                         r#"
-                        Double update detected!
+                        Fraud detected!
                         All contracts notified!
                         Replicas unenrolled!
                         Watcher has been shut down!
@@ -494,7 +707,10 @@ mod test {
     use ethers::signers::{LocalWallet, Signer};
 
     use optics_base::Replicas;
-    use optics_core::{DoubleUpdate, SignedFailureNotification, Update};
+    use optics_core::{
+        DoubleUpdate, Encode, OpticsMessage, RawCommittedMessage, SignUpdate,
+        SignedFailureNotification, Update,
+    };
     use optics_test::{
         mocks::{MockConnectionManagerContract, MockHomeContract, MockReplicaContract},
         test_utils,
@@ -632,6 +848,63 @@ mod test {
         mock_home.checkpoint();
     }
 
+    #[tokio::test]
+    async fn history_sync_waits_for_unindexed_updates() {
+        let signer: LocalWallet =
+            "1111111111111111111111111111111111111111111111111111111111111111"
+                .parse()
+                .unwrap();
+
+        let first_root = H256::from([1; 32]);
+        let first_signed_update = Update {
+            home_domain: 1,
+            previous_root: H256::zero(),
+            new_root: first_root,
+        }
+        .sign_with(&signer)
+        .await
+        .expect("!sign");
+
+        let mut mock_home = MockHomeContract::new();
+        {
+            let first_signed_update = first_signed_update.clone();
+            let mut indexed = false;
+            // the update is missing from the DB until the second call
+            mock_home
+                .expect__signed_update_by_new_root()
+                .withf(move |r: &H256| *r == first_root)
+                .times(2)
+                .returning(move |_| {
+                    let update = Some(first_signed_update.clone()).filter(|_| indexed);
+                    indexed = true;
+                    Ok(update)
+                });
+        }
+
+        let mut home: Arc<Homes> = Arc::new(mock_home.into());
+        let (tx, mut rx) = mpsc::channel(200);
+        {
+            let mut history_sync = HistorySync::new(3, first_root, tx.clone(), home.clone());
+
+            history_sync
+                .update_history()
+                .await
+                .expect("Should have received Ok(())");
+            assert_eq!(history_sync.committed_root, first_root);
+            assert!(rx.try_recv().is_err());
+
+            history_sync
+                .update_history()
+                .await
+                .expect_err("Should have received WatcherError::SyncingFinished");
+            assert_eq!(history_sync.committed_root, H256::zero());
+            assert_eq!(rx.recv().await.unwrap(), first_signed_update);
+        }
+
+        let mock_home = Arc::get_mut(&mut home).unwrap();
+        mock_home.checkpoint();
+    }
+
     #[tokio::test]
     async fn update_handler_detects_double_update() {
         test_utils::run_test_db(|db| async move {
@@ -674,9 +947,10 @@ mod test {
 
             {
                 let (_tx, rx) = mpsc::channel(200);
+                let home_db = HomeDB::new(db, "home_1".to_owned());
                 let mut handler = UpdateHandler {
                     rx,
-                    home_db: HomeDB::new(db, "home_1".to_owned()),
+                    home_db,
                     home: Arc::new(MockHomeContract::new().into()),
                 };
 
@@ -700,6 +974,155 @@ mod test {
         .await
     }
 
+    #[tokio::test]
+    async fn improper_update_checker_detects_improper_update() {
+        test_utils::run_test_db(|db| async move {
+            let signer: LocalWallet =
+                "1111111111111111111111111111111111111111111111111111111111111111"
+                    .parse()
+                    .unwrap();
+
+            // index two dispatches, tracking the roots the home produces
+            let home_db = HomeDB::new(db, "home_1".to_owned());
+            let mut tree = IncrementalMerkle::default();
+            let mut roots = vec![];
+            for nonce in 0..2 {
+                let message = RawCommittedMessage {
+                    leaf_index: nonce,
+                    committed_root: tree.root(),
+                    message: OpticsMessage {
+                        origin: 1,
+                        sender: H256::from_low_u64_be(4),
+                        nonce,
+                        destination: 2,
+                        recipient: H256::from_low_u64_be(5),
+                        body: vec![1, 2, 3],
+                    }
+                    .to_vec(),
+                };
+                home_db.store_raw_committed_message(&message).unwrap();
+                tree.ingest(message.leaf());
+                roots.push(tree.root());
+            }
+
+            let queued_root = H256::from([8; 32]);
+            let bad_root = H256::from([9; 32]);
+
+            let mut mock_home = MockHomeContract::new();
+            {
+                let committed_root = roots[0];
+                mock_home
+                    .expect__committed_root()
+                    .returning(move || Ok(committed_root));
+                mock_home
+                    .expect__queue_contains()
+                    .withf(move |r: &H256| *r == queued_root)
+                    .times(1)
+                    .return_once(|_| Ok(true));
+                mock_home
+                    .expect__queue_contains()
+                    .withf(move |r: &H256| *r == bad_root)
+                    .times(1)
+                    .return_once(|_| Ok(false));
+            }
+
+            let (_tx, rx) = mpsc::unbounded_channel();
+            let mut checker = ImproperUpdateChecker::new(rx, home_db, Arc::new(mock_home.into()));
+
+            for (new_root, improper) in [(roots[1], false), (queued_root, false), (bad_root, true)]
+            {
+                let update = Update {
+                    home_domain: 1,
+                    previous_root: roots[0],
+                    new_root,
+                }
+                .sign_with(&signer)
+                .await
+                .expect("!sign");
+
+                assert_eq!(
+                    checker.check_improper_update(&update).await.unwrap(),
+                    improper
+                );
+            }
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn root_tracker_fails_if_indexing_started_late() {
+        test_utils::run_test_db(|db| async move {
+            let home_db = HomeDB::new(db, "home_1".to_owned());
+            let message = RawCommittedMessage {
+                leaf_index: 1,
+                committed_root: H256::zero(),
+                message: OpticsMessage {
+                    origin: 1,
+                    sender: H256::from_low_u64_be(4),
+                    nonce: 1,
+                    destination: 2,
+                    recipient: H256::from_low_u64_be(5),
+                    body: vec![1, 2, 3],
+                }
+                .to_vec(),
+            };
+            home_db.store_raw_committed_message(&message).unwrap();
+
+            let mut roots = RootTracker::new(home_db);
+            assert!(roots.sync().is_err());
+            assert_eq!(roots.count(), 0);
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn update_handler_detects_double_update_while_improper_check_waits() {
+        test_utils::run_test_db(|db| async move {
+            let signer: LocalWallet =
+                "1111111111111111111111111111111111111111111111111111111111111111"
+                    .parse()
+                    .unwrap();
+
+            // The home has committed to a root no indexed leaf produced, so
+            // the improper update check waits on the indexer
+            let mut mock_home = MockHomeContract::new();
+            mock_home
+                .expect__committed_root()
+                .returning(|| Ok(H256::from([7; 32])));
+
+            let mut updates = vec![];
+            for new_root in [H256::from([2; 32]), H256::from([3; 32])] {
+                let update = Update {
+                    home_domain: 1,
+                    previous_root: H256::from([1; 32]),
+                    new_root,
+                }
+                .sign_with(&signer)
+                .await
+                .expect("!sign");
+                updates.push(update);
+            }
+
+            let (tx, rx) = mpsc::channel(200);
+            let home_db = HomeDB::new(db, "home_1".to_owned());
+            let handler = UpdateHandler::new(rx, home_db, Arc::new(mock_home.into())).spawn();
+            for update in updates.iter() {
+                tx.send(update.clone()).await.unwrap();
+            }
+
+            let fraud = tokio::time::timeout(Duration::from_secs(5), handler)
+                .await
+                .expect("double update detection stalled")
+                .unwrap()
+                .unwrap();
+            assert_eq!(
+                fraud,
+                Fraud::DoubleUpdate(DoubleUpdate(updates[0].clone(), updates[1].clone()))
+            );
+        })
+        .await
+    }
+
     #[tokio::test]
     async fn it_fails_contracts_and_unenrolls_replicas_on_double_update() {
         test_utils::run_test_db(|db| async move {
@@ -775,6 +1198,8 @@ mod test {
                         Ok(TxOutcome {
                             txid: H256::default(),
                             executed: true,
+                            gas_used: 0.into(),
+                            block_number: None,
                         })
                     });
             }
@@ -789,6 +1214,8 @@ mod test {
                         Ok(TxOutcome {
                             txid: H256::default(),
                             executed: true,
+                            gas_used: 0.into(),
+                            block_number: None,
                         })
                     });
             }
@@ -803,6 +1230,8 @@ mod test {
                         Ok(TxOutcome {
                             txid: H256::default(),
                             executed: true,
+                            gas_used: 0.into(),
+                            block_number: None,
                         })
                     });
             }
@@ -819,6 +1248,8 @@ mod test {
                         Ok(TxOutcome {
                             txid: H256::default(),
                             executed: true,
+                            gas_used: 0.into(),
+                            block_number: None,
                         })
                     });
             }
@@ -833,6 +1264,8 @@ mod test {
                         Ok(TxOutcome {
                             txid: H256::default(),
                             executed: true,
+                            gas_used: 0.into(),
+                            block_number: None,
                         })
                     });
             }
@@ -868,7 +1301,7 @@ mod test {
             };
 
             let mut watcher = Watcher::new(updater.into(), 1, connection_managers, core);
-            watcher.handle_failure(&double).await;
+            watcher.handle_failure(&Fraud::DoubleUpdate(double)).await;
 
             // Checkpoint connection managers
             for connection_manager in watcher.connection_managers.iter_mut() {
@@ -886,4 +1319,114 @@ mod test {
         })
         .await
     }
+
+    #[tokio::test]
+    async fn it_fails_home_and_unenrolls_replicas_on_improper_update() {
+        test_utils::run_test_db(|db| async move {
+            let home_domain = 1;
+
+            let updater: LocalWallet =
+                "1111111111111111111111111111111111111111111111111111111111111111"
+                    .parse()
+                    .unwrap();
+
+            let improper = Update {
+                home_domain,
+                previous_root: H256::from([1; 32]),
+                new_root: H256::from([9; 32]),
+            }
+            .sign_with(&updater)
+            .await
+            .expect("!sign");
+
+            let signed_failure = FailureNotification {
+                home_domain,
+                updater: updater.address().into(),
+            }
+            .sign_with(&updater)
+            .await
+            .expect("!sign");
+
+            let mut mock_connection_manager = MockConnectionManagerContract::new();
+            let mut mock_home = MockHomeContract::new();
+
+            // Home expectations
+            {
+                mock_home
+                    .expect__local_domain()
+                    .times(1)
+                    .return_once(move || home_domain);
+
+                let updater = updater.clone();
+                mock_home
+                    .expect__updater()
+                    .times(1)
+                    .return_once(move || Ok(updater.address().into()));
+
+                // home.improper_update called once
+                let improper = improper.clone();
+                mock_home
+                    .expect__improper_update()
+                    .withf(move |u: &SignedUpdate| *u == improper)
+                    .times(1)
+                    .return_once(move |_| {
+                        Ok(TxOutcome {
+                            txid: H256::default(),
+                            executed: true,
+                            gas_used: 0.into(),
+                            block_number: None,
+                        })
+                    });
+            }
+
+            // Connection manager expectations
+            {
+                let signed_failure = signed_failure.clone();
+                mock_connection_manager
+                    .expect__unenroll_replica()
+                    .withf(move |f: &SignedFailureNotification| *f == signed_failure)
+                    .times(1)
+                    .return_once(move |_| {
+                        Ok(TxOutcome {
+                            txid: H256::default(),
+                            executed: true,
+                            gas_used: 0.into(),
+                            block_number: None,
+                        })
+                    });
+            }
+
+            let core = AgentCore {
+                home: Arc::new(mock_home.into()),
+                replicas: HashMap::new(),
+                db,
+                indexer: IndexSettings::default(),
+                settings: optics_base::Settings::default(),
+                metrics: Arc::new(
+                    optics_base::CoreMetrics::new(
+                        "watcher_test",
+                        None,
+                        Arc::new(prometheus::Registry::new()),
+                    )
+                    .expect("could not make metrics"),
+                ),
+            };
+
+            let mut watcher = Watcher::new(
+                updater.into(),
+                1,
+                vec![mock_connection_manager.into()],
+                core,
+            );
+            watcher
+                .handle_failure(&Fraud::ImproperUpdate(improper))
+                .await;
+
+            for connection_manager in watcher.connection_managers.iter_mut() {
+                connection_manager.checkpoint();
+            }
+            Arc::get_mut(&mut watcher.core.home).unwrap().checkpoint();
+        })
+        .await
+    }
 }
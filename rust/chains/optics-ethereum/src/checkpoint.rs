@@ -0,0 +1,193 @@
+use color_eyre::{eyre::eyre, Result};
+use ethers::core::types::H256;
+use optics_core::db::{DbError, HomeDB, ReplicaDB};
+use optics_core::{Decode, Encode, OpticsError};
+use tracing::{instrument, warn};
+
+use std::sync::Arc;
+
+/// The hash of the block the indexer stopped at, and the height of the
+/// checkpoint before it. Used to detect reorgs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct IndexCheckpoint {
+    block_hash: H256,
+    previous_height: u32,
+}
+
+impl Encode for IndexCheckpoint {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        let mut written = 0;
+        written += self.block_hash.write_to(writer)?;
+        written += self.previous_height.write_to(writer)?;
+        Ok(written)
+    }
+}
+
+impl Decode for IndexCheckpoint {
+    fn read_from<R>(reader: &mut R) -> Result<Self, OpticsError>
+    where
+        R: std::io::Read,
+        Self: Sized,
+    {
+        Ok(Self {
+            block_hash: H256::read_from(reader)?,
+            previous_height: u32::read_from(reader)?,
+        })
+    }
+}
+
+/// Where an indexer keeps its progress and checkpoints
+pub(crate) trait CheckpointDB {
+    /// The height the indexer has inspected up to, if any
+    fn last_inspected(&self) -> Result<Option<u32>, DbError>;
+
+    /// Record the height the indexer has inspected up to
+    fn store_last_inspected(&self, height: u32) -> Result<(), DbError>;
+
+    /// The checkpoint recorded at `height`, if any
+    fn checkpoint_at(&self, height: u32) -> Result<Option<IndexCheckpoint>, DbError>;
+
+    /// Record a checkpoint at `height`
+    fn store_checkpoint_at(&self, height: u32, checkpoint: &IndexCheckpoint)
+        -> Result<(), DbError>;
+}
+
+macro_rules! impl_checkpoint_db {
+    ($db:ty, $last_inspected:literal, $checkpoint:literal) => {
+        impl CheckpointDB for $db {
+            fn last_inspected(&self) -> Result<Option<u32>, DbError> {
+                self.retrieve_decodable("", $last_inspected)
+            }
+
+            fn store_last_inspected(&self, height: u32) -> Result<(), DbError> {
+                self.store_encodable("", $last_inspected, &height)
+            }
+
+            fn checkpoint_at(&self, height: u32) -> Result<Option<IndexCheckpoint>, DbError> {
+                self.retrieve_keyed_decodable($checkpoint, &height)
+            }
+
+            fn store_checkpoint_at(
+                &self,
+                height: u32,
+                checkpoint: &IndexCheckpoint,
+            ) -> Result<(), DbError> {
+                self.store_keyed_encodable($checkpoint, &height, checkpoint)
+            }
+        }
+    };
+}
+
+impl_checkpoint_db!(HomeDB, "homeIndexerLastInspected", "homeIndexerCheckpoint_");
+impl_checkpoint_db!(
+    ReplicaDB,
+    "replicaIndexerLastInspected",
+    "replicaIndexerCheckpoint_"
+);
+
+/// Links the ranges an indexer has inspected by the hashes of their last
+/// blocks, so that reorgs of already indexed blocks can be detected
+#[derive(Debug)]
+pub(crate) struct Checkpoints<M> {
+    provider: Arc<M>,
+    from_height: u32,
+}
+
+impl<M> Checkpoints<M>
+where
+    M: ethers::providers::Middleware + 'static,
+{
+    /// Instantiate checkpoints for an indexer starting at `from_height`
+    pub(crate) fn new(provider: Arc<M>, from_height: u32) -> Self {
+        Self {
+            provider,
+            from_height,
+        }
+    }
+
+    /// Fetch the hash of the block at `height`. Indexers fetch the hash of
+    /// a range's last block before querying the range, so that the
+    /// checkpoint describes the chain the range was read from
+    pub(crate) async fn block_hash(&self, height: u32) -> Result<H256> {
+        self.provider
+            .get_block(u64::from(height))
+            .await?
+            .and_then(|block| block.hash)
+            .ok_or_else(|| eyre!("No block hash for height {}", height))
+    }
+
+    /// Record `block_hash` as the hash of the block at `height`, linked to
+    /// the previous checkpoint, and advance the last inspected height to
+    /// `height`.
+    ///
+    /// Returns `Ok(false)` without storing anything if the block at `height`
+    /// is no longer `block_hash`, as the range may have been read from a
+    /// chain that has since been reorged out.
+    pub(crate) async fn store_checkpoint<D: CheckpointDB>(
+        &self,
+        db: &D,
+        height: u32,
+        previous_height: u32,
+        block_hash: H256,
+    ) -> Result<bool> {
+        let canonical_hash = self.block_hash(height).await?;
+        if canonical_hash != block_hash {
+            warn!(
+                height,
+                expected_hash = ?block_hash,
+                canonical_hash = ?canonical_hash,
+                "Block at height {} was reorged while its range was indexed",
+                height
+            );
+            return Ok(false);
+        }
+
+        let checkpoint = IndexCheckpoint {
+            block_hash,
+            previous_height,
+        };
+        db.store_checkpoint_at(height, &checkpoint)?;
+        db.store_last_inspected(height)?;
+        Ok(true)
+    }
+
+    /// Walk back through the stored checkpoints, starting at `height`, until
+    /// one matches the canonical chain. Returns `Ok(None)` if the checkpoint
+    /// at `height` is still canonical, and the height of the latest
+    /// canonical checkpoint otherwise.
+    #[instrument(err, skip(self, db))]
+    pub(crate) async fn find_reorg<D: CheckpointDB>(
+        &self,
+        db: &D,
+        height: u32,
+    ) -> Result<Option<u32>> {
+        let mut height = height;
+        let mut reorged = false;
+
+        while let Some(checkpoint) = db.checkpoint_at(height)? {
+            if self.block_hash(height).await? == checkpoint.block_hash {
+                break;
+            }
+
+            warn!(
+                height,
+                stored_hash = ?checkpoint.block_hash,
+                "Checkpoint at height {} is no longer canonical",
+                height
+            );
+            reorged = true;
+
+            // We have run out of checkpoints. Reindex from the start
+            if checkpoint.previous_height >= height {
+                height = self.from_height;
+                break;
+            }
+            height = checkpoint.previous_height;
+        }
+
+        Ok(if reorged { Some(height) } else { None })
+    }
+}
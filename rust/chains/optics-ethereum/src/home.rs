@@ -1,7 +1,7 @@
 #![allow(clippy::enum_variant_names)]
 
 use async_trait::async_trait;
-use color_eyre::Result;
+use color_eyre::{eyre::eyre, Result};
 use ethers::contract::abigen;
 use ethers::core::types::{Signature, H256};
 use optics_core::db::{HomeDB, DB};
@@ -22,16 +22,15 @@ use optics_core::{
 use tokio::task::JoinHandle;
 use tokio::time::sleep;
 use tokio::try_join;
-use tracing::{info, info_span, instrument};
+use tracing::{info, info_span, instrument, warn};
 use tracing::{instrument::Instrumented, Instrument};
 
 use std::cmp::min;
 use std::time::Duration;
-use std::{convert::TryFrom, convert::TryInto, error::Error as StdError, sync::Arc};
+use std::{convert::TryFrom, error::Error as StdError, sync::Arc};
 
-use crate::report_tx;
-
-static LAST_INSPECTED: &str = "homeIndexerLastInspected";
+use crate::checkpoint::{CheckpointDB, Checkpoints};
+use crate::{report_tx, TxManager};
 
 #[allow(missing_docs)]
 abigen!(
@@ -46,8 +45,10 @@ where
     contract: Arc<EthereumHomeInternal<M>>,
     provider: Arc<M>,
     home_db: HomeDB,
+    checkpoints: Checkpoints<M>,
     from_height: u32,
     chunk_size: u32,
+    confirmations: u32,
     indexed_height: prometheus::IntGauge,
 }
 
@@ -55,8 +56,8 @@ impl<M> HomeIndexer<M>
 where
     M: ethers::providers::Middleware + 'static,
 {
-    #[instrument(err, skip(self))]
-    async fn sync_updates(&self, from: u32, to: u32) -> Result<()> {
+    #[instrument(err, skip(self, batch))]
+    async fn sync_updates(&self, batch: &HomeDB, from: u32, to: u32) -> Result<()> {
         let mut events = self
             .contract
             .update_filter()
@@ -93,12 +94,11 @@ where
         });
 
         for update_with_meta in updates_with_meta {
-            self.home_db
-                .store_latest_update(&update_with_meta.signed_update)?;
-            self.home_db.store_update_metadata(
-                update_with_meta.signed_update.update.new_root,
-                update_with_meta.metadata,
-            )?;
+            let new_root = update_with_meta.signed_update.update.new_root;
+            let block_number = update_with_meta.metadata.block_number;
+            batch.store_latest_update(&update_with_meta.signed_update)?;
+            batch.store_update_metadata(new_root, update_with_meta.metadata)?;
+            batch.store_update_timestamp(new_root, self.block_timestamp(block_number).await?)?;
 
             info!(
                 "Stored new update in db. Block number: {}. Previous root: {}. New root: {}.",
@@ -111,45 +111,59 @@ where
         Ok(())
     }
 
-    #[instrument(err, skip(self))]
-    async fn sync_leaves(&self, from: u32, to: u32) -> Result<()> {
+    #[instrument(err, skip(self, batch))]
+    async fn sync_leaves(&self, batch: &HomeDB, from: u32, to: u32) -> Result<()> {
         let events = self
             .contract
             .dispatch_filter()
             .from_block(from)
             .to_block(to)
-            .query()
+            .query_with_meta()
             .await?;
 
-        let messages = events.into_iter().map(|f| RawCommittedMessage {
-            leaf_index: f.leaf_index.as_u32(),
-            committed_root: f.committed_root.into(),
-            message: f.message,
+        let messages = events.into_iter().map(|(event, meta)| {
+            (
+                RawCommittedMessage {
+                    leaf_index: event.leaf_index.as_u32(),
+                    committed_root: event.committed_root.into(),
+                    message: event.message,
+                },
+                meta.block_number.as_u64(),
+            )
         });
 
-        for message in messages {
-            self.home_db.store_raw_committed_message(&message)?;
+        for (message, block_number) in messages {
+            batch.store_raw_committed_message(&message)?;
+            batch.store_leaf_block_number(message.leaf_index, block_number)?;
 
-            let committed_message: CommittedMessage = message.try_into()?;
+            let view = message.view()?;
             info!(
                 "Stored new message in db. Leaf index: {}. Origin: {}. Destination: {}. Nonce: {}.",
-                &committed_message.leaf_index,
-                &committed_message.message.origin,
-                &committed_message.message.destination,
-                &committed_message.message.nonce
+                message.leaf_index,
+                view.origin(),
+                view.destination(),
+                view.nonce()
             );
         }
 
         Ok(())
     }
 
+    async fn block_timestamp(&self, height: u64) -> Result<u64> {
+        self.provider
+            .get_block(height)
+            .await?
+            .map(|block| block.timestamp.as_u64())
+            .ok_or_else(|| eyre!("No block at height {}", height))
+    }
+
     fn spawn(self) -> Instrumented<JoinHandle<Result<()>>> {
         let span = info_span!("HomeIndexer");
 
         tokio::spawn(async move {
             let mut next_height: u32 = self
                 .home_db
-                .retrieve_decodable("", LAST_INSPECTED)
+                .last_inspected()
                 .expect("db failure")
                 .unwrap_or(self.from_height);
             info!(
@@ -159,7 +173,43 @@ where
 
             loop {
                 self.indexed_height.set(next_height as i64);
-                let tip = self.provider.get_block_number().await?.as_u32();
+
+                // If the last checkpoint we recorded has been reorged out,
+                // roll back everything indexed after the latest canonical
+                // checkpoint and reindex from there.
+                if let Some(height) = self
+                    .checkpoints
+                    .find_reorg(&self.home_db, next_height)
+                    .await?
+                {
+                    warn!(
+                        from = next_height,
+                        to = height,
+                        "Reorg detected. Rolling back indexer from {} to {}",
+                        next_height,
+                        height
+                    );
+                    let batch = self.home_db.batch();
+                    batch.rollback_to_block(height as u64)?;
+                    batch.store_last_inspected(height)?;
+                    batch.commit()?;
+                    next_height = height;
+                    continue;
+                }
+
+                let tip = self
+                    .provider
+                    .get_block_number()
+                    .await?
+                    .as_u32()
+                    .saturating_sub(self.confirmations);
+                // nothing new to index. Block `next_height` was included in
+                // the previous range
+                if tip <= next_height {
+                    sleep(Duration::from_secs(100)).await;
+                    continue;
+                }
+
                 let candidate = next_height + self.chunk_size;
                 let to = min(tip, candidate);
 
@@ -171,14 +221,28 @@ where
                     to
                 );
 
+                // Pin the chain the range is read from. If the range's last
+                // block changes while the range is queried, the results are
+                // discarded and the range is indexed again
+                let block_hash = self.checkpoints.block_hash(to).await?;
+
+                // Everything indexed from the range is committed along with
+                // the checkpoint, so the checkpoint only advances once the
+                // range's data is durable
+                let batch = self.home_db.batch();
                 // TODO(james): these shouldn't have to go in lockstep
                 try_join!(
-                    self.sync_updates(next_height, to),
-                    self.sync_leaves(next_height, to)
+                    self.sync_updates(&batch, next_height, to),
+                    self.sync_leaves(&batch, next_height, to)
                 )?;
-
-                self.home_db
-                    .store_encodable("", LAST_INSPECTED, &next_height)?;
+                if !self
+                    .checkpoints
+                    .store_checkpoint(&*batch, to, next_height, block_hash)
+                    .await?
+                {
+                    continue;
+                }
+                batch.commit()?;
                 next_height = to;
                 // sleep here if we've caught up
                 if to == tip {
@@ -201,6 +265,7 @@ where
     domain: u32,
     name: String,
     provider: Arc<M>,
+    tx_manager: TxManager,
 }
 
 impl<M> EthereumHome<M>
@@ -217,6 +282,7 @@ where
             address,
         }: &ContractLocator,
         db: DB,
+        tx_manager: TxManager,
     ) -> Self {
         Self {
             contract: Arc::new(EthereumHomeInternal::new(address, provider.clone())),
@@ -224,6 +290,7 @@ where
             name: name.to_owned(),
             home_db: HomeDB::new(db, name.to_owned()),
             provider,
+            tx_manager,
         }
     }
 }
@@ -274,12 +341,11 @@ where
         &self,
         old_root: H256,
     ) -> Result<Option<SignedUpdate>, ChainCommunicationError> {
-        loop {
-            if let Some(update) = self.home_db.update_by_previous_root(old_root)? {
-                return Ok(Some(update));
-            }
-            sleep(Duration::from_millis(500)).await;
-        }
+        Ok(Some(
+            self.home_db
+                .wait_for_update_by_previous_root(old_root)
+                .await?,
+        ))
     }
 
     #[tracing::instrument(err, skip(self))]
@@ -287,12 +353,9 @@ where
         &self,
         new_root: H256,
     ) -> Result<Option<SignedUpdate>, ChainCommunicationError> {
-        loop {
-            if let Some(update) = self.home_db.update_by_new_root(new_root)? {
-                return Ok(Some(update));
-            }
-            sleep(Duration::from_millis(500)).await;
-        }
+        Ok(Some(
+            self.home_db.wait_for_update_by_new_root(new_root).await?,
+        ))
     }
 
     #[tracing::instrument(err, skip(self), fields(hexSignature = %format!("0x{}", hex::encode(update.signature.to_vec()))))]
@@ -303,7 +366,7 @@ where
             update.signature.to_vec(),
         );
 
-        Ok(report_tx!(tx).into())
+        Ok(report_tx!(self, tx).into())
     }
 
     #[tracing::instrument(err, skip(self))]
@@ -320,7 +383,7 @@ where
             double.0.signature.to_vec(),
             double.1.signature.to_vec(),
         );
-        let response = report_tx!(tx);
+        let response = report_tx!(self, tx);
 
         Ok(response.into())
     }
@@ -340,14 +403,17 @@ where
         &self,
         from_height: u32,
         chunk_size: u32,
+        confirmations: u32,
         indexed_height: prometheus::IntGauge,
     ) -> Instrumented<JoinHandle<Result<()>>> {
         let indexer = HomeIndexer {
             contract: self.contract.clone(),
             home_db: self.home_db.clone(),
+            checkpoints: Checkpoints::new(self.provider.clone(), from_height),
             from_height,
             provider: self.provider.clone(),
             chunk_size,
+            confirmations,
             indexed_height,
         };
         indexer.spawn()
@@ -359,12 +425,11 @@ where
         destination: u32,
         nonce: u32,
     ) -> Result<Option<RawCommittedMessage>, ChainCommunicationError> {
-        loop {
-            if let Some(update) = self.home_db.message_by_nonce(destination, nonce)? {
-                return Ok(Some(update));
-            }
-            sleep(Duration::from_millis(500)).await;
-        }
+        Ok(Some(
+            self.home_db
+                .wait_for_message_by_nonce(destination, nonce)
+                .await?,
+        ))
     }
 
     #[tracing::instrument(err, skip(self))]
@@ -372,24 +437,14 @@ where
         &self,
         leaf: H256,
     ) -> Result<Option<RawCommittedMessage>, ChainCommunicationError> {
-        loop {
-            if let Some(update) = self.home_db.message_by_leaf(leaf)? {
-                return Ok(Some(update));
-            }
-            sleep(Duration::from_millis(500)).await;
-        }
+        Ok(Some(self.home_db.wait_for_message_by_leaf(leaf).await?))
     }
 
     async fn leaf_by_tree_index(
         &self,
         tree_index: usize,
     ) -> Result<Option<H256>, ChainCommunicationError> {
-        loop {
-            if let Some(update) = self.home_db.leaf_by_leaf_index(tree_index as u32)? {
-                return Ok(Some(update));
-            }
-            sleep(Duration::from_millis(500)).await;
-        }
+        Ok(Some(self.home_db.wait_for_leaf(tree_index as u32).await?))
     }
 
     #[tracing::instrument(err, skip(self))]
@@ -405,7 +460,7 @@ where
             message.body.clone(),
         );
 
-        Ok(report_tx!(tx).into())
+        Ok(report_tx!(self, tx).into())
     }
 
     async fn queue_contains(&self, root: H256) -> Result<bool, ChainCommunicationError> {
@@ -423,7 +478,7 @@ where
             update.signature.to_vec(),
         );
 
-        Ok(report_tx!(tx).into())
+        Ok(report_tx!(self, tx).into())
     }
 
     #[tracing::instrument(err, skip(self))]
@@ -13,6 +13,18 @@ use std::sync::Arc;
 #[macro_use]
 mod macros;
 
+/// Multi-endpoint JSON-RPC clients
+mod rpc_clients;
+pub use rpc_clients::{FallbackClient, QuorumClient, RpcClientError};
+
+/// Transaction submission with gas escalation
+mod tx_manager;
+pub use tx_manager::*;
+
+/// Block-hash checkpoints for reorg-aware indexing
+#[cfg(not(doctest))]
+mod checkpoint;
+
 /// Home abi
 #[cfg(not(doctest))]
 mod home;
@@ -39,6 +51,44 @@ pub enum Connection {
         /// Fully qualified string to connect to
         url: String,
     },
+    /// Several HTTP endpoints, tried in order. Endpoints that can't be
+    /// reached are skipped with exponential backoff
+    Fallback {
+        /// Comma-separated fully qualified strings to connect to, in
+        /// priority order
+        urls: String,
+    },
+    /// Several HTTP endpoints, each queried on every request. Responses are
+    /// only accepted if a quorum of endpoints agree
+    Quorum {
+        /// Comma-separated fully qualified strings to connect to
+        urls: String,
+        /// The number of endpoints that must agree. Defaults to a majority
+        quorum: Option<String>,
+    },
+}
+
+impl Connection {
+    /// Split a comma-separated list of urls
+    fn split_urls(urls: &str) -> Vec<String> {
+        urls.split(',')
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .map(ToOwned::to_owned)
+            .collect()
+    }
+
+    /// Instantiate a `FallbackClient` from comma-separated urls
+    pub fn fallback_client(urls: &str) -> Result<FallbackClient, RpcClientError> {
+        FallbackClient::new(&Self::split_urls(urls))
+    }
+
+    /// Instantiate a `QuorumClient` from comma-separated urls and an
+    /// optional quorum
+    pub fn quorum_client(urls: &str, quorum: Option<&str>) -> color_eyre::Result<QuorumClient> {
+        let quorum = quorum.map(str::parse::<usize>).transpose()?;
+        Ok(QuorumClient::new(&Self::split_urls(urls), quorum)?)
+    }
 }
 
 impl Default for Connection {
@@ -59,12 +109,25 @@ pub struct Chain {
     ethers: ethers::providers::Provider<ethers::providers::Http>,
 }
 
-contract!(make_replica, EthereumReplica, Replica,);
-contract!(make_home, EthereumHome, Home, db: optics_core::db::DB);
+contract!(
+    make_replica,
+    EthereumReplica,
+    Replica,
+    db: optics_core::db::DB,
+    tx_manager: TxManager
+);
+contract!(
+    make_home,
+    EthereumHome,
+    Home,
+    db: optics_core::db::DB,
+    tx_manager: TxManager
+);
 contract!(
     make_conn_manager,
     EthereumConnectionManager,
     ConnectionManager,
+    tx_manager: TxManager
 );
 
 #[async_trait::async_trait]
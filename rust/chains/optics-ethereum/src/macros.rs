@@ -1,41 +1,13 @@
-/// Dispatches a transaction, logs the tx id, and returns the result
+/// Dispatches a transaction through the contract's `TxManager`, and returns
+/// the receipt once it is included
 #[macro_export]
 macro_rules! report_tx {
-    ($tx:expr) => {{
-
-        // "0x..."
-        let data = format!("0x{}", hex::encode(&$tx.tx.data().map(|b| b.to_vec()).unwrap_or_default()));
-
-        let to = $tx.tx.to().cloned().unwrap_or_else(|| ethers::types::NameOrAddress::Address(Default::default()));
-
-        tracing::info!(
-            to = ?to,
-            data = %data,
-            "Dispatching transaction"
-        );
-        let dispatch_fut = $tx.send();
-        let dispatched = dispatch_fut.await?;
-
-        let tx_hash: ethers::core::types::H256 = *dispatched;
-
-        tracing::info!(
-            to = ?to,
-            data = %data,
-            tx_hash = ?tx_hash,
-            "Dispatched tx with tx_hash {:?}",
-            tx_hash
-        );
-
-        let result = dispatched
+    ($contract:expr, $tx:expr) => {
+        $contract
+            .tx_manager
+            .send($contract.provider.as_ref(), $tx.tx)
             .await?
-            .ok_or_else(|| optics_core::ChainCommunicationError::DroppedError(tx_hash))?;
-
-        tracing::info!(
-            "confirmed transaction with tx_hash {:?}",
-            result.transaction_hash
-        );
-        result
-    }};
+    };
 }
 
 macro_rules! contract {
@@ -71,6 +43,10 @@ macro_rules! contract {
             Arc::new(ethers::providers::Provider::<ethers::providers::Http>::try_from($url.as_ref())?);
         contract!(@finish provider, $($tail)*)
     }};
+    (@client $client:expr, $($tail:tt)*) => {{
+        let provider = Arc::new(ethers::providers::Provider::new($client));
+        contract!(@finish provider, $($tail)*)
+    }};
     ($name:ident, $abi:ident, $trait:ident, $($n:ident:$t:ty),*)  => {
         #[doc = "Cast a contract locator to a live contract handle"]
         pub async fn $name(conn: Connection, locator: &ContractLocator, signer: Option<Signers>, $($n:$t),*) -> color_eyre::Result<Box<dyn $trait>> {
@@ -81,6 +57,14 @@ macro_rules! contract {
                 Connection::Ws { url } => {
                     contract!(@ws url, $abi, signer, locator, $($n),*)
                 }
+                Connection::Fallback { urls } => {
+                    let client = Connection::fallback_client(&urls)?;
+                    contract!(@client client, $abi, signer, locator, $($n),*)
+                }
+                Connection::Quorum { urls, quorum } => {
+                    let client = Connection::quorum_client(&urls, quorum.as_deref())?;
+                    contract!(@client client, $abi, signer, locator, $($n),*)
+                }
             };
             Ok(b)
         }
@@ -1,14 +1,24 @@
 #![allow(clippy::enum_variant_names)]
 
 use async_trait::async_trait;
+use color_eyre::Result;
 use ethers::contract::abigen;
 use ethers::core::types::{Signature, H256};
+use optics_core::db::{ProcessedMessage, ReplicaDB, DB};
 use optics_core::ContractLocator;
 use optics_core::{accumulator::merkle::Proof, *};
-
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+use tokio::try_join;
+use tracing::{info, info_span, instrument, warn};
+use tracing::{instrument::Instrumented, Instrument};
+
+use std::cmp::min;
+use std::time::Duration;
 use std::{convert::TryFrom, error::Error as StdError, sync::Arc};
 
-use crate::report_tx;
+use crate::checkpoint::{CheckpointDB, Checkpoints};
+use crate::{report_tx, TxManager};
 
 #[allow(missing_docs)]
 abigen!(
@@ -20,15 +30,254 @@ abigen!(
      },
 );
 
+fn signed_update(
+    home_domain: u32,
+    old_root: H256,
+    new_root: H256,
+    signature: &[u8],
+) -> SignedUpdate {
+    let signature = Signature::try_from(signature).expect("chain accepted invalid signature");
+
+    let update = Update {
+        home_domain,
+        previous_root: old_root,
+        new_root,
+    };
+
+    SignedUpdate { update, signature }
+}
+
+struct ReplicaIndexer<M>
+where
+    M: ethers::providers::Middleware,
+{
+    contract: Arc<EthereumReplicaInternal<M>>,
+    provider: Arc<M>,
+    replica_db: ReplicaDB,
+    checkpoints: Checkpoints<M>,
+    from_height: u32,
+    chunk_size: u32,
+    confirmations: u32,
+    indexed_height: prometheus::IntGauge,
+}
+
+impl<M> ReplicaIndexer<M>
+where
+    M: ethers::providers::Middleware + 'static,
+{
+    #[instrument(err, skip(self, batch))]
+    async fn sync_updates(&self, batch: &ReplicaDB, from: u32, to: u32) -> Result<()> {
+        let events = self
+            .contract
+            .update_filter()
+            .from_block(from)
+            .to_block(to)
+            .query_with_meta()
+            .await?;
+
+        for (event, meta) in events {
+            let signed = signed_update(
+                event.home_domain,
+                event.old_root.into(),
+                event.new_root.into(),
+                &event.signature,
+            );
+            let metadata = UpdateMeta {
+                block_number: meta.block_number.as_u64(),
+            };
+            batch.store_update(&signed, metadata)?;
+
+            info!(
+                "Stored new replica update in db. Block number: {}. Previous root: {}. New root: {}.",
+                &metadata.block_number,
+                &signed.update.previous_root,
+                &signed.update.new_root,
+            );
+        }
+
+        Ok(())
+    }
+
+    #[instrument(err, skip(self, batch))]
+    async fn sync_double_updates(
+        &self,
+        batch: &ReplicaDB,
+        from: u32,
+        to: u32,
+        home_domain: u32,
+    ) -> Result<()> {
+        let events = self
+            .contract
+            .double_update_filter()
+            .from_block(from)
+            .to_block(to)
+            .query_with_meta()
+            .await?;
+
+        for (event, meta) in events {
+            let old_root = event.old_root.into();
+            let double = DoubleUpdate(
+                signed_update(
+                    home_domain,
+                    old_root,
+                    event.new_root[0].into(),
+                    &event.signature,
+                ),
+                signed_update(
+                    home_domain,
+                    old_root,
+                    event.new_root[1].into(),
+                    &event.signature_2,
+                ),
+            );
+            batch.store_double_update(&double, meta.block_number.as_u64())?;
+
+            info!("Stored double update in db. Previous root: {}.", &old_root);
+        }
+
+        Ok(())
+    }
+
+    #[instrument(err, skip(self, batch))]
+    async fn sync_processed(&self, batch: &ReplicaDB, from: u32, to: u32) -> Result<()> {
+        let events = self
+            .contract
+            .process_filter()
+            .from_block(from)
+            .to_block(to)
+            .query_with_meta()
+            .await?;
+
+        for (event, meta) in events {
+            let leaf: H256 = event.message_hash.into();
+            batch.store_processed(
+                leaf,
+                ProcessedMessage {
+                    success: event.success,
+                    block_number: meta.block_number.as_u64(),
+                },
+            )?;
+
+            info!(
+                "Stored processed message in db. Leaf: {}. Success: {}.",
+                &leaf, &event.success
+            );
+        }
+
+        Ok(())
+    }
+
+    fn spawn(self) -> Instrumented<JoinHandle<Result<()>>> {
+        let span = info_span!("ReplicaIndexer");
+
+        tokio::spawn(async move {
+            // Double update events don't include the home domain
+            let home_domain = self.contract.remote_domain().call().await?;
+
+            let mut next_height: u32 = self
+                .replica_db
+                .last_inspected()
+                .expect("db failure")
+                .unwrap_or(self.from_height);
+            info!(
+                next_height = next_height,
+                "resuming indexer from {}", next_height
+            );
+
+            loop {
+                self.indexed_height.set(next_height as i64);
+
+                // If the last checkpoint we recorded has been reorged out,
+                // roll back everything indexed after the latest canonical
+                // checkpoint and reindex from there.
+                if let Some(height) = self
+                    .checkpoints
+                    .find_reorg(&self.replica_db, next_height)
+                    .await?
+                {
+                    warn!(
+                        from = next_height,
+                        to = height,
+                        "Reorg detected. Rolling back indexer from {} to {}",
+                        next_height,
+                        height
+                    );
+                    let batch = self.replica_db.batch();
+                    batch.rollback_to_block(height as u64)?;
+                    batch.store_last_inspected(height)?;
+                    batch.commit()?;
+                    next_height = height;
+                    continue;
+                }
+
+                let tip = self
+                    .provider
+                    .get_block_number()
+                    .await?
+                    .as_u32()
+                    .saturating_sub(self.confirmations);
+                // nothing new to index. Block `next_height` was included in
+                // the previous range
+                if tip <= next_height {
+                    sleep(Duration::from_secs(100)).await;
+                    continue;
+                }
+
+                let to = min(tip, next_height + self.chunk_size);
+
+                info!(
+                    next_height = next_height,
+                    to = to,
+                    "indexing block heights {}...{}",
+                    next_height,
+                    to
+                );
+
+                // Pin the chain the range is read from. If the range's last
+                // block changes while the range is queried, the results are
+                // discarded and the range is indexed again
+                let block_hash = self.checkpoints.block_hash(to).await?;
+
+                // Everything indexed from the range is committed along with
+                // the checkpoint, so the checkpoint only advances once the
+                // range's data is durable
+                let batch = self.replica_db.batch();
+                try_join!(
+                    self.sync_updates(&batch, next_height, to),
+                    self.sync_double_updates(&batch, next_height, to, home_domain),
+                    self.sync_processed(&batch, next_height, to)
+                )?;
+                if !self
+                    .checkpoints
+                    .store_checkpoint(&*batch, to, next_height, block_hash)
+                    .await?
+                {
+                    continue;
+                }
+                batch.commit()?;
+                next_height = to;
+                // sleep here if we've caught up
+                if to == tip {
+                    sleep(Duration::from_secs(100)).await;
+                }
+            }
+        })
+        .instrument(span)
+    }
+}
+
 /// A struct that provides access to an Ethereum replica contract
 #[derive(Debug)]
 pub struct EthereumReplica<M>
 where
     M: ethers::providers::Middleware,
 {
-    contract: EthereumReplicaInternal<M>,
+    contract: Arc<EthereumReplicaInternal<M>>,
+    replica_db: ReplicaDB,
     domain: u32,
     name: String,
+    provider: Arc<M>,
+    tx_manager: TxManager,
 }
 
 impl<M> EthereumReplica<M>
@@ -44,11 +293,16 @@ where
             domain,
             address,
         }: &ContractLocator,
+        db: DB,
+        tx_manager: TxManager,
     ) -> Self {
         Self {
-            contract: EthereumReplicaInternal::new(address, provider),
+            contract: Arc::new(EthereumReplicaInternal::new(address, provider.clone())),
+            replica_db: ReplicaDB::new(db, name.to_owned()),
             domain: *domain,
             name: name.to_owned(),
+            provider,
+            tx_manager,
         }
     }
 }
@@ -99,27 +353,7 @@ where
         &self,
         old_root: H256,
     ) -> Result<Option<SignedUpdate>, ChainCommunicationError> {
-        self.contract
-            .update_filter()
-            .from_block(0)
-            .topic2(old_root)
-            .query()
-            .await?
-            .first()
-            .map(|event| {
-                let signature = Signature::try_from(event.signature.as_slice())
-                    .expect("chain accepted invalid signature");
-
-                let update = Update {
-                    home_domain: event.home_domain,
-                    previous_root: event.old_root.into(),
-                    new_root: event.new_root.into(),
-                };
-
-                SignedUpdate { update, signature }
-            })
-            .map(Ok)
-            .transpose()
+        Ok(self.replica_db.update_by_previous_root(old_root)?)
     }
 
     #[tracing::instrument(err)]
@@ -127,27 +361,7 @@ where
         &self,
         new_root: H256,
     ) -> Result<Option<SignedUpdate>, ChainCommunicationError> {
-        self.contract
-            .update_filter()
-            .from_block(0)
-            .topic3(new_root)
-            .query()
-            .await?
-            .first()
-            .map(|event| {
-                let signature = Signature::try_from(event.signature.as_slice())
-                    .expect("chain accepted invalid signature");
-
-                let update = Update {
-                    home_domain: event.home_domain,
-                    previous_root: event.old_root.into(),
-                    new_root: event.new_root.into(),
-                };
-
-                SignedUpdate { update, signature }
-            })
-            .map(Ok)
-            .transpose()
+        Ok(self.replica_db.update_by_new_root(new_root)?)
     }
 
     #[tracing::instrument(err)]
@@ -158,7 +372,7 @@ where
             update.signature.to_vec(),
         );
 
-        let result = report_tx!(tx);
+        let result = report_tx!(self, tx);
         Ok(result.into())
     }
 
@@ -177,7 +391,7 @@ where
             double.1.signature.to_vec(),
         );
 
-        Ok(report_tx!(tx).into())
+        Ok(report_tx!(self, tx).into())
     }
 }
 
@@ -206,13 +420,13 @@ where
             .contract
             .prove(proof.leaf.into(), sol_proof, proof.index.into());
 
-        Ok(report_tx!(tx).into())
+        Ok(report_tx!(self, tx).into())
     }
 
     #[tracing::instrument(err)]
     async fn process(&self, message: &OpticsMessage) -> Result<TxOutcome, ChainCommunicationError> {
         let tx = self.contract.process(message.to_vec());
-        Ok(report_tx!(tx).into())
+        Ok(report_tx!(self, tx).into())
     }
 
     #[tracing::instrument(err)]
@@ -230,7 +444,7 @@ where
         let tx = self
             .contract
             .prove_and_process(message.to_vec(), sol_proof, proof.index.into());
-        Ok(report_tx!(tx).into())
+        Ok(report_tx!(self, tx).into())
     }
 
     #[tracing::instrument(err)]
@@ -247,4 +461,25 @@ where
     async fn acceptable_root(&self, root: H256) -> Result<bool, ChainCommunicationError> {
         Ok(self.contract.acceptable_root(root.into()).call().await?)
     }
+
+    /// Start an indexing task that syncs chain state
+    fn index(
+        &self,
+        from_height: u32,
+        chunk_size: u32,
+        confirmations: u32,
+        indexed_height: prometheus::IntGauge,
+    ) -> Instrumented<JoinHandle<Result<()>>> {
+        let indexer = ReplicaIndexer {
+            contract: self.contract.clone(),
+            provider: self.provider.clone(),
+            replica_db: self.replica_db.clone(),
+            checkpoints: Checkpoints::new(self.provider.clone(), from_height),
+            from_height,
+            chunk_size,
+            confirmations,
+            indexed_height,
+        };
+        indexer.spawn()
+    }
 }
@@ -0,0 +1,539 @@
+use async_trait::async_trait;
+use ethers::providers::{Http, HttpClientError, JsonRpcClient, ProviderError};
+use futures_util::future::join_all;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::{
+    fmt::Debug,
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tracing::{instrument, warn};
+
+/// Backoff after the first failure of an endpoint. Doubles with each
+/// consecutive failure.
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+/// Longest an endpoint will be skipped after failing
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Errors from multi-endpoint clients
+#[derive(Debug, thiserror::Error)]
+pub enum RpcClientError {
+    /// No endpoints were configured
+    #[error("No RPC endpoints configured")]
+    NoEndpoints,
+    /// An endpoint URL could not be parsed
+    #[error("Invalid RPC url {0}")]
+    InvalidUrl(String),
+    /// The quorum is larger than the number of endpoints
+    #[error("Quorum of {quorum} is unreachable with {endpoints} endpoints")]
+    UnreachableQuorum {
+        /// The configured quorum
+        quorum: usize,
+        /// The number of endpoints
+        endpoints: usize,
+    },
+    /// Every endpoint failed. Contains the last error
+    #[error("All RPC endpoints failed. Last error: {0}")]
+    AllFailed(HttpClientError),
+    /// The endpoint returned a JSON-RPC error. This is a response from the
+    /// node, not an endpoint failure
+    #[error(transparent)]
+    JsonRpcError(HttpClientError),
+    /// Too few endpoints agreed on a response
+    #[error("No {quorum} endpoints agreed on a response to {method}. Responses: {responses:?}")]
+    NoQuorum {
+        /// The request method
+        method: String,
+        /// The configured quorum
+        quorum: usize,
+        /// The response (or error) from each endpoint
+        responses: Vec<Result<Value, String>>,
+    },
+    /// Response could not be deserialized into the requested type
+    #[error(transparent)]
+    SerdeJson(#[from] serde_json::Error),
+}
+
+impl From<RpcClientError> for ProviderError {
+    fn from(e: RpcClientError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(e))
+    }
+}
+
+fn parse_urls(urls: &[String]) -> Result<Vec<Http>, RpcClientError> {
+    if urls.is_empty() {
+        return Err(RpcClientError::NoEndpoints);
+    }
+    urls.iter()
+        .map(|url| Http::from_str(url).map_err(|_| RpcClientError::InvalidUrl(url.clone())))
+        .collect()
+}
+
+/// Health of a single endpoint
+#[derive(Debug, Default, Clone, Copy)]
+struct Health {
+    consecutive_failures: u32,
+    retry_at: Option<Instant>,
+}
+
+impl Health {
+    fn available(&self, now: Instant) -> bool {
+        self.retry_at.map_or(true, |retry_at| retry_at <= now)
+    }
+
+    fn record_success(&mut self) {
+        *self = Default::default();
+    }
+
+    fn record_failure(&mut self, now: Instant) {
+        self.consecutive_failures += 1;
+        let backoff = BASE_BACKOFF
+            .checked_mul(1 << (self.consecutive_failures - 1).min(16))
+            .map_or(MAX_BACKOFF, |backoff| backoff.min(MAX_BACKOFF));
+        self.retry_at = Some(now + backoff);
+    }
+}
+
+/// A JSON-RPC client that sends each request to the first healthy endpoint
+/// in priority order, failing over to the next if the endpoint can't be
+/// reached. Failed endpoints are skipped with exponential backoff. If every
+/// endpoint is backing off, all are tried in priority order.
+#[derive(Debug)]
+pub struct FallbackClient {
+    endpoints: Vec<(Http, Mutex<Health>)>,
+}
+
+impl FallbackClient {
+    /// Instantiate a client from a priority-ordered list of HTTP urls
+    pub fn new(urls: &[String]) -> Result<Self, RpcClientError> {
+        Ok(Self {
+            endpoints: parse_urls(urls)?
+                .into_iter()
+                .map(|http| (http, Default::default()))
+                .collect(),
+        })
+    }
+
+    /// Indices of endpoints to try, in order
+    fn attempt_order(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let available: Vec<usize> = self
+            .endpoints
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, health))| health.lock().expect("poisoned").available(now))
+            .map(|(i, _)| i)
+            .collect();
+
+        if available.is_empty() {
+            (0..self.endpoints.len()).collect()
+        } else {
+            available
+        }
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for FallbackClient {
+    type Error = RpcClientError;
+
+    #[instrument(err, skip(self, params))]
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned,
+    {
+        let mut last_error = None;
+        for i in self.attempt_order() {
+            let (http, health) = &self.endpoints[i];
+            match http.request(method, &params).await {
+                Ok(response) => {
+                    health.lock().expect("poisoned").record_success();
+                    return Ok(response);
+                }
+                // The node answered, so the endpoint is healthy
+                Err(e @ HttpClientError::JsonRpcError(_)) => {
+                    health.lock().expect("poisoned").record_success();
+                    return Err(RpcClientError::JsonRpcError(e));
+                }
+                Err(e) => {
+                    warn!(endpoint = i, error = %e, "RPC endpoint failed. Failing over");
+                    health
+                        .lock()
+                        .expect("poisoned")
+                        .record_failure(Instant::now());
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(RpcClientError::AllFailed(
+            last_error.expect("at least one endpoint"),
+        ))
+    }
+}
+
+/// The block tags in a request's params that refer to the latest block.
+/// Missing `eth_getLogs` bounds default to the latest block, so they are
+/// filled in first
+fn latest_tags<'a>(method: &str, params: &'a mut Value) -> Vec<&'a mut Value> {
+    let is_latest = |tag: &Value| tag.as_str() == Some("latest");
+    match method {
+        "eth_getLogs" => match params.get_mut(0).and_then(Value::as_object_mut) {
+            Some(filter) if !filter.contains_key("blockHash") => {
+                for bound in ["fromBlock", "toBlock"] {
+                    filter.entry(bound).or_insert_with(|| "latest".into());
+                }
+                filter
+                    .iter_mut()
+                    .filter(|(key, tag)| {
+                        (*key == "fromBlock" || *key == "toBlock") && is_latest(tag)
+                    })
+                    .map(|(_, tag)| tag)
+                    .collect()
+            }
+            _ => vec![],
+        },
+        _ => {
+            let position = match method {
+                "eth_getBlockByNumber" => 0,
+                "eth_call"
+                | "eth_getBalance"
+                | "eth_getCode"
+                | "eth_getTransactionCount"
+                | "eth_feeHistory" => 1,
+                "eth_getStorageAt" => 2,
+                _ => return vec![],
+            };
+            params
+                .get_mut(position)
+                .filter(|tag| is_latest(tag))
+                .into_iter()
+                .collect()
+        }
+    }
+}
+
+/// A JSON-RPC client that sends each request to every endpoint, and only
+/// returns a response that at least `quorum` endpoints agree on.
+///
+/// Block numbers are expected to differ slightly between endpoints, so for
+/// `eth_blockNumber` this returns the highest block that `quorum` endpoints
+/// have reached. Requests for state at the `latest` block are pinned to that
+/// block before they are sent, so that endpoints a block apart still agree.
+/// Requests for `pending` state are sent as they are.
+#[derive(Debug)]
+pub struct QuorumClient {
+    endpoints: Vec<Http>,
+    quorum: usize,
+}
+
+impl QuorumClient {
+    /// Instantiate a client from a list of HTTP urls. If `quorum` is
+    /// `None`, a majority of endpoints must agree
+    pub fn new(urls: &[String], quorum: Option<usize>) -> Result<Self, RpcClientError> {
+        let endpoints = parse_urls(urls)?;
+        let quorum = quorum.unwrap_or(endpoints.len() / 2 + 1);
+        if quorum == 0 || quorum > endpoints.len() {
+            return Err(RpcClientError::UnreachableQuorum {
+                quorum,
+                endpoints: endpoints.len(),
+            });
+        }
+        Ok(Self { endpoints, quorum })
+    }
+
+    fn block_number_quorum(&self, responses: &[Result<Value, String>]) -> Option<Value> {
+        let mut heights: Vec<(u64, &Value)> = responses
+            .iter()
+            .filter_map(|r| r.as_ref().ok())
+            .filter_map(|v| {
+                let height = v.as_str()?.strip_prefix("0x")?;
+                Some((u64::from_str_radix(height, 16).ok()?, v))
+            })
+            .collect();
+        heights.sort_by(|a, b| b.0.cmp(&a.0));
+        heights.get(self.quorum - 1).map(|(_, v)| (*v).clone())
+    }
+
+    fn value_quorum(&self, responses: &[Result<Value, String>]) -> Option<Value> {
+        responses
+            .iter()
+            .filter_map(|r| r.as_ref().ok())
+            .find(|candidate| {
+                responses
+                    .iter()
+                    .filter(|r| r.as_ref().ok() == Some(*candidate))
+                    .count()
+                    >= self.quorum
+            })
+            .cloned()
+    }
+
+    /// Send the request to every endpoint
+    async fn fan_out<T>(&self, method: &str, params: &T) -> Vec<Result<Value, String>>
+    where
+        T: Debug + Serialize + Send + Sync,
+    {
+        join_all(
+            self.endpoints
+                .iter()
+                .map(|http| http.request::<_, Value>(method, params)),
+        )
+        .await
+        .into_iter()
+        .map(|r| r.map_err(|e| e.to_string()))
+        .collect()
+    }
+
+    /// Send the request to every endpoint, and return the response a quorum
+    /// agreed on
+    async fn agreed<T>(&self, method: &str, params: &T) -> Result<Value, RpcClientError>
+    where
+        T: Debug + Serialize + Send + Sync,
+    {
+        let responses = self.fan_out(method, params).await;
+        let agreed = if method == "eth_blockNumber" {
+            self.block_number_quorum(&responses)
+        } else {
+            self.value_quorum(&responses)
+        };
+
+        agreed.ok_or_else(|| {
+            warn!(method, responses = ?responses, "RPC endpoints disagree");
+            RpcClientError::NoQuorum {
+                method: method.to_owned(),
+                quorum: self.quorum,
+                responses,
+            }
+        })
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for QuorumClient {
+    type Error = RpcClientError;
+
+    #[instrument(err, skip(self, params))]
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned,
+    {
+        let mut params = serde_json::to_value(&params)?;
+        let mut tags = latest_tags(method, &mut params);
+        if !tags.is_empty() {
+            let block = self.agreed("eth_blockNumber", &()).await?;
+            for tag in tags.iter_mut() {
+                **tag = block.clone();
+            }
+        }
+
+        let value = self.agreed(method, &params).await?;
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ethers::core::types::U64;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    /// Serve `result` as the response to every JSON-RPC request. Returns
+    /// the server's url
+    async fn mock_rpc_server(result: Value) -> String {
+        mock_rpc_handler(move |_| result.clone()).await
+    }
+
+    /// Serve the result of `respond` to each JSON-RPC request. Returns the
+    /// server's url
+    async fn mock_rpc_handler<F>(respond: F) -> String
+    where
+        F: Fn(&Value) -> Value + Send + Sync + 'static,
+    {
+        let respond = std::sync::Arc::new(respond);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let respond = respond.clone();
+                tokio::spawn(async move {
+                    let mut buf = vec![0u8; 4096];
+                    let mut read = 0;
+                    // read the headers and body. Requests are small enough
+                    // to assume the body arrives once the headers do
+                    let request: Value = loop {
+                        read += socket.read(&mut buf[read..]).await.unwrap();
+                        let text = String::from_utf8_lossy(&buf[..read]);
+                        if let Some((_, body)) = text.split_once("\r\n\r\n") {
+                            if let Ok(request) = serde_json::from_str(body) {
+                                break request;
+                            }
+                        }
+                    };
+                    let body = serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": request["id"],
+                        "result": respond(&request),
+                    })
+                    .to_string();
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    socket.write_all(response.as_bytes()).await.unwrap();
+                });
+            }
+        });
+
+        url
+    }
+
+    /// A url with nothing listening on it
+    async fn dead_url() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    }
+
+    #[tokio::test]
+    async fn fallback_fails_over_and_backs_off() {
+        let urls = vec![dead_url().await, mock_rpc_server(Value::from("0x10")).await];
+        let client = FallbackClient::new(&urls).unwrap();
+        assert_eq!(client.attempt_order(), vec![0, 1]);
+
+        let block: U64 = client.request("eth_blockNumber", ()).await.unwrap();
+        assert_eq!(block.as_u64(), 16);
+
+        // the dead endpoint is skipped until its backoff expires
+        assert_eq!(client.attempt_order(), vec![1]);
+        assert_eq!(
+            client.endpoints[0].1.lock().unwrap().consecutive_failures,
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn fallback_errors_when_all_endpoints_fail() {
+        let urls = vec![dead_url().await, dead_url().await];
+        let client = FallbackClient::new(&urls).unwrap();
+        let res: Result<Value, _> = client.request("eth_blockNumber", ()).await;
+        assert!(matches!(res, Err(RpcClientError::AllFailed(_))));
+
+        // with every endpoint backing off, all are still tried
+        assert_eq!(client.attempt_order(), vec![0, 1]);
+    }
+
+    #[tokio::test]
+    async fn quorum_returns_agreed_response() {
+        let urls = vec![
+            mock_rpc_server(Value::from("0x1")).await,
+            mock_rpc_server(Value::from("0x2")).await,
+            mock_rpc_server(Value::from("0x1")).await,
+            dead_url().await,
+        ];
+        let client = QuorumClient::new(&urls, Some(2)).unwrap();
+        let res: Value = client.request("eth_chainId", ()).await.unwrap();
+        assert_eq!(res, Value::from("0x1"));
+    }
+
+    #[tokio::test]
+    async fn quorum_rejects_disagreement() {
+        let urls = vec![
+            mock_rpc_server(Value::from("0x1")).await,
+            mock_rpc_server(Value::from("0x2")).await,
+            mock_rpc_server(Value::from("0x3")).await,
+        ];
+        let client = QuorumClient::new(&urls, None).unwrap();
+        let res: Result<Value, _> = client.request("eth_chainId", ()).await;
+        match res {
+            Err(RpcClientError::NoQuorum {
+                quorum, responses, ..
+            }) => {
+                assert_eq!(quorum, 2);
+                assert_eq!(responses.len(), 3);
+            }
+            other => panic!("expected NoQuorum, got {:?}", other),
+        }
+
+        // block numbers resolve to the highest block a quorum has reached
+        let block: U64 = client.request("eth_blockNumber", ()).await.unwrap();
+        assert_eq!(block.as_u64(), 2);
+    }
+
+    /// A node at `height` that answers calls for a block with its number
+    async fn mock_node(height: u64) -> String {
+        mock_rpc_handler(move |request| {
+            let height = Value::from(format!("{:#x}", height));
+            let params = &request["params"];
+            match request["method"].as_str().unwrap() {
+                "eth_blockNumber" => height,
+                "eth_call" if params[1] == "latest" => height,
+                "eth_call" => params[1].clone(),
+                "eth_getLogs" => serde_json::json!([params[0]["fromBlock"], params[0]["toBlock"]]),
+                _ => Value::Null,
+            }
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn quorum_pins_latest_requests_to_an_agreed_block() {
+        let urls = vec![mock_node(16).await, mock_node(17).await];
+        let client = QuorumClient::new(&urls, Some(2)).unwrap();
+
+        let block: U64 = client.request("eth_blockNumber", ()).await.unwrap();
+        assert_eq!(block.as_u64(), 16);
+
+        let tx = serde_json::json!({ "to": "0x0000000000000000000000000000000000000001" });
+        let res: Value = client.request("eth_call", (&tx, "latest")).await.unwrap();
+        assert_eq!(res, Value::from("0x10"));
+
+        // explicit blocks are left alone
+        let res: Value = client.request("eth_call", (&tx, "0x5")).await.unwrap();
+        assert_eq!(res, Value::from("0x5"));
+
+        // a missing log filter bound is the latest block
+        let res: Value = client
+            .request("eth_getLogs", [serde_json::json!({ "fromBlock": "0x1" })])
+            .await
+            .unwrap();
+        assert_eq!(res, serde_json::json!(["0x1", "0x10"]));
+    }
+
+    #[test]
+    fn it_finds_latest_block_tags() {
+        let mut params = serde_json::json!([{ "blockHash": "0x01" }]);
+        assert!(latest_tags("eth_getLogs", &mut params).is_empty());
+        assert_eq!(params, serde_json::json!([{ "blockHash": "0x01" }]));
+
+        let mut params = serde_json::json!(["0x01", "pending"]);
+        assert!(latest_tags("eth_getTransactionCount", &mut params).is_empty());
+
+        let mut params = serde_json::json!(["latest", false]);
+        assert_eq!(latest_tags("eth_getBlockByNumber", &mut params).len(), 1);
+
+        let mut params = serde_json::json!(["latest"]);
+        assert!(latest_tags("eth_chainId", &mut params).is_empty());
+    }
+
+    #[test]
+    fn quorum_must_be_reachable() {
+        let urls = vec!["http://127.0.0.1:1".to_owned()];
+        assert!(matches!(
+            QuorumClient::new(&urls, Some(2)),
+            Err(RpcClientError::UnreachableQuorum { .. })
+        ));
+        assert!(matches!(
+            QuorumClient::new(&[], None),
+            Err(RpcClientError::NoEndpoints)
+        ));
+    }
+}
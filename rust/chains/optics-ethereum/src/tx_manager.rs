@@ -0,0 +1,826 @@
+use ethers::core::types::{
+    transaction::eip2718::TypedTransaction, Address, BlockNumber, NameOrAddress,
+    TransactionReceipt, TransactionRequest, H256, U256,
+};
+use ethers::providers::Middleware;
+use once_cell::sync::OnceCell;
+use optics_core::{
+    db::{TypedDB, DB},
+    ChainCommunicationError, Decode, Encode, OpticsError,
+};
+use prometheus::IntCounterVec;
+use std::{
+    collections::HashMap,
+    error::Error as StdError,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+use tokio::{sync::Mutex, time::sleep};
+use tracing::{info, warn};
+
+static TX: &str = "tx_";
+static PENDING: &str = "pending_";
+
+/// Submission locks by sender, shared by every manager, as several
+/// contracts may be called with the same signer
+static SUBMISSION_LOCKS: OnceCell<std::sync::Mutex<HashMap<Address, Arc<Mutex<()>>>>> =
+    OnceCell::new();
+
+/// The lock held while submitting a transaction from `sender`
+fn submission_lock(sender: Address) -> Arc<Mutex<()>> {
+    SUBMISSION_LOCKS
+        .get_or_init(Default::default)
+        .lock()
+        .expect("poisoned")
+        .entry(sender)
+        .or_default()
+        .clone()
+}
+
+/// Gas escalation and resubmission settings
+#[derive(Debug, Clone)]
+pub struct TxManagerConf {
+    /// How long to wait for a transaction to be included before bumping its
+    /// gas price and resubmitting it
+    pub escalation_interval: Duration,
+    /// Percentage to bump the gas price by on each resubmission. Most nodes
+    /// reject replacements that bump by less than 10%
+    pub escalation_percent: u64,
+    /// Cap on the gas price (legacy) or max fee per gas (EIP-1559), in wei
+    pub max_fee: Option<U256>,
+    /// How often to poll for receipts
+    pub poll_interval: Duration,
+}
+
+impl Default for TxManagerConf {
+    fn default() -> Self {
+        Self {
+            escalation_interval: Duration::from_secs(60),
+            escalation_percent: 15,
+            max_fee: None,
+            poll_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Transaction outcome metrics. Created by the agent's `CoreMetrics`
+#[derive(Debug, Clone)]
+pub struct TxMetrics {
+    /// Labels: `chain`, `outcome`, `agent`
+    pub outcomes: IntCounterVec,
+    /// Labels: `chain`, `agent`
+    pub escalations: IntCounterVec,
+    /// The chain the contract is on
+    pub chain: String,
+    /// The agent sending the transactions
+    pub agent: String,
+}
+
+impl TxMetrics {
+    fn outcome(&self, outcome: &str) {
+        self.outcomes
+            .with_label_values(&[&self.chain, outcome, &self.agent])
+            .inc();
+    }
+
+    fn escalation(&self) {
+        self.escalations
+            .with_label_values(&[&self.chain, &self.agent])
+            .inc();
+    }
+}
+
+/// The gas price of a transaction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GasPrice {
+    /// A legacy gas price
+    Legacy(U256),
+    /// EIP-1559 fees
+    Eip1559 {
+        /// Max fee per gas
+        max_fee: U256,
+        /// Max priority fee per gas
+        max_priority_fee: U256,
+    },
+}
+
+impl GasPrice {
+    /// The most the transaction can pay per unit of gas
+    pub fn max_fee(&self) -> U256 {
+        match self {
+            GasPrice::Legacy(price) => *price,
+            GasPrice::Eip1559 { max_fee, .. } => *max_fee,
+        }
+    }
+
+    /// Limit the price to `cap`
+    pub fn capped(self, cap: Option<U256>) -> Self {
+        let cap = match cap {
+            Some(cap) => cap,
+            None => return self,
+        };
+        match self {
+            GasPrice::Legacy(price) => GasPrice::Legacy(price.min(cap)),
+            GasPrice::Eip1559 {
+                max_fee,
+                max_priority_fee,
+            } => {
+                let max_fee = max_fee.min(cap);
+                GasPrice::Eip1559 {
+                    max_fee,
+                    max_priority_fee: max_priority_fee.min(max_fee),
+                }
+            }
+        }
+    }
+
+    /// The price bumped by `percent`, limited to `cap`. `None` if the price
+    /// is already at the cap
+    pub fn escalated(self, percent: u64, cap: Option<U256>) -> Option<Self> {
+        // round up so that small prices still increase
+        let bump = |price: U256| price + (price * percent + 99) / 100;
+        let escalated = match self {
+            GasPrice::Legacy(price) => GasPrice::Legacy(bump(price)),
+            GasPrice::Eip1559 {
+                max_fee,
+                max_priority_fee,
+            } => GasPrice::Eip1559 {
+                max_fee: bump(max_fee),
+                max_priority_fee: bump(max_priority_fee),
+            },
+        }
+        .capped(cap);
+
+        if escalated.max_fee() > self.max_fee() {
+            Some(escalated)
+        } else {
+            None
+        }
+    }
+
+    fn apply(&self, tx: &mut TypedTransaction) {
+        match (self, tx) {
+            (
+                GasPrice::Eip1559 {
+                    max_fee,
+                    max_priority_fee,
+                },
+                TypedTransaction::Eip1559(inner),
+            ) => {
+                inner.max_fee_per_gas = Some(*max_fee);
+                inner.max_priority_fee_per_gas = Some(*max_priority_fee);
+            }
+            (price, tx) => {
+                tx.set_gas_price(price.max_fee());
+            }
+        }
+    }
+}
+
+/// The status of a managed transaction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TxStatus {
+    /// Submitted and not yet included
+    Pending,
+    /// Included in a block
+    Included {
+        /// The hash of the submission that was included
+        hash: H256,
+        /// The block it was included in
+        block_number: u64,
+        /// False if the transaction reverted
+        executed: bool,
+    },
+    /// The nonce was used by a transaction we didn't submit
+    Dropped,
+}
+
+/// A transaction tracked by the manager, with every submission of it
+#[derive(Debug, Clone, PartialEq)]
+pub struct TxRecord {
+    /// The nonce shared by every submission
+    pub nonce: U256,
+    /// The latest max fee per gas, in wei
+    pub max_fee: U256,
+    /// Hashes of every submission, oldest first
+    pub hashes: Vec<H256>,
+    /// The transaction's status
+    pub status: TxStatus,
+}
+
+impl Encode for TxRecord {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        let mut written = 0;
+        written += self.nonce.write_to(writer)?;
+        written += self.max_fee.write_to(writer)?;
+        written += (self.hashes.len() as u32).write_to(writer)?;
+        for hash in self.hashes.iter() {
+            written += hash.write_to(writer)?;
+        }
+        match self.status {
+            TxStatus::Pending => {
+                writer.write_all(&[0])?;
+                written += 1;
+            }
+            TxStatus::Included {
+                hash,
+                block_number,
+                executed,
+            } => {
+                writer.write_all(&[1])?;
+                written += 1;
+                written += hash.write_to(writer)?;
+                written += block_number.write_to(writer)?;
+                writer.write_all(&[executed as u8])?;
+                written += 1;
+            }
+            TxStatus::Dropped => {
+                writer.write_all(&[2])?;
+                written += 1;
+            }
+        }
+        Ok(written)
+    }
+}
+
+impl Decode for TxRecord {
+    fn read_from<R>(reader: &mut R) -> Result<Self, OpticsError>
+    where
+        R: std::io::Read,
+        Self: Sized,
+    {
+        let nonce = U256::read_from(reader)?;
+        let max_fee = U256::read_from(reader)?;
+        let hash_count = u32::read_from(reader)?;
+        let hashes = (0..hash_count)
+            .map(|_| H256::read_from(reader))
+            .collect::<Result<_, _>>()?;
+
+        let mut tag = [0u8; 1];
+        reader.read_exact(&mut tag)?;
+        let status = match tag[0] {
+            0 => TxStatus::Pending,
+            1 => {
+                let hash = H256::read_from(reader)?;
+                let block_number = u64::read_from(reader)?;
+                let mut executed = [0u8; 1];
+                reader.read_exact(&mut executed)?;
+                TxStatus::Included {
+                    hash,
+                    block_number,
+                    executed: executed[0] != 0,
+                }
+            }
+            2 => TxStatus::Dropped,
+            _ => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "unknown tx status",
+                )
+                .into())
+            }
+        };
+
+        Ok(Self {
+            nonce,
+            max_fee,
+            hashes,
+            status,
+        })
+    }
+}
+
+/// Errors specific to the `TxManager`
+#[derive(Debug, Error)]
+pub enum TxManagerError {
+    /// The node accepted a transaction, but never returned it
+    #[error("Node never returned submitted transaction {0:?}")]
+    UnknownTransaction(H256),
+}
+
+fn middleware_error<E: StdError + Send + Sync + 'static>(e: E) -> ChainCommunicationError {
+    ChainCommunicationError::CustomError(Box::new(e))
+}
+
+/// Submits transactions and sees them through to inclusion. Unconfirmed
+/// transactions are resubmitted with the same nonce and an escalating gas
+/// price, up to the configured cap. Every transaction is tracked in the DB.
+///
+/// Concurrent sends from the same signer are submitted one at a time, even
+/// across managers, so that the signer's nonces reach the node in the order
+/// they were assigned. Waiting for inclusion is concurrent.
+///
+/// Transactions left pending by a previous run are seen through before the
+/// first send. Their nonces are taken by a zero-value self-transfer at a
+/// bumped gas price, as the transactions themselves are not stored. Pending
+/// transactions are indexed separately, so that resuming does not read the
+/// record of every transaction ever sent.
+///
+/// Key structure:
+/// - ```tx_manager_<contract_name>_tx_<nonce>```
+/// - ```tx_manager_<contract_name>_pending_<nonce>```
+#[derive(Debug, Clone)]
+pub struct TxManager {
+    db: TypedDB,
+    conf: TxManagerConf,
+    metrics: Option<TxMetrics>,
+    resumed: Arc<Mutex<bool>>,
+}
+
+impl TxManager {
+    /// Instantiate a new `TxManager` for the contract named `name`
+    pub fn new(db: DB, name: &str, conf: TxManagerConf, metrics: Option<TxMetrics>) -> Self {
+        Self {
+            db: TypedDB::new(db, format!("tx_manager_{}", name)),
+            conf,
+            metrics,
+            resumed: Default::default(),
+        }
+    }
+
+    /// Retrieve the record of the transaction with `nonce`
+    pub fn record(&self, nonce: U256) -> Result<Option<TxRecord>, ChainCommunicationError> {
+        Ok(self.db.retrieve_keyed_decodable(TX, &nonce)?)
+    }
+
+    /// Retrieve the records of every transaction still pending, in nonce
+    /// order
+    pub fn pending_records(&self) -> Result<Vec<TxRecord>, ChainCommunicationError> {
+        let (_, iter) = self.db.prefix_iterator(PENDING);
+        let mut pending = vec![];
+        for (_, value) in iter {
+            let nonce = U256::read_from(&mut value.as_slice())?;
+            if let Some(record) = self.record(nonce)? {
+                pending.push(record);
+            }
+        }
+        Ok(pending)
+    }
+
+    /// Store the record, and add it to or remove it from the pending index
+    /// in the same write
+    fn store_record(&self, record: &TxRecord) -> Result<(), ChainCommunicationError> {
+        let batch = self.db.batched();
+        batch.store_keyed_encodable(TX, &record.nonce, record)?;
+        if record.status == TxStatus::Pending {
+            batch.store_keyed_encodable(PENDING, &record.nonce, &record.nonce)?;
+        } else {
+            batch.delete_keyed(PENDING, &record.nonce)?;
+        }
+        Ok(batch.commit()?)
+    }
+
+    fn outcome(&self, outcome: &str) {
+        if let Some(metrics) = &self.metrics {
+            metrics.outcome(outcome);
+        }
+    }
+
+    async fn initial_gas_price<M: Middleware + 'static>(
+        &self,
+        client: &M,
+        tx: &TypedTransaction,
+    ) -> Result<GasPrice, ChainCommunicationError> {
+        let price = match tx {
+            TypedTransaction::Eip1559(_) => {
+                let (max_fee, max_priority_fee) = client
+                    .estimate_eip1559_fees(None)
+                    .await
+                    .map_err(middleware_error)?;
+                GasPrice::Eip1559 {
+                    max_fee,
+                    max_priority_fee,
+                }
+            }
+            _ => GasPrice::Legacy(client.get_gas_price().await.map_err(middleware_error)?),
+        };
+
+        let capped = price.capped(self.conf.max_fee);
+        if capped != price {
+            warn!(
+                estimated = ?price,
+                cap = ?self.conf.max_fee,
+                "Estimated gas price exceeds the configured cap"
+            );
+        }
+        Ok(capped)
+    }
+
+    /// Check every submission for a receipt
+    async fn find_receipt<M: Middleware + 'static>(
+        &self,
+        client: &M,
+        hashes: &[H256],
+    ) -> Result<Option<TransactionReceipt>, ChainCommunicationError> {
+        for hash in hashes.iter().rev() {
+            if let Some(receipt) = client
+                .get_transaction_receipt(*hash)
+                .await
+                .map_err(middleware_error)?
+            {
+                return Ok(Some(receipt));
+            }
+        }
+        Ok(None)
+    }
+
+    /// See through the transactions left pending by a previous run, once
+    async fn resume<M: Middleware + 'static>(
+        &self,
+        client: &M,
+    ) -> Result<(), ChainCommunicationError> {
+        let mut resumed = self.resumed.lock().await;
+        if *resumed {
+            return Ok(());
+        }
+        for record in self.pending_records()? {
+            let nonce = record.nonce;
+            if let Err(e) = self.replace(client, record).await {
+                warn!(error = %e, nonce = ?nonce, "Failed to replace pending transaction");
+            }
+        }
+        *resumed = true;
+        Ok(())
+    }
+
+    /// Take a pending transaction's nonce with a zero-value self-transfer,
+    /// unless one of its submissions was already included
+    async fn replace<M: Middleware + 'static>(
+        &self,
+        client: &M,
+        mut record: TxRecord,
+    ) -> Result<(), ChainCommunicationError> {
+        if let Some(receipt) = self.find_receipt(client, &record.hashes).await? {
+            self.included(record, receipt)?;
+            return Ok(());
+        }
+
+        let sender = match client.default_sender() {
+            Some(sender) => sender,
+            None => {
+                warn!(nonce = ?record.nonce, "No sender to replace pending transaction with");
+                return Ok(());
+            }
+        };
+
+        // At the cap the replacement may be rejected as underpriced, and is
+        // rebroadcast until the original is included or the cap is raised
+        let previous = GasPrice::Legacy(record.max_fee);
+        let gas_price = previous
+            .escalated(self.conf.escalation_percent, self.conf.max_fee)
+            .unwrap_or(previous);
+        let mut tx: TypedTransaction = TransactionRequest::new()
+            .from(sender)
+            .to(sender)
+            .value(0)
+            .gas(21_000)
+            .nonce(record.nonce)
+            .into();
+        gas_price.apply(&mut tx);
+
+        info!(
+            nonce = ?record.nonce,
+            gas_price = ?gas_price,
+            "Replacing transaction left pending by a previous run"
+        );
+        match client.send_transaction(tx.clone(), None).await {
+            Ok(pending) => {
+                record.hashes.push(*pending);
+                record.max_fee = gas_price.max_fee();
+                self.store_record(&record)?;
+            }
+            Err(e) => {
+                warn!(error = %e, nonce = ?record.nonce, "Failed to submit replacement transaction")
+            }
+        }
+
+        self.see_through(client, tx, gas_price, record, sender)
+            .await
+            .map(|_| ())
+    }
+
+    /// Submit a transaction and wait for it to be included, resubmitting
+    /// it with a higher gas price if it isn't included in time
+    pub async fn send<M: Middleware + 'static>(
+        &self,
+        client: &M,
+        mut tx: TypedTransaction,
+    ) -> Result<TransactionReceipt, ChainCommunicationError> {
+        self.resume(client).await?;
+
+        // "0x..."
+        let data = format!(
+            "0x{}",
+            hex::encode(&tx.data().map(|b| b.to_vec()).unwrap_or_default())
+        );
+        let to = tx
+            .to()
+            .cloned()
+            .unwrap_or_else(|| NameOrAddress::Address(Default::default()));
+
+        let mut gas_price = self.initial_gas_price(client, &tx).await?;
+        gas_price.apply(&mut tx);
+
+        // Without a known sender the node signs, and every such send shares
+        // a lock
+        let sender = tx
+            .from()
+            .copied()
+            .or_else(|| client.default_sender())
+            .unwrap_or_default();
+        let first: H256 = {
+            let lock = submission_lock(sender);
+            let _submission = lock.lock().await;
+
+            // Estimate before the signer assigns a nonce, so that a
+            // transaction that would revert fails without leaving a gap
+            if tx.gas().is_none() {
+                if tx.from().is_none() {
+                    if let Some(from) = client.default_sender() {
+                        tx.set_from(from);
+                    }
+                }
+                let gas = client.estimate_gas(&tx).await.map_err(middleware_error)?;
+                tx.set_gas(gas);
+            }
+
+            info!(to = ?to, data = %data, gas_price = ?gas_price, "Dispatching transaction");
+            *client
+                .send_transaction(tx.clone(), None)
+                .await
+                .map_err(middleware_error)?
+        };
+        info!(
+            to = ?to,
+            data = %data,
+            tx_hash = ?first,
+            "Dispatched tx with tx_hash {:?}",
+            first
+        );
+
+        let mut record = TxRecord {
+            nonce: Default::default(),
+            max_fee: gas_price.max_fee(),
+            hashes: vec![first],
+            status: TxStatus::Pending,
+        };
+
+        // Pin the sender, nonce and gas limit the middleware filled in, so
+        // that resubmissions replace the original. The node may take a
+        // moment to return a transaction it just accepted
+        let deadline = Instant::now() + self.conf.escalation_interval;
+        let sender = loop {
+            if let Some(sent) = client
+                .get_transaction(first)
+                .await
+                .map_err(middleware_error)?
+            {
+                tx.set_from(sent.from);
+                tx.set_nonce(sent.nonce);
+                tx.set_gas(sent.gas);
+                record.nonce = sent.nonce;
+                break sent.from;
+            }
+            if Instant::now() >= deadline {
+                self.outcome("unknown");
+                return Err(middleware_error(TxManagerError::UnknownTransaction(first)));
+            }
+            sleep(self.conf.poll_interval).await;
+        };
+        self.store_record(&record)?;
+
+        self.see_through(client, tx, gas_price, record, sender)
+            .await
+    }
+
+    /// Wait for one of the record's submissions to be included, resubmitting
+    /// `tx` with an escalating gas price until it is
+    async fn see_through<M: Middleware + 'static>(
+        &self,
+        client: &M,
+        mut tx: TypedTransaction,
+        mut gas_price: GasPrice,
+        mut record: TxRecord,
+        sender: Address,
+    ) -> Result<TransactionReceipt, ChainCommunicationError> {
+        let mut escalate_at = Instant::now() + self.conf.escalation_interval;
+        loop {
+            sleep(self.conf.poll_interval).await;
+
+            if let Some(receipt) = self.find_receipt(client, &record.hashes).await? {
+                return self.included(record, receipt);
+            }
+
+            // If the nonce has been used and none of our submissions were
+            // included, another transaction took its place
+            let confirmed_nonce = client
+                .get_transaction_count(sender, Some(BlockNumber::Latest.into()))
+                .await
+                .map_err(middleware_error)?;
+            if confirmed_nonce > record.nonce {
+                if let Some(receipt) = self.find_receipt(client, &record.hashes).await? {
+                    return self.included(record, receipt);
+                }
+                warn!(
+                    nonce = ?record.nonce,
+                    tx_hashes = ?record.hashes,
+                    "Transaction nonce was used by another transaction"
+                );
+                record.status = TxStatus::Dropped;
+                self.store_record(&record)?;
+                self.outcome("dropped");
+                return Err(ChainCommunicationError::DroppedError(record.hashes[0]));
+            }
+
+            if Instant::now() < escalate_at {
+                continue;
+            }
+            escalate_at = Instant::now() + self.conf.escalation_interval;
+
+            // At the cap, rebroadcast at the same price in case the
+            // transaction was evicted from the mempool
+            if let Some(escalated) =
+                gas_price.escalated(self.conf.escalation_percent, self.conf.max_fee)
+            {
+                gas_price = escalated;
+                gas_price.apply(&mut tx);
+                if let Some(metrics) = &self.metrics {
+                    metrics.escalation();
+                }
+            }
+
+            info!(
+                nonce = ?record.nonce,
+                gas_price = ?gas_price,
+                "Transaction not yet included. Resubmitting"
+            );
+            match client.send_transaction(tx.clone(), None).await {
+                Ok(pending) => {
+                    let hash: H256 = *pending;
+                    if !record.hashes.contains(&hash) {
+                        record.hashes.push(hash);
+                    }
+                    record.max_fee = gas_price.max_fee();
+                    self.store_record(&record)?;
+                }
+                // Likely an earlier submission was just included, or the
+                // node already has this one. The next poll will tell.
+                Err(e) => {
+                    warn!(error = %e, nonce = ?record.nonce, "Failed to resubmit transaction")
+                }
+            }
+        }
+    }
+
+    fn included(
+        &self,
+        mut record: TxRecord,
+        receipt: TransactionReceipt,
+    ) -> Result<TransactionReceipt, ChainCommunicationError> {
+        let executed = receipt.status.map(|s| s.low_u32() == 1).unwrap_or(false);
+        record.status = TxStatus::Included {
+            hash: receipt.transaction_hash,
+            block_number: receipt.block_number.map(|n| n.as_u64()).unwrap_or_default(),
+            executed,
+        };
+        self.store_record(&record)?;
+        self.outcome(if executed { "executed" } else { "reverted" });
+
+        info!(
+            tx_hash = ?receipt.transaction_hash,
+            submissions = record.hashes.len(),
+            executed,
+            "confirmed transaction with tx_hash {:?}",
+            receipt.transaction_hash
+        );
+        Ok(receipt)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_escalates_up_to_the_cap() {
+        let price = GasPrice::Legacy(100.into());
+        assert_eq!(
+            price.escalated(15, None),
+            Some(GasPrice::Legacy(115.into()))
+        );
+        assert_eq!(
+            price.escalated(15, Some(110.into())),
+            Some(GasPrice::Legacy(110.into()))
+        );
+        assert_eq!(price.escalated(15, Some(100.into())), None);
+
+        // small prices still increase
+        assert_eq!(
+            GasPrice::Legacy(1.into()).escalated(15, None),
+            Some(GasPrice::Legacy(2.into()))
+        );
+
+        let price = GasPrice::Eip1559 {
+            max_fee: 200.into(),
+            max_priority_fee: 100.into(),
+        };
+        assert_eq!(
+            price.escalated(10, None),
+            Some(GasPrice::Eip1559 {
+                max_fee: 220.into(),
+                max_priority_fee: 110.into(),
+            })
+        );
+        // the priority fee never exceeds the max fee
+        assert_eq!(
+            price.capped(Some(50.into())),
+            GasPrice::Eip1559 {
+                max_fee: 50.into(),
+                max_priority_fee: 50.into(),
+            }
+        );
+    }
+
+    #[test]
+    fn it_shares_submission_locks_by_sender() {
+        let (alice, bob) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        assert!(Arc::ptr_eq(
+            &submission_lock(alice),
+            &submission_lock(alice)
+        ));
+        assert!(!Arc::ptr_eq(&submission_lock(alice), &submission_lock(bob)));
+    }
+
+    #[test]
+    fn it_lists_pending_records() {
+        let manager = TxManager::new(DB::in_memory(), "home", Default::default(), None);
+        let record = |nonce: u64, status| TxRecord {
+            nonce: nonce.into(),
+            max_fee: 100.into(),
+            hashes: vec![H256::from_low_u64_be(nonce)],
+            status,
+        };
+        let records = vec![
+            record(1, TxStatus::Pending),
+            record(2, TxStatus::Dropped),
+            record(
+                3,
+                TxStatus::Included {
+                    hash: H256::from_low_u64_be(3),
+                    block_number: 10,
+                    executed: true,
+                },
+            ),
+            record(4, TxStatus::Pending),
+        ];
+        for record in records.iter() {
+            manager.store_record(record).unwrap();
+        }
+
+        assert_eq!(
+            manager.pending_records().unwrap(),
+            vec![records[0].clone(), records[3].clone()]
+        );
+
+        // records leave the index once they are no longer pending
+        let mut dropped = records[0].clone();
+        dropped.status = TxStatus::Dropped;
+        manager.store_record(&dropped).unwrap();
+        assert_eq!(manager.pending_records().unwrap(), vec![records[3].clone()]);
+        assert_eq!(manager.record(1.into()).unwrap(), Some(dropped));
+    }
+
+    #[test]
+    fn it_encodes_tx_records() {
+        let mut record = TxRecord {
+            nonce: 7.into(),
+            max_fee: 1_000_000_000u64.into(),
+            hashes: vec![H256::repeat_byte(1), H256::repeat_byte(2)],
+            status: TxStatus::Pending,
+        };
+        assert_eq!(
+            TxRecord::read_from(&mut record.to_vec().as_slice()).unwrap(),
+            record
+        );
+
+        record.status = TxStatus::Included {
+            hash: H256::repeat_byte(2),
+            block_number: 30,
+            executed: true,
+        };
+        assert_eq!(
+            TxRecord::read_from(&mut record.to_vec().as_slice()).unwrap(),
+            record
+        );
+
+        record.status = TxStatus::Dropped;
+        assert_eq!(
+            TxRecord::read_from(&mut record.to_vec().as_slice()).unwrap(),
+            record
+        );
+    }
+}
@@ -5,7 +5,7 @@ use ethers::contract::abigen;
 use optics_core::*;
 use std::sync::Arc;
 
-use crate::report_tx;
+use crate::{report_tx, TxManager};
 
 #[allow(missing_docs)]
 abigen!(
@@ -22,6 +22,8 @@ where
     contract: EthereumConnectionManagerInternal<M>,
     domain: u32,
     name: String,
+    provider: Arc<M>,
+    tx_manager: TxManager,
 }
 
 impl<M> EthereumConnectionManager<M>
@@ -38,11 +40,14 @@ where
             domain,
             address,
         }: &ContractLocator,
+        tx_manager: TxManager,
     ) -> Self {
         Self {
-            contract: EthereumConnectionManagerInternal::new(address, provider),
+            contract: EthereumConnectionManagerInternal::new(address, provider.clone()),
             domain: *domain,
             name: name.to_owned(),
+            provider,
+            tx_manager,
         }
     }
 }
@@ -88,7 +93,7 @@ where
             .contract
             .owner_enroll_replica(replica.as_ethereum_address(), domain);
 
-        Ok(report_tx!(tx).into())
+        Ok(report_tx!(self, tx).into())
     }
 
     #[tracing::instrument(err)]
@@ -100,14 +105,14 @@ where
             .contract
             .owner_unenroll_replica(replica.as_ethereum_address());
 
-        Ok(report_tx!(tx).into())
+        Ok(report_tx!(self, tx).into())
     }
 
     #[tracing::instrument(err)]
     async fn set_home(&self, home: OpticsIdentifier) -> Result<TxOutcome, ChainCommunicationError> {
         let tx = self.contract.set_home(home.as_ethereum_address());
 
-        Ok(report_tx!(tx).into())
+        Ok(report_tx!(self, tx).into())
     }
 
     #[tracing::instrument(err)]
@@ -121,7 +126,7 @@ where
             self.contract
                 .set_watcher_permission(watcher.as_ethereum_address(), domain, access);
 
-        Ok(report_tx!(tx).into())
+        Ok(report_tx!(self, tx).into())
     }
 
     #[tracing::instrument(err)]
@@ -135,6 +140,6 @@ where
             signed_failure.signature.to_vec(),
         );
 
-        Ok(report_tx!(tx).into())
+        Ok(report_tx!(self, tx).into())
     }
 }
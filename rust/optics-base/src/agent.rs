@@ -10,7 +10,7 @@ use color_eyre::{eyre::WrapErr, Result};
 use futures_util::future::select_all;
 use optics_core::{
     db::{HomeDB, DB},
-    Common, Home,
+    Common, Home, Replica,
 };
 use tracing::instrument::Instrumented;
 use tracing::{info_span, Instrument};
@@ -81,6 +81,32 @@ pub trait OpticsAgent: Send + Sync + std::fmt::Debug + AsRef<AgentCore> {
         self.replicas().get(name).map(Clone::clone)
     }
 
+    /// Spawn an indexer for each replica, using that replica's index
+    /// settings. Each indexer reports its height to `block_height`
+    fn index_replicas(
+        &self,
+        block_height: &prometheus::IntGaugeVec,
+    ) -> Vec<Instrumented<JoinHandle<Result<()>>>> {
+        self.replicas()
+            .iter()
+            .map(|(name, replica)| {
+                let indexer = self
+                    .as_ref()
+                    .settings
+                    .replicas
+                    .get(name)
+                    .map(|setup| setup.index.clone())
+                    .unwrap_or_default();
+                replica.index(
+                    indexer.from(),
+                    indexer.chunk_size(),
+                    indexer.confirmations(),
+                    block_height.with_label_values(&[name.as_str(), Self::AGENT_NAME]),
+                )
+            })
+            .collect()
+    }
+
     /// Run the agent with the given home and replica
     fn run(&self, replica: &str) -> Instrumented<JoinHandle<Result<()>>>;
 
@@ -142,15 +168,18 @@ pub trait OpticsAgent: Send + Sync + std::fmt::Debug + AsRef<AgentCore> {
                         "Height of a recently observed block",
                         &["network", "agent"],
                     )
-                    .expect("failed to register block_height metric")
-                    .with_label_values(&[self.home().name(), Self::AGENT_NAME]);
+                    .expect("failed to register block_height metric");
 
                 let indexer = &self.as_ref().indexer;
-                let index_task =
-                    self.home()
-                        .index(indexer.from(), indexer.chunk_size(), block_height);
+                let index_task = self.home().index(
+                    indexer.from(),
+                    indexer.chunk_size(),
+                    indexer.confirmations(),
+                    block_height.with_label_values(&[self.home().name(), Self::AGENT_NAME]),
+                );
 
                 tasks.push(index_task);
+                tasks.extend(self.index_replicas(&block_height));
             }
 
             let (res, _, remaining) = select_all(tasks).await;
@@ -68,12 +68,15 @@ impl Home for Homes {
         &self,
         from_height: u32,
         chunk_size: u32,
+        confirmations: u32,
         metric: prometheus::IntGauge,
     ) -> Instrumented<tokio::task::JoinHandle<color_eyre::Result<()>>> {
         match self {
-            Homes::Ethereum(home) => home.index(from_height, chunk_size, metric),
-            Homes::Mock(mock_home) => mock_home.index(from_height, chunk_size, metric),
-            Homes::Other(home) => home.index(from_height, chunk_size, metric),
+            Homes::Ethereum(home) => home.index(from_height, chunk_size, confirmations, metric),
+            Homes::Mock(mock_home) => {
+                mock_home.index(from_height, chunk_size, confirmations, metric)
+            }
+            Homes::Other(home) => home.index(from_height, chunk_size, confirmations, metric),
         }
     }
 
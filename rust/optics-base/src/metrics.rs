@@ -1,6 +1,7 @@
 //! Useful metrics that all agents should track.
 
 use color_eyre::Result;
+use optics_ethereum::TxMetrics;
 use prometheus::{
     Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry,
 };
@@ -12,6 +13,8 @@ use tokio::task::JoinHandle;
 pub struct CoreMetrics {
     agent_name: String,
     transactions: Box<IntGaugeVec>,
+    tx_outcomes: Box<IntCounterVec>,
+    tx_escalations: Box<IntCounterVec>,
     wallet_balance: Box<IntGaugeVec>,
     rpc_latencies: Box<HistogramVec>,
     span_durations: Box<HistogramVec>,
@@ -38,6 +41,24 @@ impl CoreMetrics {
                 .const_label("VERSION", env!("CARGO_PKG_VERSION")),
                 &["chain", "wallet", "agent"],
             )?),
+            tx_outcomes: Box::new(IntCounterVec::new(
+                Opts::new(
+                    "transaction_outcomes_total",
+                    "Number of transactions that were executed, reverted or dropped",
+                )
+                .namespace("optics")
+                .const_label("VERSION", env!("CARGO_PKG_VERSION")),
+                &["chain", "outcome", "agent"],
+            )?),
+            tx_escalations: Box::new(IntCounterVec::new(
+                Opts::new(
+                    "transaction_gas_escalations_total",
+                    "Number of times a transaction was resubmitted with a higher gas price",
+                )
+                .namespace("optics")
+                .const_label("VERSION", env!("CARGO_PKG_VERSION")),
+                &["chain", "agent"],
+            )?),
             wallet_balance: Box::new(IntGaugeVec::new(
                 Opts::new(
                     "wallet_balance_total",
@@ -72,6 +93,8 @@ impl CoreMetrics {
         // TODO: only register these if they aren't already registered?
 
         metrics.registry.register(metrics.transactions.clone())?;
+        metrics.registry.register(metrics.tx_outcomes.clone())?;
+        metrics.registry.register(metrics.tx_escalations.clone())?;
         metrics.registry.register(metrics.wallet_balance.clone())?;
         metrics.registry.register(metrics.rpc_latencies.clone())?;
         metrics.registry.register(metrics.span_durations.clone())?;
@@ -120,6 +143,37 @@ impl CoreMetrics {
         Ok(counter)
     }
 
+    /// Register a histogram with the given buckets.
+    pub fn new_histogram(
+        &self,
+        metric_name: &str,
+        help: &str,
+        labels: &[&str],
+        buckets: Vec<f64>,
+    ) -> Result<prometheus::HistogramVec> {
+        let histogram = HistogramVec::new(
+            HistogramOpts::new(metric_name, help)
+                .namespace("optics")
+                .const_label("VERSION", env!("CARGO_PKG_VERSION"))
+                .buckets(buckets),
+            labels,
+        )?;
+
+        self.registry.register(Box::new(histogram.clone()))?;
+
+        Ok(histogram)
+    }
+
+    /// Transaction outcome metrics for contracts on `chain`
+    pub fn tx_metrics(&self, chain: &str) -> TxMetrics {
+        TxMetrics {
+            outcomes: *self.tx_outcomes.clone(),
+            escalations: *self.tx_escalations.clone(),
+            chain: chain.to_owned(),
+            agent: self.agent_name.clone(),
+        }
+    }
+
     /// Call with the new balance when gas is spent.
     pub fn wallet_balance_changed(
         &self,
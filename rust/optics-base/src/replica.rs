@@ -7,7 +7,7 @@ use optics_core::{
 
 use optics_ethereum::EthereumReplica;
 use optics_test::mocks::MockReplicaContract;
-use tracing::instrument;
+use tracing::{instrument, instrument::Instrumented};
 
 /// Replica type
 #[derive(Debug)]
@@ -115,6 +115,26 @@ impl Replica for Replicas {
             Replicas::Other(replica) => replica.acceptable_root(root).await,
         }
     }
+
+    fn index(
+        &self,
+        from_height: u32,
+        chunk_size: u32,
+        confirmations: u32,
+        metric: prometheus::IntGauge,
+    ) -> Instrumented<tokio::task::JoinHandle<color_eyre::Result<()>>> {
+        match self {
+            Replicas::Ethereum(replica) => {
+                replica.index(from_height, chunk_size, confirmations, metric)
+            }
+            Replicas::Mock(mock_replica) => {
+                mock_replica.index(from_height, chunk_size, confirmations, metric)
+            }
+            Replicas::Other(replica) => {
+                replica.index(from_height, chunk_size, confirmations, metric)
+            }
+        }
+    }
 }
 
 #[async_trait]
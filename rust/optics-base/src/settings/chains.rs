@@ -2,9 +2,15 @@ use color_eyre::Report;
 use serde::Deserialize;
 
 use optics_core::{db::DB, ContractLocator, Signers};
-use optics_ethereum::{make_conn_manager, make_home, make_replica, Connection};
+use optics_ethereum::{make_conn_manager, make_home, make_replica, Connection, TxManager};
 
-use crate::{home::Homes, replica::Replicas, xapp::ConnectionManagers};
+use crate::{
+    home::Homes,
+    metrics::CoreMetrics,
+    replica::Replicas,
+    settings::{IndexSettings, TxSettings},
+    xapp::ConnectionManagers,
+};
 
 /// A connection to _some_ blockchain.
 ///
@@ -39,11 +45,33 @@ pub struct ChainSetup {
     /// Set this key to disable the replica. Does nothing for homes.
     #[serde(default)]
     pub disabled: Option<String>,
+    /// Settings for the replica indexer. Does nothing for homes, which use
+    /// the top-level `index` settings.
+    #[serde(default)]
+    pub index: IndexSettings,
+    /// Transaction submission settings
+    #[serde(default)]
+    pub tx: TxSettings,
 }
 
 impl ChainSetup {
+    /// Instantiate the `TxManager` for this chain's contract
+    pub fn tx_manager(&self, db: DB, metrics: &CoreMetrics) -> TxManager {
+        TxManager::new(
+            db,
+            &self.name,
+            self.tx.conf(),
+            Some(metrics.tx_metrics(&self.name)),
+        )
+    }
+
     /// Try to convert the chain setting into a Home contract
-    pub async fn try_into_home(&self, signer: Option<Signers>, db: DB) -> Result<Homes, Report> {
+    pub async fn try_into_home(
+        &self,
+        signer: Option<Signers>,
+        db: DB,
+        metrics: &CoreMetrics,
+    ) -> Result<Homes, Report> {
         match &self.chain {
             ChainConf::Ethereum(conf) => Ok(Homes::Ethereum(
                 make_home(
@@ -54,7 +82,8 @@ impl ChainSetup {
                         address: self.address.parse::<ethers::types::Address>()?.into(),
                     },
                     signer,
-                    db,
+                    db.clone(),
+                    self.tx_manager(db, metrics),
                 )
                 .await?,
             )),
@@ -62,7 +91,12 @@ impl ChainSetup {
     }
 
     /// Try to convert the chain setting into a replica contract
-    pub async fn try_into_replica(&self, signer: Option<Signers>) -> Result<Replicas, Report> {
+    pub async fn try_into_replica(
+        &self,
+        signer: Option<Signers>,
+        db: DB,
+        metrics: &CoreMetrics,
+    ) -> Result<Replicas, Report> {
         match &self.chain {
             ChainConf::Ethereum(conf) => Ok(Replicas::Ethereum(
                 make_replica(
@@ -73,6 +107,8 @@ impl ChainSetup {
                         address: self.address.parse::<ethers::types::Address>()?.into(),
                     },
                     signer,
+                    db.clone(),
+                    self.tx_manager(db, metrics),
                 )
                 .await?,
             )),
@@ -83,6 +119,8 @@ impl ChainSetup {
     pub async fn try_into_connection_manager(
         &self,
         signer: Option<Signers>,
+        db: DB,
+        metrics: &CoreMetrics,
     ) -> Result<ConnectionManagers, Report> {
         match &self.chain {
             ChainConf::Ethereum(conf) => Ok(ConnectionManagers::Ethereum(
@@ -94,6 +132,7 @@ impl ChainSetup {
                         address: self.address.parse::<ethers::types::Address>()?.into(),
                     },
                     signer,
+                    self.tx_manager(db, metrics),
                 )
                 .await?,
             )),
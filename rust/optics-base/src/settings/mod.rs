@@ -36,15 +36,16 @@
 //!    intended to be used by a specific agent.
 //!    E.g. `export OPT_KATHY_CHAT_TYPE="static message"`
 
-use crate::{agent::AgentCore, home::Homes, replica::Replicas};
+use crate::{agent::AgentCore, home::Homes, metrics::CoreMetrics, replica::Replicas};
 use color_eyre::{eyre::bail, Report};
 use config::{Config, ConfigError, Environment, File};
-use ethers::prelude::AwsSigner;
+use ethers::prelude::{AwsSigner, U256};
 use optics_core::{db::DB, utils::HexString, Signers};
+use optics_ethereum::TxManagerConf;
 use rusoto_core::{credential::EnvironmentProvider, HttpClient};
 use rusoto_kms::KmsClient;
 use serde::Deserialize;
-use std::{collections::HashMap, env, sync::Arc};
+use std::{collections::HashMap, env, sync::Arc, time::Duration};
 use tracing::instrument;
 
 /// Chain configuartion
@@ -114,14 +115,16 @@ impl SignerConf {
     }
 }
 
-/// Home indexing settings
+/// Home and replica indexing settings
 #[derive(Debug, Deserialize, Default, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct IndexSettings {
-    /// The height at which to start indexing the Home contract
+    /// The height at which to start indexing the contract
     from: Option<String>,
     /// The number of blocks to query at once at which to start indexing the Home contract
     chunk: Option<String>,
+    /// The number of blocks the indexer should stay behind the chain tip
+    confirmations: Option<String>,
 }
 
 impl IndexSettings {
@@ -140,6 +143,50 @@ impl IndexSettings {
             .and_then(|s| s.parse::<u32>().ok())
             .unwrap_or(1999)
     }
+
+    /// Get the `confirmations` setting
+    pub fn confirmations(&self) -> u32 {
+        self.confirmations
+            .as_ref()
+            .and_then(|s| s.parse::<u32>().ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Transaction submission settings
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TxSettings {
+    /// Seconds to wait for a transaction to be included before bumping its
+    /// gas price and resubmitting it
+    escalation_interval: Option<String>,
+    /// Percentage to bump the gas price by on each resubmission
+    escalation_percent: Option<String>,
+    /// Cap on the gas price or EIP-1559 max fee per gas, in wei
+    max_fee: Option<String>,
+    /// Seconds between receipt polls
+    poll_interval: Option<String>,
+}
+
+impl TxSettings {
+    /// Get the `TxManagerConf` for these settings
+    pub fn conf(&self) -> TxManagerConf {
+        let default = TxManagerConf::default();
+        let int = |s: &Option<String>| s.as_ref().map(|s| s.parse().expect("invalid integer"));
+        TxManagerConf {
+            escalation_interval: int(&self.escalation_interval)
+                .map(Duration::from_secs)
+                .unwrap_or(default.escalation_interval),
+            escalation_percent: int(&self.escalation_percent).unwrap_or(default.escalation_percent),
+            max_fee: self
+                .max_fee
+                .as_ref()
+                .map(|s| U256::from_dec_str(s).expect("invalid integer")),
+            poll_interval: int(&self.poll_interval)
+                .map(Duration::from_secs)
+                .unwrap_or(default.poll_interval),
+        }
+    }
 }
 
 /// Settings. Usually this should be treated as a base config and used as
@@ -208,7 +255,11 @@ impl Settings {
     }
 
     /// Try to get all replicas from this settings object
-    pub async fn try_replicas(&self) -> Result<HashMap<String, Arc<Replicas>>, Report> {
+    pub async fn try_replicas(
+        &self,
+        db: DB,
+        metrics: &CoreMetrics,
+    ) -> Result<HashMap<String, Arc<Replicas>>, Report> {
         let mut result = HashMap::default();
         for (k, v) in self.replicas.iter().filter(|(_, v)| v.disabled.is_none()) {
             if k != &v.name {
@@ -219,20 +270,23 @@ impl Settings {
                 );
             }
             let signer = self.get_signer(&v.name).await;
-            result.insert(v.name.clone(), Arc::new(v.try_into_replica(signer).await?));
+            result.insert(
+                v.name.clone(),
+                Arc::new(v.try_into_replica(signer, db.clone(), metrics).await?),
+            );
         }
         Ok(result)
     }
 
     /// Try to get a home object
-    pub async fn try_home(&self, db: DB) -> Result<Homes, Report> {
+    pub async fn try_home(&self, db: DB, metrics: &CoreMetrics) -> Result<Homes, Report> {
         let signer = self.get_signer(&self.home.name).await;
-        self.home.try_into_home(signer, db).await
+        self.home.try_into_home(signer, db, metrics).await
     }
 
     /// Try to generate an agent core for a named agent
     pub async fn try_into_core(&self, name: &str) -> Result<AgentCore, Report> {
-        let metrics = Arc::new(crate::metrics::CoreMetrics::new(
+        let metrics = Arc::new(CoreMetrics::new(
             name,
             self.metrics
                 .as_ref()
@@ -241,8 +295,8 @@ impl Settings {
         )?);
 
         let db = DB::from_path(&self.db)?;
-        let home = Arc::new(self.try_home(db.clone()).await?);
-        let replicas = self.try_replicas().await?;
+        let home = Arc::new(self.try_home(db.clone(), &metrics).await?);
+        let replicas = self.try_replicas(db.clone(), &metrics).await?;
 
         Ok(AgentCore {
             home,
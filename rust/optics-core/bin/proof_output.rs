@@ -5,5 +5,6 @@ fn main() {
     #[cfg(feature = "output")]
     {
         output_merkle_proof();
+        output_multi_proof();
     }
 }
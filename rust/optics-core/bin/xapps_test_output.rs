@@ -0,0 +1,10 @@
+#[cfg(feature = "output")]
+use optics_core::test_output::output_functions::*;
+
+fn main() {
+    #[cfg(feature = "output")]
+    {
+        output_bridge_messages();
+        output_governance_messages();
+    }
+}
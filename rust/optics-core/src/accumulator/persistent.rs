@@ -0,0 +1,459 @@
+use ethers::core::types::H256;
+
+use crate::{
+    accumulator::{
+        consistency::ConsistencyProof, hash_concat, incremental::IncrementalMerkle, merkle::Proof,
+        INITIAL_ROOT, TREE_DEPTH, ZERO_HASHES,
+    },
+    db::{DbError, TypedDB, DB},
+    Encode,
+};
+
+static NODE: &str = "tree_node_";
+static COUNT: &str = "tree_count";
+static ROOT: &str = "tree_root_";
+
+/// Error type for persistent merkle tree ops.
+#[derive(Debug, thiserror::Error)]
+pub enum PersistentMerkleError {
+    /// No more space in the tree
+    #[error("No more space in the tree")]
+    TreeFull,
+    /// Requested a leaf that is not in the tree
+    #[error("Requested leaf {index} of a tree with {count} leaves")]
+    IndexOutOfRange {
+        /// The index requested
+        index: usize,
+        /// The number of leaves
+        count: usize,
+    },
+    /// Requested a tree size the tree has not reached
+    #[error("Requested the tree at {requested} leaves. Tree has: {count}")]
+    CountTooHigh {
+        /// The number of leaves requested
+        requested: usize,
+        /// The number of leaves
+        count: usize,
+    },
+    /// The root was never a root of the tree, or was dropped by `truncate`
+    #[error("Unknown root {0:?}")]
+    UnknownRoot(H256),
+    /// A node of a complete subtree is missing from the DB
+    #[error("Missing node at height {height}, index {index}")]
+    MissingNode {
+        /// The height of the node
+        height: usize,
+        /// The index of the node within its layer
+        index: u64,
+    },
+    /// DB Error
+    #[error("{0}")]
+    DbError(#[from] DbError),
+}
+
+/// An append-only merkle tree that persists its nodes to a DB.
+///
+/// Every complete subtree's root is written once, when its last leaf is
+/// ingested. The tree as it was at any earlier leaf count can therefore be
+/// rebuilt from O(depth) nodes, which makes loading the tree, and proving
+/// under historical roots, cheap.
+///
+/// Key structure:
+/// - ```tree_node_<height (1 byte)><index (4 bytes)>``` --> node
+/// - ```tree_root_<root>``` --> leaf count at that root
+#[derive(Debug, Clone)]
+pub struct PersistentMerkle {
+    db: TypedDB,
+    incremental: IncrementalMerkle,
+}
+
+impl PersistentMerkle {
+    /// Load the tree stored in `db`, or an empty tree if there is none
+    pub fn load(db: TypedDB) -> Result<Self, PersistentMerkleError> {
+        let count: u32 = db.retrieve_decodable(COUNT, "")?.unwrap_or_default();
+        let mut tree = Self {
+            db,
+            incremental: Default::default(),
+        };
+        tree.incremental = tree.incremental_at(count as usize)?;
+        Ok(tree)
+    }
+
+    /// An empty tree backed by an in-memory DB
+    pub fn in_memory() -> Self {
+        Self {
+            db: TypedDB::new(DB::in_memory(), "merkle"),
+            incremental: Default::default(),
+        }
+    }
+
+    fn node_key(height: usize, index: u64) -> Vec<u8> {
+        let mut key = vec![height as u8];
+        key.extend((index as u32).to_vec());
+        key
+    }
+
+    fn node(&self, height: usize, index: u64) -> Result<H256, PersistentMerkleError> {
+        self.db
+            .retrieve_decodable(NODE, Self::node_key(height, index))?
+            .ok_or(PersistentMerkleError::MissingNode { height, index })
+    }
+
+    /// The root of the subtree at `height` and `index` in the tree as it was
+    /// at `count` leaves
+    fn subtree_root(
+        &self,
+        height: usize,
+        index: u64,
+        count: u64,
+    ) -> Result<H256, PersistentMerkleError> {
+        let start = index << height;
+        let end = (index + 1) << height;
+        if end <= count {
+            self.node(height, index)
+        } else if start >= count {
+            Ok(ZERO_HASHES[height])
+        } else {
+            // Partially filled. Only one child can be partially filled, so
+            // this recurses at most once per layer
+            Ok(hash_concat(
+                self.subtree_root(height - 1, 2 * index, count)?,
+                self.subtree_root(height - 1, 2 * index + 1, count)?,
+            ))
+        }
+    }
+
+    fn check_count(&self, requested: usize) -> Result<(), PersistentMerkleError> {
+        let count = self.count();
+        if requested > count {
+            return Err(PersistentMerkleError::CountTooHigh { requested, count });
+        }
+        Ok(())
+    }
+
+    /// Get the number of leaves in the tree
+    pub fn count(&self) -> usize {
+        self.incremental.count()
+    }
+
+    /// Calculate the current tree root
+    pub fn root(&self) -> H256 {
+        self.incremental.root()
+    }
+
+    /// Get the current leading-edge branch as an incremental tree
+    pub fn incremental(&self) -> IncrementalMerkle {
+        self.incremental
+    }
+
+    /// Rebuild the leading-edge branch of the tree as it was at `count`
+    /// leaves
+    pub fn incremental_at(&self, count: usize) -> Result<IncrementalMerkle, PersistentMerkleError> {
+        let mut branch = [H256::zero(); TREE_DEPTH];
+        for (height, elem) in branch.iter_mut().enumerate() {
+            // The branch holds the last complete subtree of each layer whose
+            // bit is set in the count
+            if (count >> height) & 1 == 1 {
+                *elem = self.node(height, (count as u64 >> height) - 1)?;
+            }
+        }
+        Ok(IncrementalMerkle::new(branch, count))
+    }
+
+    /// Calculate the tree root as it was at `count` leaves
+    pub fn root_at(&self, count: usize) -> Result<H256, PersistentMerkleError> {
+        self.check_count(count)?;
+        Ok(self.incremental_at(count)?.root())
+    }
+
+    /// Look up the leaf count at which the tree had root `root`
+    pub fn count_at_root(&self, root: H256) -> Result<Option<usize>, PersistentMerkleError> {
+        if root == *INITIAL_ROOT {
+            return Ok(Some(0));
+        }
+        let count = match self.db.retrieve_decodable::<u32>(ROOT, root)? {
+            Some(count) => count as usize,
+            None => return Ok(None),
+        };
+        // Roots of truncated leaves are left in place, and may no longer be
+        // roots of the tree
+        if count > self.count() || self.root_at(count)? != root {
+            return Ok(None);
+        }
+        Ok(Some(count))
+    }
+
+    /// Retrieve the leaf at `index`
+    pub fn leaf(&self, index: usize) -> Result<H256, PersistentMerkleError> {
+        let count = self.count();
+        if index >= count {
+            return Err(PersistentMerkleError::IndexOutOfRange { index, count });
+        }
+        self.node(0, index as u64)
+    }
+
+    /// Push leaves to the tree, writing them atomically. Returns the new
+    /// root.
+    pub fn ingest_all(
+        &mut self,
+        leaves: impl IntoIterator<Item = H256>,
+    ) -> Result<H256, PersistentMerkleError> {
+        let batch = self.db.batched();
+        let mut incremental = self.incremental;
+
+        for leaf in leaves {
+            let index = incremental.count() as u64;
+            if index >= u32::MAX as u64 {
+                return Err(PersistentMerkleError::TreeFull);
+            }
+            batch.store_encodable(NODE, Self::node_key(0, index), &leaf)?;
+
+            // Store the root of every subtree this leaf completes
+            let mut node = leaf;
+            let mut size = index + 1;
+            for height in 0..TREE_DEPTH {
+                if (size & 1) == 1 {
+                    break;
+                }
+                node = hash_concat(incremental.branch()[height], node);
+                size /= 2;
+                batch.store_encodable(NODE, Self::node_key(height + 1, size - 1), &node)?;
+            }
+            incremental.ingest(leaf);
+            batch.store_encodable(ROOT, incremental.root(), &(incremental.count() as u32))?;
+        }
+
+        batch.store_encodable(COUNT, "", &(incremental.count() as u32))?;
+        batch.commit()?;
+        self.incremental = incremental;
+        Ok(self.root())
+    }
+
+    /// Push a leaf to the tree. Returns the new root.
+    pub fn ingest(&mut self, leaf: H256) -> Result<H256, PersistentMerkleError> {
+        self.ingest_all(std::iter::once(leaf))
+    }
+
+    /// Drop every leaf at or after `count`. Nodes of the dropped leaves are
+    /// left in place, and are overwritten as new leaves are ingested.
+    pub fn truncate(&mut self, count: usize) -> Result<(), PersistentMerkleError> {
+        self.check_count(count)?;
+        let incremental = self.incremental_at(count)?;
+        self.db.store_encodable(COUNT, "", &(count as u32))?;
+        self.incremental = incremental;
+        Ok(())
+    }
+
+    /// Create a proof of the leaf at `index` under the current root
+    pub fn prove(&self, index: usize) -> Result<Proof, PersistentMerkleError> {
+        self.prove_at(index, self.count())
+    }
+
+    /// Create a proof of the leaf at `index` under the root of the tree as it
+    /// was at `count` leaves
+    pub fn prove_at(&self, index: usize, count: usize) -> Result<Proof, PersistentMerkleError> {
+        self.check_count(count)?;
+        if index >= count {
+            return Err(PersistentMerkleError::IndexOutOfRange { index, count });
+        }
+
+        let mut path = [H256::zero(); TREE_DEPTH];
+        for (height, sibling) in path.iter_mut().enumerate() {
+            *sibling = self.subtree_root(height, (index as u64 >> height) ^ 1, count as u64)?;
+        }
+        Ok(Proof {
+            leaf: self.node(0, index as u64)?,
+            index,
+            path,
+        })
+    }
+
+    /// Create a proof of the leaf at `index` under `root`, which may be any
+    /// current or past root of the tree
+    pub fn prove_under_root(
+        &self,
+        index: usize,
+        root: H256,
+    ) -> Result<Proof, PersistentMerkleError> {
+        let count = self
+            .count_at_root(root)?
+            .ok_or(PersistentMerkleError::UnknownRoot(root))?;
+        self.prove_at(index, count)
+    }
+
+    /// Create a proof that the tree at `new_count` leaves extends the tree at
+    /// `old_count` leaves
+    pub fn prove_consistency(
+        &self,
+        old_count: usize,
+        new_count: usize,
+    ) -> Result<ConsistencyProof, PersistentMerkleError> {
+        self.check_count(new_count)?;
+        if old_count > new_count {
+            return Err(PersistentMerkleError::CountTooHigh {
+                requested: old_count,
+                count: new_count,
+            });
+        }
+
+        let old_branch = ConsistencyProof::old_branch(&self.incremental_at(old_count)?);
+        let mut new_nodes = vec![];
+        if new_count > old_count {
+            let (old_count, new_count) = (old_count as u64, new_count as u64);
+            new_nodes.push(self.node(0, old_count)?);
+            for height in 0..TREE_DEPTH {
+                let index = old_count >> height;
+                if index & 1 == 0 && (index + 1) << height < new_count {
+                    new_nodes.push(self.subtree_root(height, index + 1, new_count)?);
+                }
+            }
+        }
+
+        Ok(ConsistencyProof {
+            old_count,
+            new_count,
+            old_branch,
+            new_nodes,
+        })
+    }
+
+    /// Create a proof that the tree with root `new_root` extends the tree
+    /// with root `old_root`
+    pub fn prove_consistency_between_roots(
+        &self,
+        old_root: H256,
+        new_root: H256,
+    ) -> Result<ConsistencyProof, PersistentMerkleError> {
+        let old_count = self
+            .count_at_root(old_root)?
+            .ok_or(PersistentMerkleError::UnknownRoot(old_root))?;
+        let new_count = self
+            .count_at_root(new_root)?
+            .ok_or(PersistentMerkleError::UnknownRoot(new_root))?;
+        self.prove_consistency(old_count, new_count)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ethers::utils::hash_message;
+
+    use super::*;
+    use crate::{accumulator::consistency::ConsistencyProofError, test_utils, Decode};
+
+    #[test]
+    fn it_produces_proofs_under_current_and_historical_roots() {
+        let test_cases = test_utils::load_merkle_test_json();
+
+        for test_case in test_cases.iter() {
+            let leaves: Vec<_> = test_case.leaves.iter().map(hash_message).collect();
+            let mut tree = PersistentMerkle::in_memory();
+            tree.ingest_all(leaves.iter().copied()).unwrap();
+
+            assert_eq!(tree.count(), leaves.len());
+            assert_eq!(tree.root(), test_case.expected_root);
+            for n in 0..leaves.len() {
+                assert_eq!(tree.prove(n).unwrap(), test_case.proofs[n]);
+            }
+
+            // Historical proofs match proofs from a tree of that size
+            for count in 1..=leaves.len() {
+                let mut smaller = IncrementalMerkle::default();
+                leaves[..count]
+                    .iter()
+                    .for_each(|leaf| smaller.ingest(*leaf));
+                assert_eq!(tree.root_at(count).unwrap(), smaller.root());
+                for n in 0..count {
+                    let proof = tree.prove_at(n, count).unwrap();
+                    assert_eq!(proof.leaf, leaves[n]);
+                    assert!(smaller.verify(&proof));
+                    assert_eq!(tree.prove_under_root(n, smaller.root()).unwrap(), proof);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn it_reloads_and_truncates() {
+        let db = TypedDB::new(DB::in_memory(), "home_1");
+        let leaves: Vec<_> = (0..13u64).map(H256::from_low_u64_be).collect();
+
+        let mut tree = PersistentMerkle::load(db.clone()).unwrap();
+        assert_eq!(tree.count(), 0);
+        tree.ingest_all(leaves[..7].iter().copied()).unwrap();
+        tree.ingest_all(leaves[7..].iter().copied()).unwrap();
+
+        let reloaded = PersistentMerkle::load(db.clone()).unwrap();
+        assert_eq!(reloaded.count(), leaves.len());
+        assert_eq!(reloaded.root(), tree.root());
+        assert_eq!(reloaded.prove(5).unwrap(), tree.prove(5).unwrap());
+
+        // Replace the last 4 leaves
+        let root_at_9 = tree.root_at(9).unwrap();
+        tree.truncate(9).unwrap();
+        assert_eq!(tree.root(), root_at_9);
+        assert!(tree.prove(9).is_err());
+        let dropped_root = reloaded.root();
+        assert!(matches!(
+            tree.prove_under_root(0, dropped_root),
+            Err(PersistentMerkleError::UnknownRoot(root)) if root == dropped_root
+        ));
+        tree.ingest_all((100..104u64).map(H256::from_low_u64_be))
+            .unwrap();
+
+        let mut expected = IncrementalMerkle::default();
+        leaves[..9].iter().for_each(|leaf| expected.ingest(*leaf));
+        (100..104u64).for_each(|i| expected.ingest(H256::from_low_u64_be(i)));
+        assert_eq!(tree.root(), expected.root());
+        assert!(expected.verify(&tree.prove(11).unwrap()));
+        assert_eq!(PersistentMerkle::load(db).unwrap().root(), expected.root());
+    }
+
+    fn tree_of(leaves: impl IntoIterator<Item = H256>) -> PersistentMerkle {
+        let mut tree = PersistentMerkle::in_memory();
+        tree.ingest_all(leaves).unwrap();
+        tree
+    }
+
+    #[test]
+    fn it_proves_consistency_between_any_sizes() {
+        let tree = tree_of((0..17u64).map(H256::from_low_u64_be));
+
+        for new_count in 0..=17 {
+            for old_count in 0..=new_count {
+                let proof = tree.prove_consistency(old_count, new_count).unwrap();
+                let old_root = tree.root_at(old_count).unwrap();
+                let new_root = tree.root_at(new_count).unwrap();
+                assert!(proof.verify(old_root, new_root));
+
+                let decoded = ConsistencyProof::read_from(&mut proof.to_vec().as_slice()).unwrap();
+                assert_eq!(decoded, proof);
+            }
+        }
+
+        let proof = tree
+            .prove_consistency_between_roots(*INITIAL_ROOT, tree.root())
+            .unwrap();
+        assert_eq!((proof.old_count, proof.new_count), (0, 17));
+    }
+
+    #[test]
+    fn it_rejects_inconsistent_trees() {
+        let tree = tree_of((0..9u64).map(H256::from_low_u64_be));
+        // Same size, but leaf 2 differs
+        let other = tree_of((0..9u64).map(|i| H256::from_low_u64_be(if i == 2 { 100 } else { i })));
+
+        let proof = tree.prove_consistency(5, 9).unwrap();
+        assert!(!proof.verify(other.root_at(5).unwrap(), tree.root()));
+        assert!(!proof.verify(tree.root_at(5).unwrap(), other.root()));
+        assert!(!proof.verify(tree.root_at(4).unwrap(), tree.root()));
+
+        let mut short = proof.clone();
+        short.new_nodes.pop();
+        assert_eq!(short.roots(), Err(ConsistencyProofError::MissingNodes));
+
+        let mut long = proof;
+        long.old_branch.push(H256::zero());
+        assert_eq!(long.roots(), Err(ConsistencyProofError::ExtraNodes));
+    }
+}
@@ -1,40 +1,314 @@
 use crate::db::{DbError, TypedDB, DB};
 use crate::UpdateMeta;
 use crate::{
-    accumulator::merkle::Proof, traits::RawCommittedMessage, utils, Decode, Encode, OpticsMessage,
-    SignedUpdate,
+    accumulator::{
+        merkle::Proof,
+        persistent::{PersistentMerkle, PersistentMerkleError},
+    },
+    traits::RawCommittedMessage,
+    utils, Decode, Encode, OpticsError, SignedUpdate,
 };
 use color_eyre::Result;
-use ethers::core::types::H256;
-use tokio::time::sleep;
+use ethers::core::types::{H256, U256};
+use std::{
+    ops::Deref,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::broadcast::{self, error::RecvError};
 use tracing::{debug, warn};
 
-use std::future::Future;
-use std::time::Duration;
-
 use crate::db::iterator::PrefixIterator;
 
 static LEAF_IDX: &str = "leaf_index_";
 static LEAF: &str = "leaf_";
 static PREV_ROOT: &str = "update_prev_root_";
 static PROOF: &str = "proof_";
+static ROOT_PROOF: &str = "root_proof_";
 static MESSAGE: &str = "message_";
 static UPDATE: &str = "update_";
 static UPDATE_META: &str = "update_metadata_";
+static UPDATE_TIMESTAMP: &str = "update_timestamp_";
 static LATEST_ROOT: &str = "update_latest_root_";
 static LATEST_NONCE: &str = "latest_nonce_";
 static LATEST_LEAF_INDEX: &str = "latest_known_leaf_index_";
+static LEAF_BLOCK: &str = "leaf_block_";
+static ROLLBACKS: &str = "rollback_count_";
+static SKIPPED: &str = "skipped_message_";
+static DEAD_LETTER: &str = "dead_letter_";
+static PUSHED: &str = "pushed_leaf_index_";
+static SENDER_GAS: &str = "gas_spent_by_sender_";
+static RECIPIENT_GAS: &str = "gas_spent_on_recipient_";
+
+/// A change to the contents of a `HomeDB`. Published to all subscribers
+/// after the change is written.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HomeEvent {
+    /// A leaf and its message were stored
+    Leaf {
+        /// The index of the leaf in the tree
+        leaf_index: u32,
+        /// The leaf hash
+        leaf: H256,
+    },
+    /// A signed update was stored
+    Update {
+        /// The update's previous root
+        previous_root: H256,
+        /// The update's new root
+        new_root: H256,
+    },
+    /// A proof was stored
+    Proof {
+        /// The index of the proven leaf
+        leaf_index: u32,
+    },
+    /// Leaves and updates were removed by a rollback
+    RolledBack,
+}
+
+/// Whose gas spending the processor tracks for a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GasAccount {
+    /// The message's sender
+    Sender,
+    /// The message's recipient
+    Recipient,
+}
+
+impl GasAccount {
+    fn prefix(&self) -> &'static str {
+        match self {
+            GasAccount::Sender => SENDER_GAS,
+            GasAccount::Recipient => RECIPIENT_GAS,
+        }
+    }
+}
+
+impl std::fmt::Display for GasAccount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GasAccount::Sender => write!(f, "sender"),
+            GasAccount::Recipient => write!(f, "recipient"),
+        }
+    }
+}
+
+/// A message the processor skipped, and the rule that skipped it
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkippedMessage {
+    /// The index of the message's leaf
+    pub leaf_index: u32,
+    /// The name of the rule that matched the message
+    pub rule: String,
+    /// True if the message should be processed once a rule allows it.
+    /// False if it was denied
+    pub deferred: bool,
+    /// When the message was skipped, in seconds since the unix epoch
+    pub timestamp: u64,
+}
+
+impl Encode for SkippedMessage {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        let mut written = self.leaf_index.write_to(writer)?;
+        written += self.rule.write_to(writer)?;
+        writer.write_all(&[self.deferred as u8])?;
+        written += 1;
+        written += self.timestamp.write_to(writer)?;
+        Ok(written)
+    }
+}
+
+impl Decode for SkippedMessage {
+    fn read_from<R>(reader: &mut R) -> Result<Self, OpticsError>
+    where
+        R: std::io::Read,
+        Self: Sized,
+    {
+        let leaf_index = u32::read_from(reader)?;
+        let rule = String::read_from(reader)?;
+
+        let mut deferred = [0u8; 1];
+        reader.read_exact(&mut deferred)?;
+
+        Ok(Self {
+            leaf_index,
+            rule,
+            deferred: deferred[0] != 0,
+            timestamp: u64::read_from(reader)?,
+        })
+    }
+}
+
+/// A message whose processing failed, held in the processor's dead-letter
+/// queue until a retry succeeds or it is dropped
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeadLetter {
+    /// The index of the message's leaf
+    pub leaf_index: u32,
+    /// Why the latest attempt failed, e.g. the revert reason
+    pub reason: String,
+    /// The number of failed attempts
+    pub attempts: u32,
+    /// When the first attempt failed, in seconds since the unix epoch
+    pub first_failed: u64,
+    /// When the latest attempt failed, in seconds since the unix epoch
+    pub last_failed: u64,
+    /// When to retry, in seconds since the unix epoch. None once the retries
+    /// are used up
+    pub retry_at: Option<u64>,
+    /// True if the message was deferred by a rule, and still has a skipped
+    /// message record
+    pub deferred: bool,
+}
+
+impl Encode for DeadLetter {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        let mut written = self.leaf_index.write_to(writer)?;
+        written += self.reason.write_to(writer)?;
+        written += self.attempts.write_to(writer)?;
+        written += self.first_failed.write_to(writer)?;
+        written += self.last_failed.write_to(writer)?;
+        written += self.retry_at.write_to(writer)?;
+        writer.write_all(&[self.deferred as u8])?;
+        Ok(written + 1)
+    }
+}
+
+impl Decode for DeadLetter {
+    fn read_from<R>(reader: &mut R) -> Result<Self, OpticsError>
+    where
+        R: std::io::Read,
+        Self: Sized,
+    {
+        let leaf_index = u32::read_from(reader)?;
+        let reason = String::read_from(reader)?;
+        let attempts = u32::read_from(reader)?;
+        let first_failed = u64::read_from(reader)?;
+        let last_failed = u64::read_from(reader)?;
+        let retry_at = Option::<u64>::read_from(reader)?;
+
+        let mut flag = [0u8; 1];
+        reader.read_exact(&mut flag)?;
+
+        Ok(Self {
+            leaf_index,
+            reason,
+            attempts,
+            first_failed,
+            last_failed,
+            retry_at,
+            deferred: flag[0] != 0,
+        })
+    }
+}
 
 /// DB handle for storing data tied to a specific home.
 ///
 /// Key structure: ```<home_name>_<additional_prefix(es)>_<key>```
 #[derive(Debug, Clone)]
-pub struct HomeDB(TypedDB);
+pub struct HomeDB {
+    db: TypedDB,
+    events: broadcast::Sender<HomeEvent>,
+    // Events held back until a batch is committed
+    pending_events: Option<Arc<Mutex<Vec<HomeEvent>>>>,
+}
+
+/// A `HomeDB` handle whose writes are staged and committed atomically by
+/// `commit`. Reads through the handle see its staged writes. Events are
+/// published once the batch is committed, and dropped if it isn't.
+#[derive(Debug)]
+pub struct HomeBatch(HomeDB);
+
+impl Deref for HomeBatch {
+    type Target = HomeDB;
+
+    fn deref(&self) -> &HomeDB {
+        &self.0
+    }
+}
+
+impl HomeBatch {
+    /// Write all staged changes, then publish their events
+    pub fn commit(self) -> Result<(), DbError> {
+        self.0.db.commit()?;
+
+        let pending = self
+            .0
+            .pending_events
+            .as_ref()
+            .map(|pending| std::mem::take(&mut *pending.lock().expect("poisoned")))
+            .unwrap_or_default();
+        for event in pending {
+            // An error means there are no subscribers, which is fine
+            let _ = self.0.events.send(event);
+        }
+        Ok(())
+    }
+}
 
 impl HomeDB {
     /// Instantiated new `HomeDB`
     pub fn new(db: DB, home_name: String) -> Self {
-        Self(TypedDB::new(db, home_name))
+        let events = db.home_events(&home_name);
+        Self {
+            db: TypedDB::new(db, home_name),
+            events,
+            pending_events: None,
+        }
+    }
+
+    /// Start a batch of writes to be committed atomically
+    pub fn batch(&self) -> HomeBatch {
+        HomeBatch(Self {
+            db: self.db.batched(),
+            events: self.events.clone(),
+            pending_events: Some(Default::default()),
+        })
+    }
+
+    /// Subscribe to changes to this home's data
+    pub fn subscribe(&self) -> broadcast::Receiver<HomeEvent> {
+        self.events.subscribe()
+    }
+
+    fn publish(&self, event: HomeEvent) {
+        if let Some(pending) = &self.pending_events {
+            pending.lock().expect("poisoned").push(event);
+            return;
+        }
+        // An error means there are no subscribers, which is fine
+        let _ = self.events.send(event);
+    }
+
+    /// Wait until `check` returns `Some`. `check` is re-run whenever an event
+    /// matching `relevant` is published, or if events were missed.
+    pub async fn wait_until<T, F, P>(&self, mut check: F, relevant: P) -> Result<T, DbError>
+    where
+        F: FnMut() -> Result<Option<T>, DbError>,
+        P: Fn(&HomeEvent) -> bool,
+    {
+        // Subscribe before checking, so that no event is missed between the
+        // check and the wait
+        let mut rx = self.subscribe();
+        loop {
+            if let Some(t) = check()? {
+                return Ok(t);
+            }
+            loop {
+                match rx.recv().await {
+                    Ok(event) if relevant(&event) => break,
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(_)) => break,
+                    Err(RecvError::Closed) => unreachable!("HomeDB holds a sender"),
+                }
+            }
+        }
     }
 
     /// Store encodable value
@@ -44,7 +318,7 @@ impl HomeDB {
         key: impl AsRef<[u8]>,
         value: &V,
     ) -> Result<(), DbError> {
-        self.0.store_encodable(prefix, key, value)
+        self.db.store_encodable(prefix, key, value)
     }
 
     /// Retrieve decodable value
@@ -53,7 +327,7 @@ impl HomeDB {
         prefix: impl AsRef<[u8]>,
         key: impl AsRef<[u8]>,
     ) -> Result<Option<V>, DbError> {
-        self.0.retrieve_decodable(prefix, key)
+        self.db.retrieve_decodable(prefix, key)
     }
 
     /// Store encodable kv pair
@@ -63,7 +337,7 @@ impl HomeDB {
         key: &K,
         value: &V,
     ) -> Result<(), DbError> {
-        self.0.store_encodable(prefix, key.to_vec(), value)
+        self.db.store_encodable(prefix, key.to_vec(), value)
     }
 
     /// Retrieve decodable value given encodable key
@@ -72,7 +346,7 @@ impl HomeDB {
         prefix: impl AsRef<[u8]>,
         key: &K,
     ) -> Result<Option<V>, DbError> {
-        self.0.retrieve_decodable(prefix, key.to_vec())
+        self.db.retrieve_decodable(prefix, key.to_vec())
     }
 
     /// Store a raw committed message
@@ -82,7 +356,7 @@ impl HomeDB {
     /// - `leaf_index` --> `leaf`
     /// - `leaf` --> `message`
     pub fn store_raw_committed_message(&self, message: &RawCommittedMessage) -> Result<()> {
-        let parsed = OpticsMessage::read_from(&mut message.message.clone().as_slice())?;
+        let parsed = message.view()?;
 
         let destination_and_nonce = parsed.destination_and_nonce();
 
@@ -91,16 +365,37 @@ impl HomeDB {
         debug!(
             leaf = ?leaf,
             destination_and_nonce,
-            destination = parsed.destination,
-            nonce = parsed.nonce,
+            destination = parsed.destination(),
+            nonce = parsed.nonce(),
             leaf_index = message.leaf_index,
             "storing raw committed message in db"
         );
-        self.store_leaf(message.leaf_index, destination_and_nonce, leaf)?;
-        self.store_keyed_encodable(MESSAGE, &leaf, message)?;
+        self.atomically(|batch| {
+            batch.store_leaf(message.leaf_index, destination_and_nonce, leaf)?;
+            batch.store_keyed_encodable(MESSAGE, &leaf, message)?;
+            batch.publish(HomeEvent::Leaf {
+                leaf_index: message.leaf_index,
+                leaf,
+            });
+            Ok(())
+        })?;
         Ok(())
     }
 
+    /// Run `f` against a batch, and commit it if `f` succeeds. If this
+    /// handle is already a batch, `f` joins it instead
+    fn atomically<F>(&self, f: F) -> Result<(), DbError>
+    where
+        F: FnOnce(&HomeDB) -> Result<(), DbError>,
+    {
+        if self.pending_events.is_some() {
+            return f(self);
+        }
+        let batch = self.batch();
+        f(&batch)?;
+        batch.commit()
+    }
+
     /// Store the latest known leaf_index
     ///
     /// Key --> value: `LATEST_LEAF_INDEX` --> `leaf_index`
@@ -131,7 +426,7 @@ impl HomeDB {
             "storing leaf hash keyed by index and dest+nonce"
         );
         self.store_keyed_encodable(LEAF, &destination_and_nonce, &leaf)?;
-        self.store_keyed_encodable(LEAF, &leaf_index, &leaf)?;
+        self.store_keyed_encodable(LEAF_IDX, &leaf_index, &leaf)?;
         self.update_latest_leaf_index(leaf_index)
     }
 
@@ -142,7 +437,7 @@ impl HomeDB {
 
     /// Retrieve the leaf hash keyed by leaf index
     pub fn leaf_by_leaf_index(&self, leaf_index: u32) -> Result<Option<H256>, DbError> {
-        self.retrieve_keyed_decodable(LEAF, &leaf_index)
+        self.retrieve_keyed_decodable(LEAF_IDX, &leaf_index)
     }
 
     /// Retrieve the leaf hash keyed by destination and nonce
@@ -191,6 +486,230 @@ impl HomeDB {
         self.retrieve_keyed_decodable(LATEST_NONCE, &replica_domain)
     }
 
+    /// Stores the latest leaf index whose proof was published to `sink`
+    ///
+    /// Keys --> Values:
+    /// - `sink` --> `leaf_index`
+    pub fn store_pushed_leaf_index(&self, sink: &str, leaf_index: u32) -> Result<(), DbError> {
+        self.store_encodable(PUSHED, sink, &leaf_index)
+    }
+
+    /// Retrieves the latest leaf index whose proof was published to `sink`
+    pub fn retrieve_pushed_leaf_index(&self, sink: &str) -> Result<Option<u32>, DbError> {
+        self.retrieve_decodable(PUSHED, sink)
+    }
+
+    /// Store a per-message record under `prefix`, keyed by the message's
+    /// destination and nonce
+    fn store_by_nonce<V: Encode>(
+        &self,
+        prefix: &str,
+        destination: u32,
+        nonce: u32,
+        value: &V,
+    ) -> Result<(), DbError> {
+        let dest_and_nonce = utils::destination_and_nonce(destination, nonce);
+        self.store_keyed_encodable(prefix, &dest_and_nonce, value)
+    }
+
+    /// Retrieve a per-message record under `prefix`
+    fn retrieve_by_nonce<V: Decode>(
+        &self,
+        prefix: &str,
+        destination: u32,
+        nonce: u32,
+    ) -> Result<Option<V>, DbError> {
+        let dest_and_nonce = utils::destination_and_nonce(destination, nonce);
+        self.retrieve_keyed_decodable(prefix, &dest_and_nonce)
+    }
+
+    /// Retrieve every per-message record under `prefix` for messages to
+    /// `destination`, with their nonces, in nonce order
+    fn list_by_destination<V: Decode>(
+        &self,
+        prefix: &str,
+        destination: u32,
+    ) -> Result<Vec<(u32, V)>, DbError> {
+        let mut prefix = prefix.as_bytes().to_vec();
+        prefix.extend(destination.to_vec());
+        let (full_prefix, iter) = self.db.prefix_iterator(&prefix);
+        iter.map(|(key, value)| {
+            let nonce = u32::read_from(&mut &key[full_prefix.len()..])?;
+            Ok((nonce, V::read_from(&mut value.as_slice())?))
+        })
+        .collect()
+    }
+
+    /// Remove a per-message record under `prefix`
+    fn remove_by_nonce(&self, prefix: &str, destination: u32, nonce: u32) -> Result<(), DbError> {
+        let dest_and_nonce = utils::destination_and_nonce(destination, nonce);
+        self.db.delete_keyed(prefix, &dest_and_nonce)
+    }
+
+    /// Store a message skipped by the processor, keyed by its destination
+    /// and nonce
+    ///
+    /// Keys --> Values:
+    /// - `destination_and_nonce` --> `skipped_message`
+    pub fn store_skipped_message(
+        &self,
+        destination: u32,
+        nonce: u32,
+        skipped: &SkippedMessage,
+    ) -> Result<(), DbError> {
+        debug!(
+            destination,
+            nonce,
+            rule = %skipped.rule,
+            deferred = skipped.deferred,
+            "storing skipped message in DB"
+        );
+        self.store_by_nonce(SKIPPED, destination, nonce, skipped)
+    }
+
+    /// Retrieve a skipped message by its destination and nonce
+    pub fn skipped_message(
+        &self,
+        destination: u32,
+        nonce: u32,
+    ) -> Result<Option<SkippedMessage>, DbError> {
+        self.retrieve_by_nonce(SKIPPED, destination, nonce)
+    }
+
+    /// Retrieve all skipped messages to `destination`, with their nonces,
+    /// in nonce order
+    pub fn skipped_messages(
+        &self,
+        destination: u32,
+    ) -> Result<Vec<(u32, SkippedMessage)>, DbError> {
+        self.list_by_destination(SKIPPED, destination)
+    }
+
+    /// Remove a skipped message, e.g. once it has been processed
+    pub fn remove_skipped_message(&self, destination: u32, nonce: u32) -> Result<(), DbError> {
+        self.remove_by_nonce(SKIPPED, destination, nonce)
+    }
+
+    /// Store a message in the dead-letter queue, keyed by its destination
+    /// and nonce
+    ///
+    /// Keys --> Values:
+    /// - `destination_and_nonce` --> `dead_letter`
+    pub fn store_dead_letter(
+        &self,
+        destination: u32,
+        nonce: u32,
+        dead_letter: &DeadLetter,
+    ) -> Result<(), DbError> {
+        debug!(
+            destination,
+            nonce,
+            attempts = dead_letter.attempts,
+            reason = %dead_letter.reason,
+            "storing dead letter in DB"
+        );
+        self.store_by_nonce(DEAD_LETTER, destination, nonce, dead_letter)
+    }
+
+    /// Retrieve a dead letter by its destination and nonce
+    pub fn dead_letter(&self, destination: u32, nonce: u32) -> Result<Option<DeadLetter>, DbError> {
+        self.retrieve_by_nonce(DEAD_LETTER, destination, nonce)
+    }
+
+    /// Retrieve all dead letters to `destination`, with their nonces, in
+    /// nonce order
+    pub fn dead_letters(&self, destination: u32) -> Result<Vec<(u32, DeadLetter)>, DbError> {
+        self.list_by_destination(DEAD_LETTER, destination)
+    }
+
+    /// Remove a dead letter, e.g. once a retry succeeds
+    pub fn remove_dead_letter(&self, destination: u32, nonce: u32) -> Result<(), DbError> {
+        self.remove_by_nonce(DEAD_LETTER, destination, nonce)
+    }
+
+    /// Record the gas spent processing the message with `nonce` to
+    /// `destination`, against the `account` at `address`
+    ///
+    /// Keys --> Values:
+    /// - `destination_address_timestamp_nonce` --> `gas`
+    pub fn store_gas_spent(
+        &self,
+        destination: u32,
+        nonce: u32,
+        account: GasAccount,
+        address: H256,
+        timestamp: u64,
+        gas: U256,
+    ) -> Result<(), DbError> {
+        debug!(
+            destination,
+            nonce,
+            account = %account,
+            address = ?address,
+            gas = %gas,
+            "storing gas spent in DB"
+        );
+        let mut key = destination.to_vec();
+        key.extend(address.as_bytes());
+        key.extend(timestamp.to_vec());
+        key.extend(nonce.to_vec());
+        self.store_encodable(account.prefix(), key, &gas)
+    }
+
+    /// Total gas spent on messages to `destination` for the `account` at
+    /// `address` since `since`, in seconds since the unix epoch
+    pub fn gas_spent_since(
+        &self,
+        destination: u32,
+        account: GasAccount,
+        address: H256,
+        since: u64,
+    ) -> Result<U256, DbError> {
+        let mut prefix = account.prefix().as_bytes().to_vec();
+        prefix.extend(destination.to_vec());
+        prefix.extend(address.as_bytes());
+        let (full_prefix, iter) = self.db.prefix_iterator(&prefix);
+
+        let mut spent = U256::zero();
+        for (key, value) in iter {
+            let timestamp = u64::read_from(&mut &key[full_prefix.len()..])?;
+            if timestamp >= since {
+                spent = spent.saturating_add(U256::read_from(&mut value.as_slice())?);
+            }
+        }
+        Ok(spent)
+    }
+
+    /// Remove the records of gas spent on messages to `destination` for
+    /// every address of the `account` before `before`, in seconds since the
+    /// unix epoch
+    pub fn prune_gas_spent_before(
+        &self,
+        destination: u32,
+        account: GasAccount,
+        before: u64,
+    ) -> Result<(), DbError> {
+        let destination = destination.to_vec();
+        let mut prefix = account.prefix().as_bytes().to_vec();
+        prefix.extend(&destination);
+        let (full_prefix, iter) = self.db.prefix_iterator(&prefix);
+
+        // Keys after the account prefix are
+        // `<destination><address><timestamp><nonce>`
+        let key_start = full_prefix.len() - destination.len();
+        let mut expired = vec![];
+        for (key, _) in iter {
+            let timestamp = u64::read_from(&mut &key[full_prefix.len() + 32..])?;
+            if timestamp < before {
+                expired.push(key[key_start..].to_vec());
+            }
+        }
+        for key in expired {
+            self.db.delete(account.prefix(), key)?;
+        }
+        Ok(())
+    }
+
     /// Retrieve the latest committed
     pub fn retrieve_latest_root(&self) -> Result<Option<H256>, DbError> {
         self.retrieve_decodable("", LATEST_ROOT)
@@ -220,6 +739,138 @@ impl HomeDB {
         self.retrieve_keyed_decodable(UPDATE_META, &new_root)
     }
 
+    /// Store the timestamp of the block including an update (by update's
+    /// new root)
+    ///
+    /// Keys --> Values:
+    /// - `update_new_root` --> `timestamp`
+    pub fn store_update_timestamp(&self, new_root: H256, timestamp: u64) -> Result<(), DbError> {
+        self.store_keyed_encodable(UPDATE_TIMESTAMP, &new_root, &timestamp)
+    }
+
+    /// Retrieve the timestamp of the block including an update (by update's
+    /// new root), in seconds since the unix epoch. None for updates indexed
+    /// before timestamps were stored
+    pub fn update_timestamp(&self, new_root: H256) -> Result<Option<u64>, DbError> {
+        self.retrieve_keyed_decodable(UPDATE_TIMESTAMP, &new_root)
+    }
+
+    /// Store the block number at which a leaf was dispatched
+    ///
+    /// Keys --> Values:
+    /// - `leaf_index` --> `block_number`
+    pub fn store_leaf_block_number(
+        &self,
+        leaf_index: u32,
+        block_number: u64,
+    ) -> Result<(), DbError> {
+        self.store_keyed_encodable(LEAF_BLOCK, &leaf_index, &block_number)
+    }
+
+    /// Retrieve the block number at which a leaf was dispatched
+    pub fn leaf_block_number(&self, leaf_index: u32) -> Result<Option<u64>, DbError> {
+        self.retrieve_keyed_decodable(LEAF_BLOCK, &leaf_index)
+    }
+
+    /// Retrieve the number of rollbacks performed on this DB
+    pub fn retrieve_rollback_count(&self) -> Result<u64, DbError> {
+        Ok(self.retrieve_decodable("", ROLLBACKS)?.unwrap_or_default())
+    }
+
+    /// Remove all leaves, messages, proofs, updates and update metadata
+    /// indexed from blocks above `block_number`, then bump the rollback
+    /// count so that consumers (e.g. the processor's prover) know to reload.
+    ///
+    /// Leaves and updates without a recorded block number are never removed.
+    pub fn rollback_to_block(&self, block_number: u64) -> Result<(), DbError> {
+        warn!(block_number, "rolling back HomeDB");
+        self.atomically(|batch| batch.rollback_to_block_inner(block_number))
+    }
+
+    fn rollback_to_block_inner(&self, block_number: u64) -> Result<(), DbError> {
+        let mut latest_leaf_index = self.retrieve_latest_leaf_index()?;
+        while let Some(leaf_index) = latest_leaf_index {
+            match self.leaf_block_number(leaf_index)? {
+                Some(block) if block > block_number => self.remove_leaf(leaf_index)?,
+                _ => break,
+            }
+            latest_leaf_index = leaf_index.checked_sub(1);
+        }
+        match latest_leaf_index {
+            Some(leaf_index) => self.store_encodable("", LATEST_LEAF_INDEX, &leaf_index)?,
+            None => self.db.delete("", LATEST_LEAF_INDEX)?,
+        }
+
+        while let Some(root) = self.retrieve_latest_root()? {
+            let metadata = self.retrieve_update_metadata(root)?;
+            match (metadata, self.update_by_new_root(root)?) {
+                (Some(metadata), Some(update)) if metadata.block_number > block_number => {
+                    self.remove_update(&update)?
+                }
+                _ => break,
+            }
+        }
+
+        let rollbacks = self.retrieve_rollback_count()? + 1;
+        self.store_encodable("", ROLLBACKS, &rollbacks)?;
+        self.publish(HomeEvent::RolledBack);
+        Ok(())
+    }
+
+    /// Remove a leaf and all data keyed by it
+    fn remove_leaf(&self, leaf_index: u32) -> Result<(), DbError> {
+        if let Some(leaf) = self.leaf_by_leaf_index(leaf_index)? {
+            if let Some(message) = self.message_by_leaf(leaf)? {
+                let dest_and_nonce = message.view()?.destination_and_nonce();
+                self.db.delete_keyed(LEAF, &dest_and_nonce)?;
+                self.db.delete_keyed(SKIPPED, &dest_and_nonce)?;
+                self.db.delete_keyed(DEAD_LETTER, &dest_and_nonce)?;
+            }
+            self.db.delete_keyed(MESSAGE, &leaf)?;
+        }
+
+        debug!(leaf_index, "removing leaf from DB");
+        self.db.delete_keyed(LEAF_IDX, &leaf_index)?;
+        self.db.delete_keyed(PROOF, &leaf_index)?;
+        self.remove_proofs_under_roots(leaf_index)?;
+        self.db.delete_keyed(LEAF_BLOCK, &leaf_index)
+    }
+
+    /// Remove every proof of the leaf at `leaf_index` keyed by root
+    fn remove_proofs_under_roots(&self, leaf_index: u32) -> Result<(), DbError> {
+        let mut prefix = ROOT_PROOF.as_bytes().to_vec();
+        prefix.extend(leaf_index.to_vec());
+        let (full_prefix, iter) = self.db.prefix_iterator(&prefix);
+        let roots: Vec<_> = iter
+            .map(|(key, _)| key[full_prefix.len()..].to_vec())
+            .collect();
+        for root in roots {
+            self.db.delete(&prefix, root)?;
+        }
+        Ok(())
+    }
+
+    /// Remove the latest update, and move the latest root back to its
+    /// previous root
+    fn remove_update(&self, update: &SignedUpdate) -> Result<(), DbError> {
+        let previous_root = update.update.previous_root;
+        let new_root = update.update.new_root;
+        debug!(previous_root = ?previous_root, new_root = ?new_root, "removing update from DB");
+
+        self.db.delete_keyed(UPDATE, &previous_root)?;
+        self.db.delete_keyed(PREV_ROOT, &new_root)?;
+        self.db.delete_keyed(UPDATE_META, &new_root)?;
+        self.db.delete_keyed(UPDATE_TIMESTAMP, &new_root)?;
+
+        // If no update produced the previous root, the removed update was
+        // the first one, and we have no latest root
+        if self.update_by_new_root(previous_root)?.is_some() {
+            self.store_latest_root(previous_root)
+        } else {
+            self.db.delete("", LATEST_ROOT)
+        }
+    }
+
     /// Store a signed update building off latest root
     ///
     /// Keys --> Values:
@@ -233,28 +884,35 @@ impl HomeDB {
             "storing update in DB"
         );
 
-        // If there is no latest root, or if this update is on the latest root
-        // update latest root
-        match self.retrieve_latest_root()? {
-            Some(root) => {
-                if root == update.update.previous_root {
-                    self.store_latest_root(update.update.new_root)?;
-                } else {
-                    warn!(
-                        "Attempted to store update not building off latest root: {:?}",
-                        update
-                    )
+        self.atomically(|batch| {
+            // If there is no latest root, or if this update is on the latest
+            // root update latest root
+            match batch.retrieve_latest_root()? {
+                Some(root) => {
+                    if root == update.update.previous_root {
+                        batch.store_latest_root(update.update.new_root)?;
+                    } else {
+                        warn!(
+                            "Attempted to store update not building off latest root: {:?}",
+                            update
+                        )
+                    }
                 }
+                None => batch.store_latest_root(update.update.new_root)?,
             }
-            None => self.store_latest_root(update.update.new_root)?,
-        }
 
-        self.store_keyed_encodable(UPDATE, &update.update.previous_root, update)?;
-        self.store_keyed_encodable(
-            PREV_ROOT,
-            &update.update.new_root,
-            &update.update.previous_root,
-        )
+            batch.store_keyed_encodable(UPDATE, &update.update.previous_root, update)?;
+            batch.store_keyed_encodable(
+                PREV_ROOT,
+                &update.update.new_root,
+                &update.update.previous_root,
+            )?;
+            batch.publish(HomeEvent::Update {
+                previous_root: update.update.previous_root,
+                new_root: update.update.new_root,
+            });
+            Ok(())
+        })
     }
 
     /// Retrieve an update by its previous root
@@ -275,18 +933,30 @@ impl HomeDB {
         }
     }
 
-    /// Iterate over all leaves
-    pub fn leaf_iterator(&self) -> PrefixIterator<H256> {
-        PrefixIterator::new(self.0.db().prefix_iterator(LEAF_IDX), LEAF_IDX.as_ref())
+    /// Load the persistent merkle tree stored alongside this home's data
+    pub fn merkle(&self) -> Result<PersistentMerkle, PersistentMerkleError> {
+        PersistentMerkle::load(self.db.clone())
     }
 
-    /// Store a proof by its leaf index
+    /// Iterate over all leaves, in leaf index order
+    pub fn leaf_iterator(&self) -> impl Iterator<Item = H256> {
+        let (prefix, iter) = self.db.prefix_iterator(LEAF_IDX);
+        PrefixIterator::new(iter, &prefix)
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// Store a proof by its leaf index, and by its leaf index and root
     ///
     /// Keys --> Values:
     /// - `leaf_index` --> `proof`
+    /// - `leaf_index`, `root` --> `proof`
     pub fn store_proof(&self, leaf_index: u32, proof: &Proof) -> Result<(), DbError> {
         debug!(leaf_index, "storing proof in DB");
-        self.store_keyed_encodable(PROOF, &leaf_index, proof)
+        self.store_keyed_encodable(PROOF, &leaf_index, proof)?;
+        self.store_proof_under_root(leaf_index, proof)?;
+        self.publish(HomeEvent::Proof { leaf_index });
+        Ok(())
     }
 
     /// Retrieve a proof by its leaf index
@@ -294,20 +964,117 @@ impl HomeDB {
         self.retrieve_keyed_decodable(PROOF, &leaf_index)
     }
 
-    // TODO(james): this is a quick-fix for the prover_sync and I don't like it
-    /// poll db ever 100 milliseconds waitinf for a leaf.
-    pub fn wait_for_leaf(
+    fn root_proof_key(leaf_index: u32, root: H256) -> Vec<u8> {
+        let mut key = leaf_index.to_vec();
+        key.extend(root.as_bytes());
+        key
+    }
+
+    /// Store a proof by its leaf index and the root it proves the leaf under
+    ///
+    /// Keys --> Values:
+    /// - `leaf_index`, `root` --> `proof`
+    pub fn store_proof_under_root(&self, leaf_index: u32, proof: &Proof) -> Result<(), DbError> {
+        self.store_encodable(
+            ROOT_PROOF,
+            Self::root_proof_key(leaf_index, proof.root()),
+            proof,
+        )
+    }
+
+    /// Retrieve a stored proof of the leaf at `leaf_index` under `root`
+    pub fn proof_under_root(&self, leaf_index: u32, root: H256) -> Result<Option<Proof>, DbError> {
+        self.retrieve_decodable(ROOT_PROOF, Self::root_proof_key(leaf_index, root))
+    }
+
+    /// Retrieve a proof of the leaf at `leaf_index` under `root`. If none is
+    /// stored, generate one from the home's merkle tree and store it.
+    ///
+    /// Returns `None` if the leaf was not yet in the tree at root `root`.
+    /// Errors with `UnknownRoot` if the merkle tree has never had root
+    /// `root`.
+    pub fn prove_under_root(
         &self,
         leaf_index: u32,
-    ) -> impl Future<Output = Result<Option<H256>, DbError>> {
-        let slf = self.clone();
-        async move {
-            loop {
-                if let Some(leaf) = slf.leaf_by_leaf_index(leaf_index)? {
-                    return Ok(Some(leaf));
-                }
-                sleep(Duration::from_millis(100)).await
+        root: H256,
+    ) -> Result<Option<Proof>, PersistentMerkleError> {
+        if let Some(proof) = self.proof_under_root(leaf_index, root)? {
+            return Ok(Some(proof));
+        }
+        match self.merkle()?.prove_under_root(leaf_index as usize, root) {
+            Ok(proof) => {
+                self.store_proof_under_root(leaf_index, &proof)?;
+                Ok(Some(proof))
             }
+            Err(PersistentMerkleError::IndexOutOfRange { .. }) => Ok(None),
+            Err(e) => Err(e),
         }
     }
+
+    /// Wait for the leaf at `leaf_index` to be stored
+    pub async fn wait_for_leaf(&self, leaf_index: u32) -> Result<H256, DbError> {
+        self.wait_until(
+            || self.leaf_by_leaf_index(leaf_index),
+            |event| matches!(event, HomeEvent::Leaf { leaf_index: i, .. } if *i == leaf_index),
+        )
+        .await
+    }
+
+    /// Wait for the message with leaf hash `leaf` to be stored
+    pub async fn wait_for_message_by_leaf(
+        &self,
+        leaf: H256,
+    ) -> Result<RawCommittedMessage, DbError> {
+        self.wait_until(
+            || self.message_by_leaf(leaf),
+            |event| matches!(event, HomeEvent::Leaf { leaf: l, .. } if *l == leaf),
+        )
+        .await
+    }
+
+    /// Wait for the message to `destination` at `nonce` to be stored
+    pub async fn wait_for_message_by_nonce(
+        &self,
+        destination: u32,
+        nonce: u32,
+    ) -> Result<RawCommittedMessage, DbError> {
+        self.wait_until(
+            || self.message_by_nonce(destination, nonce),
+            |event| matches!(event, HomeEvent::Leaf { .. }),
+        )
+        .await
+    }
+
+    /// Wait for an update building off `previous_root` to be stored
+    pub async fn wait_for_update_by_previous_root(
+        &self,
+        previous_root: H256,
+    ) -> Result<SignedUpdate, DbError> {
+        self.wait_until(
+            || self.update_by_previous_root(previous_root),
+            |event| matches!(event, HomeEvent::Update { previous_root: r, .. } if *r == previous_root),
+        )
+        .await
+    }
+
+    /// Wait for an update producing `new_root` to be stored
+    pub async fn wait_for_update_by_new_root(
+        &self,
+        new_root: H256,
+    ) -> Result<SignedUpdate, DbError> {
+        self.wait_until(
+            || self.update_by_new_root(new_root),
+            |event| matches!(event, HomeEvent::Update { new_root: r, .. } if *r == new_root),
+        )
+        .await
+    }
+
+    /// Wait for a proof of the leaf at `leaf_index` to be stored
+    pub async fn wait_for_proof(&self, leaf_index: u32) -> Result<Proof, DbError> {
+        self.wait_until(
+            || self.proof_by_leaf_index(leaf_index),
+            |event| matches!(event, HomeEvent::Proof { leaf_index: i } if *i == leaf_index),
+        )
+        .await
+    }
 }
@@ -0,0 +1,228 @@
+use crate::accumulator::persistent::{PersistentMerkle, PersistentMerkleError};
+use crate::db::{DbError, TypedDB, WriteBatch, DB};
+use crate::{Decode, Encode, SignedUpdate};
+use ethers::core::types::H256;
+use std::collections::{BTreeMap, HashMap};
+use tracing::info;
+
+/// Key of the schema version. Not namespaced by any type prefix
+static SCHEMA_VERSION_KEY: &str = "optics_schema_version";
+
+/// A numbered change to the DB format
+pub struct Migration {
+    /// The schema version after the migration has run
+    pub version: u32,
+    /// What the migration changes
+    pub description: &'static str,
+    /// Compute the writes that perform the migration
+    pub run: fn(&DB) -> Result<WriteBatch, DbError>,
+}
+
+impl std::fmt::Debug for Migration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Migration")
+            .field("version", &self.version)
+            .field("description", &self.description)
+            .finish()
+    }
+}
+
+/// Every migration, in order. Migration `i` moves the schema from version
+/// `i` to version `i + 1`
+pub static MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Move leaves keyed by leaf index from `leaf_` to `leaf_index_`",
+        run: move_leaf_indices,
+    },
+    Migration {
+        version: 2,
+        description: "Build each home's persistent merkle tree from its stored leaves",
+        run: build_persistent_trees,
+    },
+    Migration {
+        version: 3,
+        description: "Key the signing history by the updater that signed each update",
+        run: key_signing_history_by_updater,
+    },
+];
+
+/// The schema version written by this version of the code
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// The outcome of a migration
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationReport {
+    /// The schema version after the migration
+    pub version: u32,
+    /// What the migration changes
+    pub description: &'static str,
+    /// The number of puts and deletes the migration performs
+    pub writes: usize,
+}
+
+/// Before version 1, leaves were keyed by both leaf index (4 bytes) and
+/// destination and nonce (8 bytes) under the same `leaf_` prefix. Move the
+/// leaf index keys to `leaf_index_`.
+fn move_leaf_indices(db: &DB) -> Result<WriteBatch, DbError> {
+    const OLD: &[u8] = b"_leaf_";
+    const NEW: &[u8] = b"_leaf_index_";
+
+    let mut batch = WriteBatch::default();
+    for (key, value) in db.prefix_iterator("") {
+        // `<home_name>_leaf_<u32 leaf index>` --> `<32 byte leaf>`
+        if key.len() < OLD.len() + 4 || value.len() != 32 {
+            continue;
+        }
+        let (head, index) = key.split_at(key.len() - 4);
+        if let Some(home_prefix) = head.strip_suffix(OLD) {
+            let mut new_key = home_prefix.to_vec();
+            new_key.extend(NEW);
+            new_key.extend(index);
+            batch.put(new_key, &value);
+            batch.delete(&key);
+        }
+    }
+    Ok(batch)
+}
+
+/// Before version 2, the prover rebuilt its tree from the leaves on every
+/// start. Build the persistent tree it now loads from each home's leaves,
+/// up to the first missing leaf. Homes that already have a tree are left
+/// alone.
+fn build_persistent_trees(db: &DB) -> Result<WriteBatch, DbError> {
+    const LEAF_IDX: &[u8] = b"_leaf_index_";
+    const TREE_COUNT: &[u8] = b"_tree_count";
+
+    // `<home_name>_leaf_index_<u32 leaf index>` --> `<32 byte leaf>`
+    let mut homes: HashMap<Vec<u8>, BTreeMap<u32, H256>> = HashMap::new();
+    let mut built = vec![];
+    for (key, value) in db.prefix_iterator("") {
+        if let Some(home) = key.strip_suffix(TREE_COUNT) {
+            built.push(home.to_vec());
+        }
+        if key.len() < LEAF_IDX.len() + 4 || value.len() != 32 {
+            continue;
+        }
+        let (head, index) = key.split_at(key.len() - 4);
+        if let Some(home) = head.strip_suffix(LEAF_IDX) {
+            homes
+                .entry(home.to_vec())
+                .or_default()
+                .insert(u32::read_from(&mut &index[..])?, H256::from_slice(&value));
+        }
+    }
+
+    let mut batch = WriteBatch::default();
+    for (home, leaves) in homes {
+        if built.contains(&home) {
+            continue;
+        }
+        let leaves = leaves
+            .into_iter()
+            .enumerate()
+            .take_while(|(i, (index, _))| *i as u32 == *index)
+            .map(|(_, (_, leaf))| leaf);
+
+        // Build the tree in memory under the home's prefix, then copy it
+        // u32 leaf indices cannot fill the tree, so only the DB can fail
+        let tree_db = DB::in_memory();
+        PersistentMerkle::load(TypedDB::new(tree_db.clone(), home))
+            .and_then(|mut tree| tree.ingest_all(leaves))
+            .map_err(|e| match e {
+                PersistentMerkleError::DbError(e) => e,
+                e => panic!("building persistent tree: {}", e),
+            })?;
+        for (key, value) in tree_db.prefix_iterator("") {
+            batch.put(key, value);
+        }
+    }
+    Ok(batch)
+}
+
+/// Before version 3, the signing history was keyed only by home domain and
+/// previous root, so an updater with a rotated key found the old key's
+/// signatures. Key each record by the updater recovered from its signature.
+fn key_signing_history_by_updater(db: &DB) -> Result<WriteBatch, DbError> {
+    const SIGNED: &[u8] = b"signing_history_signed_update_";
+    // `<u32 home domain>_<32 byte previous root>`
+    const OLD_KEY_LEN: usize = 4 + 1 + 32;
+
+    let mut batch = WriteBatch::default();
+    for (key, value) in db.prefix_iterator(SIGNED) {
+        let old_key = &key[SIGNED.len()..];
+        if old_key.len() != OLD_KEY_LEN {
+            continue;
+        }
+        let updater = SignedUpdate::read_from(&mut &value[..])?.recover()?;
+
+        let mut new_key = SIGNED.to_vec();
+        new_key.extend(updater.as_bytes());
+        new_key.extend(b"_");
+        new_key.extend(old_key);
+        batch.put(new_key, &value);
+        batch.delete(&key);
+    }
+    Ok(batch)
+}
+
+impl DB {
+    /// Retrieve the schema version. A DB without a stored version is at
+    /// version 0, unless it is empty
+    pub fn schema_version(&self) -> Result<u32, DbError> {
+        match self.retrieve_decodable("", SCHEMA_VERSION_KEY)? {
+            Some(version) => Ok(version),
+            None if self.prefix_iterator("").next().is_none() => Ok(SCHEMA_VERSION),
+            None => Ok(0),
+        }
+    }
+
+    /// Run every migration the DB has not yet had. Each migration is
+    /// committed atomically along with its new schema version. If `dry_run`
+    /// is true, nothing is written.
+    ///
+    /// Errors if the DB has a newer schema than this code supports.
+    pub fn migrate(&self, dry_run: bool) -> Result<Vec<MigrationReport>, DbError> {
+        let found = self.schema_version()?;
+        if found > SCHEMA_VERSION {
+            return Err(DbError::UnsupportedSchema {
+                found,
+                supported: SCHEMA_VERSION,
+            });
+        }
+
+        let mut reports = vec![];
+        for migration in MIGRATIONS.iter().skip(found as usize) {
+            let mut batch = (migration.run)(self)?;
+            let report = MigrationReport {
+                version: migration.version,
+                description: migration.description,
+                writes: batch.ops().len(),
+            };
+            info!(
+                version = report.version,
+                writes = report.writes,
+                dry_run,
+                "Migrating DB: {}",
+                report.description
+            );
+
+            if !dry_run {
+                batch.put(SCHEMA_VERSION_KEY, migration.version.to_vec());
+                self.write_batch(batch)?;
+            }
+            reports.push(report);
+        }
+
+        // Record the version of new DBs, so that data written later is not
+        // mistaken for an old format
+        if !dry_run
+            && self
+                .retrieve_decodable::<u32>("", SCHEMA_VERSION_KEY)?
+                .is_none()
+        {
+            self.store_encodable("", SCHEMA_VERSION_KEY, &SCHEMA_VERSION)?;
+        }
+        Ok(reports)
+    }
+}
@@ -1,11 +1,20 @@
 use color_eyre::eyre::WrapErr;
-use rocksdb::{DBIterator, Options, DB as Rocks};
-use std::{path::Path, sync::Arc};
+use rocksdb::{Options, DB as Rocks};
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::broadcast;
 use tracing::info;
 
 /// Shared functionality surrounding use of rocksdb
 pub mod iterator;
 
+/// Storage backends
+mod store;
+pub use store::*;
+
 /// Type-specific db operations
 mod typed_db;
 pub use typed_db::*;
@@ -14,15 +23,35 @@ pub use typed_db::*;
 mod home_db;
 pub use home_db::*;
 
+/// DB operations tied to specific replica
+mod replica_db;
+pub use replica_db::*;
+
+/// Updater signing history for slashing protection
+mod signing_db;
+pub use signing_db::*;
+
+/// Schema versioning and migrations
+mod migrations;
+pub use migrations::*;
+
 use crate::{Decode, Encode, OpticsError};
 
+/// Capacity of each home's event channel
+const HOME_EVENT_CAPACITY: usize = 1024;
+
 #[derive(Debug, Clone)]
 /// A KV Store
-pub struct DB(Arc<Rocks>);
+pub struct DB {
+    store: Arc<dyn KeyValueStore>,
+    // Shared so that every `HomeDB` handle for a home publishes to and
+    // subscribes from the same channel
+    home_events: Arc<Mutex<HashMap<String, broadcast::Sender<HomeEvent>>>>,
+}
 
 impl From<Rocks> for DB {
     fn from(rocks: Rocks) -> Self {
-        Self(Arc::new(rocks))
+        Self::new(rocks)
     }
 }
 
@@ -35,14 +64,46 @@ pub enum DbError {
     /// Optics Error
     #[error("{0}")]
     OpticsError(#[from] OpticsError),
+    /// DB was written by a newer version of the software
+    #[error("DB schema version {found} is newer than the supported version {supported}")]
+    UnsupportedSchema {
+        /// The schema version of the DB
+        found: u32,
+        /// The newest schema version this software supports
+        supported: u32,
+    },
 }
 
 type Result<T> = std::result::Result<T, DbError>;
 
 impl DB {
-    /// Opens db at `db_path` and creates if missing
+    /// Instantiate a `DB` backed by `store`
+    pub fn new(store: impl KeyValueStore + 'static) -> Self {
+        Self {
+            store: Arc::new(store),
+            home_events: Default::default(),
+        }
+    }
+
+    /// Instantiate an empty in-memory `DB`
+    pub fn in_memory() -> Self {
+        Self::new(MemoryStore::default())
+    }
+
+    /// Opens db at `db_path`, creates if missing, and migrates it to the
+    /// current schema version
     #[tracing::instrument(err)]
     pub fn from_path(db_path: &str) -> color_eyre::Result<DB> {
+        let db = Self::open(db_path)?;
+        db.migrate(false)
+            .wrap_err(format!("Failed to migrate db at {}", db_path))?;
+        Ok(db)
+    }
+
+    /// Opens db at `db_path` and creates if missing, without running
+    /// migrations
+    #[tracing::instrument(err)]
+    pub fn open(db_path: &str) -> color_eyre::Result<DB> {
         // Canonicalize ensures existence, so we have to do that, then extend
         let mut path = Path::new(".").canonicalize()?;
         path.extend(&[db_path]);
@@ -66,14 +127,35 @@ impl DB {
             .map(Into::into)
     }
 
+    /// Get the event channel for the home named `home_name`, creating it
+    /// if necessary
+    pub(crate) fn home_events(&self, home_name: &str) -> broadcast::Sender<HomeEvent> {
+        self.home_events
+            .lock()
+            .expect("poisoned")
+            .entry(home_name.to_owned())
+            .or_insert_with(|| broadcast::channel(HOME_EVENT_CAPACITY).0)
+            .clone()
+    }
+
     /// Store a value in the DB
     fn _store(&self, key: impl AsRef<[u8]>, value: impl AsRef<[u8]>) -> Result<()> {
-        Ok(self.0.put(key, value)?)
+        self.store.put(key.as_ref(), value.as_ref())
+    }
+
+    /// Store a value in the DB, syncing the write to disk before returning
+    fn _store_synced(&self, key: impl AsRef<[u8]>, value: impl AsRef<[u8]>) -> Result<()> {
+        self.store.put_synced(key.as_ref(), value.as_ref())
     }
 
     /// Retrieve a value from the DB
     fn _retrieve(&self, key: impl AsRef<[u8]>) -> Result<Option<Vec<u8>>> {
-        Ok(self.0.get(key)?)
+        self.store.get(key.as_ref())
+    }
+
+    /// Delete a value from the DB
+    fn _delete(&self, key: impl AsRef<[u8]>) -> Result<()> {
+        self.store.delete(key.as_ref())
     }
 
     /// Prefix a key and store in the DB
@@ -89,6 +171,19 @@ impl DB {
         self._store(buf, value)
     }
 
+    /// Prefix a key and store in the DB, syncing the write to disk
+    fn prefix_store_synced(
+        &self,
+        prefix: impl AsRef<[u8]>,
+        key: impl AsRef<[u8]>,
+        value: impl AsRef<[u8]>,
+    ) -> Result<()> {
+        let mut buf = vec![];
+        buf.extend(prefix.as_ref());
+        buf.extend(key.as_ref());
+        self._store_synced(buf, value)
+    }
+
     /// Prefix the key and retrieve
     fn prefix_retrieve(
         &self,
@@ -101,6 +196,14 @@ impl DB {
         self._retrieve(buf)
     }
 
+    /// Prefix the key and delete
+    pub fn prefix_delete(&self, prefix: impl AsRef<[u8]>, key: impl AsRef<[u8]>) -> Result<()> {
+        let mut buf = vec![];
+        buf.extend(prefix.as_ref());
+        buf.extend(key.as_ref());
+        self._delete(buf)
+    }
+
     /// Store any encodeable
     pub fn store_encodable<V: Encode>(
         &self,
@@ -111,6 +214,16 @@ impl DB {
         self.prefix_store(prefix, key, value.to_vec())
     }
 
+    /// Store any encodeable, syncing the write to disk before returning
+    pub fn store_encodable_synced<V: Encode>(
+        &self,
+        prefix: impl AsRef<[u8]>,
+        key: impl AsRef<[u8]>,
+        value: &V,
+    ) -> Result<()> {
+        self.prefix_store_synced(prefix, key, value.to_vec())
+    }
+
     /// Retrieve and attempt to decode
     pub fn retrieve_decodable<V: Decode>(
         &self,
@@ -142,8 +255,18 @@ impl DB {
         self.retrieve_decodable(prefix, key.to_vec())
     }
 
+    /// Delete any value keyed by an encodable
+    pub fn delete_keyed<K: Encode>(&self, prefix: impl AsRef<[u8]>, key: &K) -> Result<()> {
+        self.prefix_delete(prefix, key.to_vec())
+    }
+
     /// Get prefix db iterator for `prefix`
-    pub fn prefix_iterator(&self, prefix: impl AsRef<[u8]>) -> DBIterator {
-        self.0.prefix_iterator(prefix)
+    pub fn prefix_iterator(&self, prefix: impl AsRef<[u8]>) -> KvIterator {
+        self.store.prefix_iter(prefix.as_ref())
+    }
+
+    /// Apply every write in `batch` atomically and durably
+    pub fn write_batch(&self, batch: WriteBatch) -> Result<()> {
+        self.store.write_batch(batch)
     }
 }
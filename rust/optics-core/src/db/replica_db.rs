@@ -0,0 +1,320 @@
+use crate::db::{DbError, TypedDB, DB};
+use crate::{Decode, DoubleUpdate, Encode, OpticsError, SignedUpdate, UpdateMeta};
+use color_eyre::Result;
+use ethers::core::types::H256;
+use std::ops::Deref;
+use tracing::{debug, warn};
+
+static PREV_ROOT: &str = "update_prev_root_";
+static UPDATE: &str = "update_";
+static UPDATE_META: &str = "update_metadata_";
+static DOUBLE_UPDATE: &str = "double_update_";
+static DOUBLE_UPDATE_BLOCK: &str = "double_block_";
+static PROCESSED: &str = "processed_";
+static RELAYED: &str = "relayed_update_";
+
+/// The outcome of processing a message on a replica
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProcessedMessage {
+    /// True if the message handler succeeded
+    pub success: bool,
+    /// The block the message was processed in
+    pub block_number: u64,
+}
+
+impl Encode for ProcessedMessage {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        writer.write_all(&[self.success as u8])?;
+        Ok(1 + self.block_number.write_to(writer)?)
+    }
+}
+
+impl Decode for ProcessedMessage {
+    fn read_from<R>(reader: &mut R) -> Result<Self, OpticsError>
+    where
+        R: std::io::Read,
+        Self: Sized,
+    {
+        let mut success = [0u8; 1];
+        reader.read_exact(&mut success)?;
+        Ok(Self {
+            success: success[0] != 0,
+            block_number: u64::read_from(reader)?,
+        })
+    }
+}
+
+/// Timing of an update relayed to the replica, tracked by the relayer until
+/// the update's new root is acceptable
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RelayedUpdate {
+    /// When the relayer first saw the update, in seconds since the unix
+    /// epoch
+    pub seen_at: u64,
+    /// When the relayer submitted the update, in seconds since the unix
+    /// epoch. None if another relayer submitted it
+    pub submitted_at: Option<u64>,
+    /// The block the relayer's submission was included in, if known
+    pub submission_block: Option<u64>,
+}
+
+impl Encode for RelayedUpdate {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        let mut written = self.seen_at.write_to(writer)?;
+        written += self.submitted_at.write_to(writer)?;
+        written += self.submission_block.write_to(writer)?;
+        Ok(written)
+    }
+}
+
+impl Decode for RelayedUpdate {
+    fn read_from<R>(reader: &mut R) -> Result<Self, OpticsError>
+    where
+        R: std::io::Read,
+        Self: Sized,
+    {
+        Ok(Self {
+            seen_at: u64::read_from(reader)?,
+            submitted_at: Option::read_from(reader)?,
+            submission_block: Option::read_from(reader)?,
+        })
+    }
+}
+
+/// DB handle for storing data tied to a specific replica.
+///
+/// Key structure: ```replica_<replica_name>_<additional_prefix(es)>_<key>```
+#[derive(Debug, Clone)]
+pub struct ReplicaDB(TypedDB);
+
+/// A `ReplicaDB` handle whose writes are staged and committed atomically by
+/// `commit`. Reads through the handle see its staged writes.
+#[derive(Debug)]
+pub struct ReplicaBatch(ReplicaDB);
+
+impl Deref for ReplicaBatch {
+    type Target = ReplicaDB;
+
+    fn deref(&self) -> &ReplicaDB {
+        &self.0
+    }
+}
+
+impl ReplicaBatch {
+    /// Write all staged changes
+    pub fn commit(self) -> Result<(), DbError> {
+        self.0 .0.commit()
+    }
+}
+
+impl ReplicaDB {
+    /// Instantiated new `ReplicaDB`
+    pub fn new(db: DB, replica_name: String) -> Self {
+        Self(TypedDB::new(db, format!("replica_{}", replica_name)))
+    }
+
+    /// Start a batch of writes to be committed atomically
+    pub fn batch(&self) -> ReplicaBatch {
+        ReplicaBatch(Self(self.0.batched()))
+    }
+
+    /// Store encodable value
+    pub fn store_encodable<V: Encode>(
+        &self,
+        prefix: impl AsRef<[u8]>,
+        key: impl AsRef<[u8]>,
+        value: &V,
+    ) -> Result<(), DbError> {
+        self.0.store_encodable(prefix, key, value)
+    }
+
+    /// Retrieve decodable value
+    pub fn retrieve_decodable<V: Decode>(
+        &self,
+        prefix: impl AsRef<[u8]>,
+        key: impl AsRef<[u8]>,
+    ) -> Result<Option<V>, DbError> {
+        self.0.retrieve_decodable(prefix, key)
+    }
+
+    /// Store encodable kv pair
+    pub fn store_keyed_encodable<K: Encode, V: Encode>(
+        &self,
+        prefix: impl AsRef<[u8]>,
+        key: &K,
+        value: &V,
+    ) -> Result<(), DbError> {
+        self.0.store_keyed_encodable(prefix, key, value)
+    }
+
+    /// Retrieve decodable value given encodable key
+    pub fn retrieve_keyed_decodable<K: Encode, V: Decode>(
+        &self,
+        prefix: impl AsRef<[u8]>,
+        key: &K,
+    ) -> Result<Option<V>, DbError> {
+        self.0.retrieve_keyed_decodable(prefix, key)
+    }
+
+    /// Decode every committed value under `prefix`, along with the root or
+    /// leaf hash it is keyed by
+    fn entries<V: Decode>(&self, prefix: &str) -> Result<Vec<(H256, V)>, DbError> {
+        let (full_prefix, iter) = self.0.prefix_iterator(prefix);
+        iter.map(|(key, value)| {
+            let hash = H256::read_from(&mut &key[full_prefix.len()..])?;
+            Ok((hash, V::read_from(&mut value.as_slice())?))
+        })
+        .collect()
+    }
+
+    /// Store a signed update accepted by the replica, along with its
+    /// metadata
+    pub fn store_update(&self, update: &SignedUpdate, meta: UpdateMeta) -> Result<(), DbError> {
+        debug!(
+            previous_root = ?update.update.previous_root,
+            new_root = ?update.update.new_root,
+            "storing replica update in DB"
+        );
+        self.0
+            .store_keyed_encodable(UPDATE, &update.update.previous_root, update)?;
+        self.0.store_keyed_encodable(
+            PREV_ROOT,
+            &update.update.new_root,
+            &update.update.previous_root,
+        )?;
+        self.0
+            .store_keyed_encodable(UPDATE_META, &update.update.new_root, &meta)
+    }
+
+    /// Retrieve an update accepted by the replica by its previous root
+    pub fn update_by_previous_root(
+        &self,
+        previous_root: H256,
+    ) -> Result<Option<SignedUpdate>, DbError> {
+        self.0.retrieve_keyed_decodable(UPDATE, &previous_root)
+    }
+
+    /// Retrieve an update accepted by the replica by its new root
+    pub fn update_by_new_root(&self, new_root: H256) -> Result<Option<SignedUpdate>, DbError> {
+        let prev_root: Option<H256> = self.0.retrieve_keyed_decodable(PREV_ROOT, &new_root)?;
+
+        match prev_root {
+            Some(prev_root) => self.update_by_previous_root(prev_root),
+            None => Ok(None),
+        }
+    }
+
+    /// Retrieve the metadata of the update producing `new_root`
+    pub fn update_metadata(&self, new_root: H256) -> Result<Option<UpdateMeta>, DbError> {
+        self.0.retrieve_keyed_decodable(UPDATE_META, &new_root)
+    }
+
+    /// Store a double update proven on the replica, along with the block
+    /// it was proven in
+    pub fn store_double_update(
+        &self,
+        double: &DoubleUpdate,
+        block_number: u64,
+    ) -> Result<(), DbError> {
+        let previous_root = double.0.update.previous_root;
+        self.0
+            .store_keyed_encodable(DOUBLE_UPDATE, &previous_root, double)?;
+        self.0
+            .store_keyed_encodable(DOUBLE_UPDATE_BLOCK, &previous_root, &block_number)
+    }
+
+    /// Retrieve a double update proven on the replica by its previous root
+    pub fn double_update_by_previous_root(
+        &self,
+        previous_root: H256,
+    ) -> Result<Option<DoubleUpdate>, DbError> {
+        self.0
+            .retrieve_keyed_decodable(DOUBLE_UPDATE, &previous_root)
+    }
+
+    /// Store the outcome of processing the message with hash `leaf`
+    pub fn store_processed(&self, leaf: H256, processed: ProcessedMessage) -> Result<(), DbError> {
+        self.0.store_keyed_encodable(PROCESSED, &leaf, &processed)
+    }
+
+    /// Retrieve the outcome of processing the message with hash `leaf`
+    pub fn processed(&self, leaf: H256) -> Result<Option<ProcessedMessage>, DbError> {
+        self.0.retrieve_keyed_decodable(PROCESSED, &leaf)
+    }
+
+    /// Store the timing of the relayed update producing `new_root`
+    ///
+    /// Keys --> Values:
+    /// - `new_root` --> `relayed_update`
+    pub fn store_relayed_update(
+        &self,
+        new_root: H256,
+        relayed: &RelayedUpdate,
+    ) -> Result<(), DbError> {
+        debug!(
+            new_root = ?new_root,
+            submitted_at = ?relayed.submitted_at,
+            "storing relayed update in DB"
+        );
+        self.0.store_keyed_encodable(RELAYED, &new_root, relayed)
+    }
+
+    /// Retrieve the timing of the relayed update producing `new_root`
+    pub fn relayed_update(&self, new_root: H256) -> Result<Option<RelayedUpdate>, DbError> {
+        self.0.retrieve_keyed_decodable(RELAYED, &new_root)
+    }
+
+    /// Retrieve the timing of every tracked relayed update, with its new root
+    pub fn relayed_updates(&self) -> Result<Vec<(H256, RelayedUpdate)>, DbError> {
+        self.entries(RELAYED)
+    }
+
+    /// Stop tracking the relayed update producing `new_root`, e.g. once the
+    /// root is acceptable
+    pub fn remove_relayed_update(&self, new_root: H256) -> Result<(), DbError> {
+        self.0.delete_keyed(RELAYED, &new_root)
+    }
+
+    /// Remove all updates, double updates and processed messages indexed
+    /// from blocks above `block_number`. Call on a batch to remove them
+    /// atomically.
+    ///
+    /// Relayed update timings are kept, as they are not indexed from the
+    /// chain.
+    pub fn rollback_to_block(&self, block_number: u64) -> Result<(), DbError> {
+        warn!(block_number, "rolling back ReplicaDB");
+
+        for (new_root, meta) in self.entries::<UpdateMeta>(UPDATE_META)? {
+            if meta.block_number <= block_number {
+                continue;
+            }
+            let prev_root: Option<H256> = self.0.retrieve_keyed_decodable(PREV_ROOT, &new_root)?;
+            if let Some(prev_root) = prev_root {
+                self.0.delete_keyed(UPDATE, &prev_root)?;
+            }
+            self.0.delete_keyed(PREV_ROOT, &new_root)?;
+            self.0.delete_keyed(UPDATE_META, &new_root)?;
+        }
+
+        for (previous_root, block) in self.entries::<u64>(DOUBLE_UPDATE_BLOCK)? {
+            if block > block_number {
+                self.0.delete_keyed(DOUBLE_UPDATE, &previous_root)?;
+                self.0.delete_keyed(DOUBLE_UPDATE_BLOCK, &previous_root)?;
+            }
+        }
+
+        for (leaf, processed) in self.entries::<ProcessedMessage>(PROCESSED)? {
+            if processed.block_number > block_number {
+                self.0.delete_keyed(PROCESSED, &leaf)?;
+            }
+        }
+
+        Ok(())
+    }
+}
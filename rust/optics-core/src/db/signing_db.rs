@@ -0,0 +1,229 @@
+use crate::db::{iterator::PrefixIterator, DbError, TypedDB, DB};
+use crate::{OpticsError, SignedUpdate, Update};
+use ethers::core::types::{Address, Signature, H256};
+use std::{collections::HashMap, str::FromStr};
+
+static SIGNING_HISTORY: &str = "signing_history";
+static SIGNED: &str = "signed_update_";
+
+/// The version of the signing history interchange format
+pub const SIGNING_HISTORY_VERSION: u32 = 1;
+
+/// Signing history interchange format, used to move an updater's history
+/// between hosts along with its key.
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "updater": "0x<20-byte updater address>",
+///   "signedUpdates": [
+///     {
+///       "homeDomain": 1000,
+///       "previousRoot": "0x<32-byte root>",
+///       "newRoot": "0x<32-byte root>",
+///       "signature": "0x<65-byte signature>"
+///     }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningHistory {
+    /// Format version. Must be `SIGNING_HISTORY_VERSION`
+    pub version: u32,
+    /// The updater that signed every update in the history
+    pub updater: Address,
+    /// All updates signed by the updater
+    pub signed_updates: Vec<SigningRecord>,
+}
+
+/// A single signed update in a `SigningHistory`
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningRecord {
+    /// The home domain
+    pub home_domain: u32,
+    /// The update's previous root
+    pub previous_root: H256,
+    /// The update's new root
+    pub new_root: H256,
+    /// The 0x-prefixed hex signature
+    pub signature: String,
+}
+
+impl From<&SignedUpdate> for SigningRecord {
+    fn from(signed: &SignedUpdate) -> Self {
+        Self {
+            home_domain: signed.update.home_domain,
+            previous_root: signed.update.previous_root,
+            new_root: signed.update.new_root,
+            signature: format!("0x{}", signed.signature),
+        }
+    }
+}
+
+impl SigningRecord {
+    /// Convert to a `SignedUpdate`, checking that it was signed by `updater`
+    pub fn to_signed_update(&self, updater: Address) -> Result<SignedUpdate, SigningError> {
+        let signed = SignedUpdate {
+            update: Update {
+                home_domain: self.home_domain,
+                previous_root: self.previous_root,
+                new_root: self.new_root,
+            },
+            signature: Signature::from_str(&self.signature).map_err(OpticsError::SignatureError)?,
+        };
+        signed.verify(updater)?;
+        Ok(signed)
+    }
+}
+
+/// Errors from the signing history
+#[derive(Debug, thiserror::Error)]
+pub enum SigningError {
+    /// A different update was already signed for the same previous root.
+    /// Signing both would be a slashable double update
+    #[error(
+        "Refusing to sign conflicting update. Existing: {existing:?}. Attempted: {attempted:?}"
+    )]
+    Conflict {
+        /// The update already in the signing history
+        existing: Box<SignedUpdate>,
+        /// The conflicting update
+        attempted: Box<SignedUpdate>,
+    },
+    /// Imported history has an unknown format version
+    #[error("Unsupported signing history version: {0}")]
+    UnsupportedVersion(u32),
+    /// Invalid signature in imported history
+    #[error(transparent)]
+    OpticsError(#[from] OpticsError),
+    /// DB Error
+    #[error("{0}")]
+    DbError(#[from] DbError),
+}
+
+/// Append-only record of every update signed by an updater, used to refuse
+/// signatures that would conflict with one already released. Records are
+/// kept per updater, so that a rotated key starts with its own history.
+///
+/// Key structure: ```signing_history_signed_update_<updater>_<home_domain>_<previous_root>```
+#[derive(Debug, Clone)]
+pub struct SigningDB(TypedDB);
+
+impl SigningDB {
+    /// Instantiate new `SigningDB`
+    pub fn new(db: DB) -> Self {
+        Self(TypedDB::new(db, SIGNING_HISTORY))
+    }
+
+    fn prefix(updater: Address) -> Vec<u8> {
+        let mut prefix = SIGNED.as_bytes().to_vec();
+        prefix.extend(updater.as_bytes());
+        prefix.extend(b"_");
+        prefix
+    }
+
+    fn key(home_domain: u32, previous_root: H256) -> Vec<u8> {
+        let mut key = home_domain.to_be_bytes().to_vec();
+        key.extend(b"_");
+        key.extend(previous_root.as_bytes());
+        key
+    }
+
+    /// Retrieve the update `updater` signed for `previous_root` on
+    /// `home_domain`, if any
+    pub fn signed_update(
+        &self,
+        updater: Address,
+        home_domain: u32,
+        previous_root: H256,
+    ) -> Result<Option<SignedUpdate>, DbError> {
+        self.0
+            .retrieve_decodable(Self::prefix(updater), Self::key(home_domain, previous_root))
+    }
+
+    /// Record a signed update in the history of the updater that signed it.
+    /// The write is synced to disk before returning, so the signature must
+    /// not be released until this succeeds.
+    ///
+    /// Recording an update that is already in the history is a no-op.
+    /// Recording an update that conflicts with one in the history errors.
+    pub fn record(&self, signed: &SignedUpdate) -> Result<(), SigningError> {
+        let update = &signed.update;
+        let updater = signed.recover()?;
+        match self.signed_update(updater, update.home_domain, update.previous_root)? {
+            Some(existing) if existing.update == signed.update => Ok(()),
+            Some(existing) => Err(SigningError::Conflict {
+                existing: Box::new(existing),
+                attempted: Box::new(signed.clone()),
+            }),
+            None => Ok(self.0.store_encodable_synced(
+                Self::prefix(updater),
+                Self::key(update.home_domain, update.previous_root),
+                signed,
+            )?),
+        }
+    }
+
+    /// All updates in `updater`'s signing history
+    pub fn signed_updates(&self, updater: Address) -> Vec<SignedUpdate> {
+        let (prefix, iter) = self.0.prefix_iterator(Self::prefix(updater));
+        PrefixIterator::new(iter, &prefix).collect()
+    }
+
+    /// Export `updater`'s signing history in the interchange format
+    pub fn export(&self, updater: Address) -> Result<SigningHistory, SigningError> {
+        let signed_updates = self
+            .signed_updates(updater)
+            .iter()
+            .map(|signed| {
+                signed.verify(updater)?;
+                Ok(signed.into())
+            })
+            .collect::<Result<_, SigningError>>()?;
+
+        Ok(SigningHistory {
+            version: SIGNING_HISTORY_VERSION,
+            updater,
+            signed_updates,
+        })
+    }
+
+    /// Import a signing history. The whole history is checked for invalid
+    /// signatures and conflicts before anything is written. Returns the
+    /// number of updates that were not already recorded.
+    pub fn import(&self, history: &SigningHistory) -> Result<usize, SigningError> {
+        if history.version != SIGNING_HISTORY_VERSION {
+            return Err(SigningError::UnsupportedVersion(history.version));
+        }
+
+        let mut new: HashMap<(u32, H256), SignedUpdate> = HashMap::new();
+        for record in history.signed_updates.iter() {
+            let signed = record.to_signed_update(history.updater)?;
+            let key = (record.home_domain, record.previous_root);
+
+            let existing = match new.get(&key) {
+                Some(existing) => Some(existing.clone()),
+                None => self.signed_update(history.updater, key.0, key.1)?,
+            };
+            match existing {
+                Some(existing) if existing.update == signed.update => {}
+                Some(existing) => {
+                    return Err(SigningError::Conflict {
+                        existing: Box::new(existing),
+                        attempted: Box::new(signed),
+                    })
+                }
+                None => {
+                    new.insert(key, signed);
+                }
+            }
+        }
+
+        for signed in new.values() {
+            self.record(signed)?;
+        }
+        Ok(new.len())
+    }
+}
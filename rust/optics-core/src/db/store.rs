@@ -0,0 +1,176 @@
+use crate::db::DbError;
+use rocksdb::{WriteOptions, DB as Rocks};
+use std::{collections::BTreeMap, fmt::Debug, sync::RwLock};
+
+/// An iterator over key-value pairs
+pub type KvIterator<'a> = Box<dyn Iterator<Item = (Vec<u8>, Vec<u8>)> + 'a>;
+
+/// A single write in a `WriteBatch`
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchOp {
+    /// Store `value` at `key`
+    Put {
+        /// The key
+        key: Vec<u8>,
+        /// The value
+        value: Vec<u8>,
+    },
+    /// Delete the value at `key`
+    Delete {
+        /// The key
+        key: Vec<u8>,
+    },
+}
+
+/// A set of writes to be applied atomically
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WriteBatch {
+    ops: Vec<BatchOp>,
+}
+
+impl WriteBatch {
+    /// Add a put to the batch
+    pub fn put(&mut self, key: impl AsRef<[u8]>, value: impl AsRef<[u8]>) {
+        self.ops.push(BatchOp::Put {
+            key: key.as_ref().to_vec(),
+            value: value.as_ref().to_vec(),
+        });
+    }
+
+    /// Add a delete to the batch
+    pub fn delete(&mut self, key: impl AsRef<[u8]>) {
+        self.ops.push(BatchOp::Delete {
+            key: key.as_ref().to_vec(),
+        });
+    }
+
+    /// The writes in the batch, in order
+    pub fn ops(&self) -> &[BatchOp] {
+        &self.ops
+    }
+
+    /// True if the batch contains no writes
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
+
+/// A byte-oriented key-value store backing a `DB`
+pub trait KeyValueStore: Debug + Send + Sync {
+    /// Retrieve the value at `key`
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, DbError>;
+
+    /// Store `value` at `key`
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<(), DbError>;
+
+    /// Store `value` at `key`, persisting the write before returning.
+    /// Stores without a durability distinction can use the default
+    fn put_synced(&self, key: &[u8], value: &[u8]) -> Result<(), DbError> {
+        self.put(key, value)
+    }
+
+    /// Delete the value at `key`
+    fn delete(&self, key: &[u8]) -> Result<(), DbError>;
+
+    /// Iterate over every pair whose key starts with `prefix`, in key order
+    fn prefix_iter<'a>(&'a self, prefix: &[u8]) -> KvIterator<'a>;
+
+    /// Apply every write in `batch`, or none of them, persisting the writes
+    /// before returning
+    fn write_batch(&self, batch: WriteBatch) -> Result<(), DbError>;
+}
+
+impl KeyValueStore for Rocks {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, DbError> {
+        Ok(Rocks::get(self, key)?)
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<(), DbError> {
+        Ok(Rocks::put(self, key, value)?)
+    }
+
+    fn put_synced(&self, key: &[u8], value: &[u8]) -> Result<(), DbError> {
+        let mut opts = WriteOptions::default();
+        opts.set_sync(true);
+        Ok(self.put_opt(key, value, &opts)?)
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<(), DbError> {
+        Ok(Rocks::delete(self, key)?)
+    }
+
+    fn prefix_iter<'a>(&'a self, prefix: &[u8]) -> KvIterator<'a> {
+        let prefix = prefix.to_vec();
+        Box::new(
+            self.prefix_iterator(&prefix)
+                .take_while(move |(k, _)| k.starts_with(&prefix))
+                .map(|(k, v)| (k.to_vec(), v.to_vec())),
+        )
+    }
+
+    fn write_batch(&self, batch: WriteBatch) -> Result<(), DbError> {
+        let mut rocks_batch = rocksdb::WriteBatch::default();
+        for op in batch.ops {
+            match op {
+                BatchOp::Put { key, value } => rocks_batch.put(key, value),
+                BatchOp::Delete { key } => rocks_batch.delete(key),
+            }
+        }
+        let mut opts = WriteOptions::default();
+        opts.set_sync(true);
+        Ok(self.write_opt(rocks_batch, &opts)?)
+    }
+}
+
+/// An in-memory store, for tests and ephemeral agents
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    map: RwLock<BTreeMap<Vec<u8>, Vec<u8>>>,
+}
+
+impl KeyValueStore for MemoryStore {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, DbError> {
+        Ok(self.map.read().expect("poisoned").get(key).cloned())
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<(), DbError> {
+        self.map
+            .write()
+            .expect("poisoned")
+            .insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    fn delete(&self, key: &[u8]) -> Result<(), DbError> {
+        self.map.write().expect("poisoned").remove(key);
+        Ok(())
+    }
+
+    fn prefix_iter<'a>(&'a self, prefix: &[u8]) -> KvIterator<'a> {
+        // Snapshot the matching pairs so the lock isn't held while iterating
+        let pairs: Vec<_> = self
+            .map
+            .read()
+            .expect("poisoned")
+            .range(prefix.to_vec()..)
+            .take_while(|(k, _)| k.starts_with(prefix))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        Box::new(pairs.into_iter())
+    }
+
+    fn write_batch(&self, batch: WriteBatch) -> Result<(), DbError> {
+        let mut map = self.map.write().expect("poisoned");
+        for op in batch.ops {
+            match op {
+                BatchOp::Put { key, value } => {
+                    map.insert(key, value);
+                }
+                BatchOp::Delete { key } => {
+                    map.remove(&key);
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,6 +1,13 @@
-use crate::db::{DbError, DB};
+use crate::db::{DbError, KvIterator, WriteBatch, DB};
 use crate::{Decode, Encode};
 use color_eyre::Result;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+/// Writes staged by a batched `TypedDB`. `None` marks a delete
+type Staged = BTreeMap<Vec<u8>, Option<Vec<u8>>>;
 
 /// DB handle for storing data tied to a specific type/entity.
 ///
@@ -9,6 +16,7 @@ use color_eyre::Result;
 pub struct TypedDB {
     db: DB,
     type_prefix: Vec<u8>,
+    staged: Option<Arc<Mutex<Staged>>>,
 }
 
 impl TypedDB {
@@ -17,7 +25,54 @@ impl TypedDB {
         Self {
             db,
             type_prefix: type_prefix.into(),
+            staged: None,
+        }
+    }
+
+    /// Return a handle whose writes are staged until `commit` is called.
+    /// Reads through the handle see its staged writes. Prefix iterators do
+    /// not.
+    pub fn batched(&self) -> Self {
+        Self {
+            db: self.db.clone(),
+            type_prefix: self.type_prefix.clone(),
+            staged: Some(Default::default()),
+        }
+    }
+
+    /// Apply all staged writes in a single atomic write. No-op if the
+    /// handle is not batched
+    pub fn commit(&self) -> Result<(), DbError> {
+        let staged = match &self.staged {
+            Some(staged) => std::mem::take(&mut *staged.lock().expect("poisoned")),
+            None => return Ok(()),
+        };
+
+        let mut batch = WriteBatch::default();
+        for (key, value) in staged {
+            match value {
+                Some(value) => batch.put(key, value),
+                None => batch.delete(key),
+            }
         }
+        self.db.write_batch(batch)
+    }
+
+    /// Stage a write if batched. Returns false if not batched
+    fn stage(&self, key: Vec<u8>, value: Option<Vec<u8>>) -> bool {
+        match &self.staged {
+            Some(staged) => {
+                staged.lock().expect("poisoned").insert(key, value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn full_key(&self, prefix: impl AsRef<[u8]>, key: impl AsRef<[u8]>) -> Vec<u8> {
+        let mut full_key = self.full_prefix(prefix);
+        full_key.extend(key.as_ref());
+        full_key
     }
 
     /// Return reference to raw db
@@ -40,16 +95,46 @@ impl TypedDB {
         key: impl AsRef<[u8]>,
         value: &V,
     ) -> Result<(), DbError> {
+        if self.stage(self.full_key(&prefix, &key), Some(value.to_vec())) {
+            return Ok(());
+        }
         self.db
             .store_encodable(&self.full_prefix(prefix), key, value)
     }
 
+    /// Store encodable value, syncing the write to disk before returning
+    pub fn store_encodable_synced<V: Encode>(
+        &self,
+        prefix: impl AsRef<[u8]>,
+        key: impl AsRef<[u8]>,
+        value: &V,
+    ) -> Result<(), DbError> {
+        // batches are written durably on commit
+        if self.stage(self.full_key(&prefix, &key), Some(value.to_vec())) {
+            return Ok(());
+        }
+        self.db
+            .store_encodable_synced(&self.full_prefix(prefix), key, value)
+    }
+
     /// Retrieve decodable value
     pub fn retrieve_decodable<V: Decode>(
         &self,
         prefix: impl AsRef<[u8]>,
         key: impl AsRef<[u8]>,
     ) -> Result<Option<V>, DbError> {
+        if let Some(staged) = &self.staged {
+            if let Some(value) = staged
+                .lock()
+                .expect("poisoned")
+                .get(&self.full_key(&prefix, &key))
+            {
+                return Ok(value
+                    .as_ref()
+                    .map(|value| V::read_from(&mut value.as_slice()))
+                    .transpose()?);
+            }
+        }
         self.db.retrieve_decodable(&self.full_prefix(prefix), key)
     }
 
@@ -71,4 +156,29 @@ impl TypedDB {
     ) -> Result<Option<V>, DbError> {
         self.retrieve_decodable(prefix, key.to_vec())
     }
+
+    /// Get prefix db iterator for `prefix` within this type prefix.
+    /// Returns the full prefix, which the iterator's keys start with
+    pub fn prefix_iterator(&self, prefix: impl AsRef<[u8]>) -> (Vec<u8>, KvIterator) {
+        let full_prefix = self.full_prefix(prefix);
+        let iter = self.db.prefix_iterator(&full_prefix);
+        (full_prefix, iter)
+    }
+
+    /// Delete value
+    pub fn delete(&self, prefix: impl AsRef<[u8]>, key: impl AsRef<[u8]>) -> Result<(), DbError> {
+        if self.stage(self.full_key(&prefix, &key), None) {
+            return Ok(());
+        }
+        self.db.prefix_delete(&self.full_prefix(prefix), key)
+    }
+
+    /// Delete value given encodable key
+    pub fn delete_keyed<K: Encode>(
+        &self,
+        prefix: impl AsRef<[u8]>,
+        key: &K,
+    ) -> Result<(), DbError> {
+        self.delete(prefix, key.to_vec())
+    }
 }
@@ -8,6 +8,12 @@
 #![forbid(unsafe_code)]
 #![forbid(where_clauses_object_safety)]
 
+/// Message encoding and accumulators, usable without `std`
+pub use optics_primitives as primitives;
+
+/// Typed bodies of the bridge and governance xApp messages
+pub use optics_primitives::xapps;
+
 /// Accumulator management
 pub mod accumulator;
 
@@ -30,6 +36,7 @@ pub use traits::*;
 pub mod utils;
 
 /// Testing utilities
+#[cfg(any(test, feature = "test-utils"))]
 pub mod test_utils;
 
 /// Core optics system data structures
@@ -78,6 +85,9 @@ pub enum OpticsError {
     /// IO error from Read/Write usage
     #[error(transparent)]
     IoError(#[from] std::io::Error),
+    /// Malformed message
+    #[error(transparent)]
+    MessageViewError(#[from] OpticsMessageViewError),
 }
 
 /// Error types for Signers
@@ -1,14 +1,19 @@
 use crate::{
     accumulator::{
-        merkle::{merkle_root_from_branch, MerkleTree},
+        merkle::{merkle_root_from_branch, MerkleTree, Proof},
+        multiproof::MultiProof,
         TREE_DEPTH,
     },
     test_utils::find_vector,
     utils::{destination_and_nonce, home_domain_hash},
-    FailureNotification, OpticsMessage, Update,
+    xapps::{
+        bridge::{BridgeAction, BridgeMessage, TokenId},
+        governance::{Call, GovernanceMessage},
+    },
+    Encode, FailureNotification, OpticsMessage, SignUpdate, Update,
 };
 use ethers::{
-    core::types::{H160, H256},
+    core::types::{H160, H256, U256},
     signers::Signer,
 };
 use hex::FromHex;
@@ -97,6 +102,44 @@ pub mod output_functions {
             .expect("Failed to write to file");
     }
 
+    /// Output multi-leaf merkle proof test vectors
+    pub fn output_multi_proof() {
+        let leaves: Vec<H256> = (0..10).map(H256::from_low_u64_be).collect();
+        let tree = MerkleTree::create(&leaves, TREE_DEPTH);
+
+        let proofs: Vec<Proof> = [1, 2, 3, 8]
+            .iter()
+            .map(|index| {
+                let (leaf, hashes) = tree.generate_proof(*index, TREE_DEPTH);
+                let mut path = [H256::zero(); TREE_DEPTH];
+                path.copy_from_slice(&hashes);
+                Proof {
+                    leaf,
+                    index: *index,
+                    path,
+                }
+            })
+            .collect();
+        let proof = MultiProof::from_proofs(&proofs).unwrap();
+
+        let json = json!({
+            "proof": proof,
+            "root": tree.hash(),
+            "encoded": format!("0x{}", hex::encode(proof.to_vec())),
+        })
+        .to_string();
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(find_vector("multiProof.json"))
+            .expect("Failed to open/create file");
+
+        file.write_all(json.as_bytes())
+            .expect("Failed to write to file");
+    }
+
     /// Outputs domain hash test cases in /vector/domainHash.json
     pub fn output_home_domain_hashes() {
         let test_cases: Vec<Value> = (1..=3)
@@ -147,6 +190,135 @@ pub mod output_functions {
             .expect("Failed to write to file");
     }
 
+    /// Outputs bridge message test cases in /vector/bridgeMessage.json
+    pub fn output_bridge_messages() {
+        let token_id = TokenId {
+            domain: 1,
+            id: H256::repeat_byte(0x11),
+        };
+
+        // names and symbols are right-padded, like solidity's bytes32
+        let mut name = H256::zero();
+        name[..10].copy_from_slice(b"TEST TOKEN");
+        let mut symbol = H256::zero();
+        symbol[..4].copy_from_slice(b"TEST");
+
+        let actions = vec![
+            (
+                "transfer",
+                BridgeAction::Transfer {
+                    recipient: H256::from(
+                        H160::from_str("0x2222222222222222222222222222222222222222").unwrap(),
+                    ),
+                    amount: U256::from(0xffff),
+                },
+            ),
+            (
+                "details",
+                BridgeAction::Details {
+                    name,
+                    symbol,
+                    decimals: 8,
+                },
+            ),
+            ("requestDetails", BridgeAction::RequestDetails),
+        ];
+
+        let test_cases: Vec<Value> = actions
+            .into_iter()
+            .map(|(test_name, action)| {
+                let message = BridgeMessage { token_id, action };
+                json!({
+                    "testName": test_name,
+                    "message": message,
+                    "tokenId": format!("0x{}", hex::encode(message.token_id.to_vec())),
+                    "action": format!("0x{}", hex::encode(message.action.to_vec())),
+                    "encoded": format!("0x{}", hex::encode(message.to_vec())),
+                })
+            })
+            .collect();
+
+        let json = json!(test_cases).to_string();
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(find_vector("bridgeMessage.json"))
+            .expect("Failed to open/create file");
+
+        file.write_all(json.as_bytes())
+            .expect("Failed to write to file");
+    }
+
+    /// Outputs governance message test cases in /vector/governanceMessage.json
+    pub fn output_governance_messages() {
+        let messages = vec![
+            (
+                "calls",
+                GovernanceMessage::Calls {
+                    calls: vec![
+                        Call {
+                            to: H256::from(
+                                H160::from_str("0x1111111111111111111111111111111111111111")
+                                    .unwrap(),
+                            ),
+                            data: Vec::from_hex("12345678").unwrap(),
+                        },
+                        Call {
+                            to: H256::from(
+                                H160::from_str("0x2222222222222222222222222222222222222222")
+                                    .unwrap(),
+                            ),
+                            data: vec![],
+                        },
+                    ],
+                },
+            ),
+            (
+                "transferGovernor",
+                GovernanceMessage::TransferGovernor {
+                    domain: 2000,
+                    governor: H256::from(
+                        H160::from_str("0x3333333333333333333333333333333333333333").unwrap(),
+                    ),
+                },
+            ),
+            (
+                "setRouter",
+                GovernanceMessage::SetRouter {
+                    domain: 3000,
+                    router: H256::from(
+                        H160::from_str("0x4444444444444444444444444444444444444444").unwrap(),
+                    ),
+                },
+            ),
+        ];
+
+        let test_cases: Vec<Value> = messages
+            .into_iter()
+            .map(|(test_name, message)| {
+                json!({
+                    "testName": test_name,
+                    "encoded": format!("0x{}", hex::encode(message.to_vec())),
+                    "message": message,
+                })
+            })
+            .collect();
+
+        let json = json!(test_cases).to_string();
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(find_vector("governanceMessage.json"))
+            .expect("Failed to open/create file");
+
+        file.write_all(json.as_bytes())
+            .expect("Failed to write to file");
+    }
+
     /// Outputs signed update test cases in /vector/signedUpdate.json
     pub fn output_signed_updates() {
         let t = async {
@@ -1,5 +1,12 @@
-use crate::OpticsError;
-use ethers::prelude::{Signature, SignatureError, H256};
+use crate::{
+    accumulator::{consistency::ConsistencyProof, merkle::Proof, multiproof::MultiProof},
+    xapps::{
+        bridge::{BridgeAction, BridgeMessage, TokenId},
+        governance::{Call, GovernanceMessage},
+    },
+    OpticsError, OpticsMessage, Update,
+};
+use ethers::prelude::{Signature, SignatureError, H256, U256};
 use std::convert::TryFrom;
 
 /// Simple trait for types with a canonical encoding
@@ -51,68 +58,106 @@ impl Decode for Signature {
     }
 }
 
-impl Encode for H256 {
+impl Encode for String {
     fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
     where
         W: std::io::Write,
     {
-        writer.write_all(self.as_ref())?;
-        Ok(32)
+        let written = (self.len() as u32).write_to(writer)?;
+        writer.write_all(self.as_bytes())?;
+        Ok(written + self.len())
     }
 }
 
-impl Decode for H256 {
+impl Decode for String {
     fn read_from<R>(reader: &mut R) -> Result<Self, OpticsError>
     where
         R: std::io::Read,
         Self: Sized,
     {
-        let mut digest = H256::default();
-        reader.read_exact(digest.as_mut())?;
-        Ok(digest)
+        let mut buf = vec![0u8; u32::read_from(reader)? as usize];
+        reader.read_exact(&mut buf)?;
+        String::from_utf8(buf).map_err(|e| {
+            OpticsError::IoError(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+        })
     }
 }
 
-impl Encode for u32 {
+/// A flag byte, 1 if there is a value, followed by the value if there is one
+impl<T: Encode> Encode for Option<T> {
     fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
     where
         W: std::io::Write,
     {
-        writer.write_all(&self.to_be_bytes())?;
-        Ok(4)
+        match self {
+            Some(value) => {
+                writer.write_all(&[1])?;
+                Ok(1 + value.write_to(writer)?)
+            }
+            None => {
+                writer.write_all(&[0])?;
+                Ok(1)
+            }
+        }
     }
 }
 
-impl Decode for u32 {
+impl<T: Decode> Decode for Option<T> {
     fn read_from<R>(reader: &mut R) -> Result<Self, OpticsError>
     where
         R: std::io::Read,
         Self: Sized,
     {
-        let mut buf = [0; 4];
-        reader.read_exact(&mut buf)?;
-        Ok(u32::from_be_bytes(buf))
+        let mut flag = [0u8; 1];
+        reader.read_exact(&mut flag)?;
+        if flag[0] != 0 {
+            Ok(Some(T::read_from(reader)?))
+        } else {
+            Ok(None)
+        }
     }
 }
 
-impl Encode for u64 {
-    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
-    where
-        W: std::io::Write,
-    {
-        writer.write_all(&self.to_be_bytes())?;
-        Ok(8)
-    }
-}
+/// Implement `Encode` and `Decode` using the canonical encoding from
+/// `optics_primitives`
+macro_rules! impl_via_primitives {
+    ($($t:ty),* $(,)?) => {
+        $(
+            impl Encode for $t {
+                fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+                where
+                    W: std::io::Write,
+                {
+                    optics_primitives::Encode::write_to(self, writer)
+                }
+            }
 
-impl Decode for u64 {
-    fn read_from<R>(reader: &mut R) -> Result<Self, OpticsError>
-    where
-        R: std::io::Read,
-        Self: Sized,
-    {
-        let mut buf = [0; 8];
-        reader.read_exact(&mut buf)?;
-        Ok(u64::from_be_bytes(buf))
-    }
+            impl Decode for $t {
+                fn read_from<R>(reader: &mut R) -> Result<Self, OpticsError>
+                where
+                    R: std::io::Read,
+                    Self: Sized,
+                {
+                    Ok(<$t as optics_primitives::Decode>::read_from(reader)?)
+                }
+            }
+        )*
+    };
 }
+
+impl_via_primitives!(
+    H256,
+    U256,
+    u32,
+    u64,
+    OpticsMessage,
+    Update,
+    Proof,
+    MultiProof,
+    ConsistencyProof,
+    TokenId,
+    BridgeAction,
+    BridgeMessage,
+    Call,
+    GovernanceMessage,
+);
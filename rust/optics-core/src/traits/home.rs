@@ -3,7 +3,7 @@ use std::convert::TryFrom;
 use crate::{
     traits::{ChainCommunicationError, Common, TxOutcome},
     utils::home_domain_hash,
-    Decode, Encode, Message, OpticsError, OpticsMessage, SignedUpdate, Update,
+    Decode, Encode, Message, OpticsError, OpticsMessage, OpticsMessageView, SignedUpdate, Update,
 };
 use async_trait::async_trait;
 use color_eyre::Result;
@@ -30,6 +30,11 @@ impl RawCommittedMessage {
     pub fn leaf(&self) -> H256 {
         keccak256(&self.message).into()
     }
+
+    /// Borrow the message's fields without decoding it
+    pub fn view(&self) -> Result<OpticsMessageView<'_>, OpticsError> {
+        Ok(OpticsMessageView::new(&self.message)?)
+    }
 }
 
 impl Encode for RawCommittedMessage {
@@ -99,7 +104,7 @@ impl TryFrom<RawCommittedMessage> for CommittedMessage {
         Ok(Self {
             leaf_index: raw.leaf_index,
             committed_root: raw.committed_root,
-            message: OpticsMessage::read_from(&mut &raw.message[..])?,
+            message: raw.view()?.to_message(),
         })
     }
 }
@@ -111,11 +116,13 @@ pub trait Home: Common + Send + Sync + std::fmt::Debug {
     /// Return the domain ID
     fn local_domain(&self) -> u32;
 
-    /// Run a task indexing the chain (if necessary)
+    /// Run a task indexing the chain (if necessary). The indexer stays
+    /// `confirmations` blocks behind the chain tip.
     fn index(
         &self,
         from_height: u32,
         chunk_size: u32,
+        confirmations: u32,
         indexed_height: prometheus::IntGauge,
     ) -> Instrumented<JoinHandle<Result<()>>>;
 
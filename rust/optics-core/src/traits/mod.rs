@@ -6,7 +6,7 @@ mod xapp;
 use async_trait::async_trait;
 use ethers::{
     contract::ContractError,
-    core::types::{TransactionReceipt, H256},
+    core::types::{TransactionReceipt, H256, U256},
     providers::{Middleware, ProviderError},
 };
 use std::error::Error as StdError;
@@ -31,6 +31,31 @@ pub enum State {
 #[derive(Debug, Clone, PartialEq)]
 pub struct DoubleUpdate(pub SignedUpdate, pub SignedUpdate);
 
+impl Encode for DoubleUpdate {
+    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    where
+        W: std::io::Write,
+    {
+        let mut written = 0;
+        written += self.0.write_to(writer)?;
+        written += self.1.write_to(writer)?;
+        Ok(written)
+    }
+}
+
+impl Decode for DoubleUpdate {
+    fn read_from<R>(reader: &mut R) -> Result<Self, OpticsError>
+    where
+        R: std::io::Read,
+        Self: Sized,
+    {
+        Ok(Self(
+            SignedUpdate::read_from(reader)?,
+            SignedUpdate::read_from(reader)?,
+        ))
+    }
+}
+
 /// The result of a transaction
 #[derive(Debug, Clone, Copy)]
 pub struct TxOutcome {
@@ -38,6 +63,10 @@ pub struct TxOutcome {
     pub txid: H256,
     /// True if executed, false otherwise (reverted, etc.)
     pub executed: bool,
+    /// The gas the transaction used
+    pub gas_used: U256,
+    /// The block the transaction was included in, if known
+    pub block_number: Option<u64>,
     // TODO: more? What can be abstracted across all chains?
 }
 
@@ -46,6 +75,8 @@ impl From<TransactionReceipt> for TxOutcome {
         Self {
             txid: t.transaction_hash,
             executed: t.status.unwrap().low_u32() == 1,
+            gas_used: t.gas_used.unwrap_or_default(),
+            block_number: t.block_number.map(|n| n.as_u64()),
         }
     }
 }
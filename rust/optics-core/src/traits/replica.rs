@@ -1,5 +1,8 @@
 use async_trait::async_trait;
+use color_eyre::Result;
 use ethers::core::types::H256;
+use tokio::task::JoinHandle;
+use tracing::instrument::Instrumented;
 
 use crate::{
     accumulator::merkle::Proof,
@@ -49,4 +52,14 @@ pub trait Replica: Common + Send + Sync + std::fmt::Debug {
 
     /// Fetch the confirmation time for a specific root
     async fn acceptable_root(&self, root: H256) -> Result<bool, ChainCommunicationError>;
+
+    /// Run a task indexing the chain (if necessary). The indexer stays
+    /// `confirmations` blocks behind the chain tip.
+    fn index(
+        &self,
+        from_height: u32,
+        chunk_size: u32,
+        confirmations: u32,
+        indexed_height: prometheus::IntGauge,
+    ) -> Instrumented<JoinHandle<Result<()>>>;
 }
@@ -1,5 +1,4 @@
 mod failure;
-mod messages;
 mod update;
 
 /// Unified 32-byte identifier with convenience tooling for handling
@@ -7,5 +6,7 @@ mod update;
 pub mod identifiers;
 
 pub use failure::*;
-pub use messages::*;
+pub use optics_primitives::{
+    Message, OpticsMessage, OpticsMessageView, OpticsMessageViewError, OPTICS_MESSAGE_PREFIX_LEN,
+};
 pub use update::*;
@@ -1,89 +1,21 @@
-use crate::{utils::home_domain_hash, Decode, Encode, OpticsError, SignerExt};
-use ethers::{
-    prelude::{Address, Signature},
-    types::H256,
-    utils::hash_message,
-};
+use crate::{Decode, Encode, OpticsError, SignerExt};
+use async_trait::async_trait;
+use ethers::prelude::{Address, Signature};
 use ethers_signers::Signer;
 use serde::{Deserialize, Serialize};
-use sha3::{Digest, Keccak256};
 
-/// An Optics update message
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
-pub struct Update {
-    /// The home chain
-    pub home_domain: u32,
-    /// The previous root
-    pub previous_root: H256,
-    /// The new root
-    pub new_root: H256,
-}
-
-impl std::fmt::Display for Update {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "Update(domain {} moved from {} to {})",
-            self.home_domain, self.previous_root, self.new_root
-        )
-    }
-}
+pub use optics_primitives::Update;
 
-impl Encode for Update {
-    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
-    where
-        W: std::io::Write,
-    {
-        writer.write_all(&self.home_domain.to_be_bytes())?;
-        writer.write_all(self.previous_root.as_ref())?;
-        writer.write_all(self.new_root.as_ref())?;
-        Ok(4 + 32 + 32)
-    }
-}
-
-impl Decode for Update {
-    fn read_from<R>(reader: &mut R) -> Result<Self, OpticsError>
-    where
-        R: std::io::Read,
-        Self: Sized,
-    {
-        let mut home_domain = [0u8; 4];
-        reader.read_exact(&mut home_domain)?;
-
-        let mut previous_root = H256::zero();
-        reader.read_exact(previous_root.as_mut())?;
-
-        let mut new_root = H256::zero();
-        reader.read_exact(new_root.as_mut())?;
-
-        Ok(Self {
-            home_domain: u32::from_be_bytes(home_domain),
-            previous_root,
-            new_root,
-        })
-    }
+/// Signing for `Update`s
+#[async_trait]
+pub trait SignUpdate {
+    /// Sign an update using the specified signer
+    async fn sign_with<S: Signer>(self, signer: &S) -> Result<SignedUpdate, S::Error>;
 }
 
-impl Update {
-    fn signing_hash(&self) -> H256 {
-        // sign:
-        // domain(home_domain) || previous_root || new_root
-        H256::from_slice(
-            Keccak256::new()
-                .chain(home_domain_hash(self.home_domain))
-                .chain(self.previous_root)
-                .chain(self.new_root)
-                .finalize()
-                .as_slice(),
-        )
-    }
-
-    fn prepended_hash(&self) -> H256 {
-        hash_message(self.signing_hash())
-    }
-
-    /// Sign an update using the specified signer
-    pub async fn sign_with<S: Signer>(self, signer: &S) -> Result<SignedUpdate, S::Error> {
+#[async_trait]
+impl SignUpdate for Update {
+    async fn sign_with<S: Signer>(self, signer: &S) -> Result<SignedUpdate, S::Error> {
         let signature = signer
             .sign_message_without_eip_155(self.signing_hash())
             .await?;
@@ -1,8 +1,8 @@
 use std::str::FromStr;
 
 use color_eyre::{eyre::bail, Report};
-use ethers::core::types::H256;
-use sha3::{Digest, Keccak256};
+
+pub use optics_primitives::utils::{destination_and_nonce, home_domain_hash};
 
 /// Strips the '0x' prefix off of hex string so it can be deserialized.
 ///
@@ -17,25 +17,6 @@ pub fn strip_0x_prefix(s: &str) -> &str {
     }
 }
 
-/// Computes hash of home domain concatenated with "OPTICS"
-pub fn home_domain_hash(home_domain: u32) -> H256 {
-    H256::from_slice(
-        Keccak256::new()
-            .chain(home_domain.to_be_bytes())
-            .chain("OPTICS".as_bytes())
-            .finalize()
-            .as_slice(),
-    )
-}
-
-/// Destination and destination-specific nonce combined in single field (
-/// (destination << 32) & nonce)
-pub fn destination_and_nonce(destination: u32, nonce: u32) -> u64 {
-    assert!(destination < u32::MAX);
-    assert!(nonce < u32::MAX);
-    ((destination as u64) << 32) | nonce as u64
-}
-
 /// A Hex String of length `N` representing bytes of length `N / 2`
 #[derive(Debug, Clone)]
 pub struct HexString<const N: usize>(String);
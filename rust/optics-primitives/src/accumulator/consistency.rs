@@ -0,0 +1,226 @@
+use alloc::{vec, vec::Vec};
+use primitive_types::H256;
+
+use crate::{
+    accumulator::{hash_concat, incremental::IncrementalMerkle, TREE_DEPTH, ZERO_HASHES},
+    io, Decode, Encode,
+};
+
+/// A proof that the tree at `new_count` leaves is an append-only extension
+/// of the tree at `old_count` leaves.
+///
+/// Both roots are computed along the path of leaf `old_count`. Left of the
+/// path, both trees share the old tree's complete subtrees, which are the
+/// old tree's incremental branch. Right of the path, the old tree is empty,
+/// and the new tree's subtrees are provided by the proof.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct ConsistencyProof {
+    /// The number of leaves in the old tree
+    pub old_count: usize,
+    /// The number of leaves in the new tree
+    pub new_count: usize,
+    /// The old tree's complete subtrees, from the leaves up. These are the
+    /// entries of its incremental branch whose bit is set in `old_count`
+    pub old_branch: Vec<H256>,
+    /// The new tree's nodes on the path, from the leaves up: leaf
+    /// `old_count`, then each non-empty subtree right of the path. Empty if
+    /// the counts are equal
+    pub new_nodes: Vec<H256>,
+}
+
+/// Error type for consistency proof ops.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "std", derive(thiserror::Error))]
+pub enum ConsistencyProofError {
+    /// The old tree is larger than the new tree
+    #[cfg_attr(
+        feature = "std",
+        error("Old tree has {old_count} leaves, more than the new tree's {new_count}")
+    )]
+    OldTreeLarger {
+        /// The number of leaves in the old tree
+        old_count: usize,
+        /// The number of leaves in the new tree
+        new_count: usize,
+    },
+    /// Count is outside the tree
+    #[cfg_attr(feature = "std", error("Count {0} is outside the tree"))]
+    CountTooHigh(usize),
+    /// Proof has too few nodes to compute the roots
+    #[cfg_attr(feature = "std", error("Proof has too few nodes"))]
+    MissingNodes,
+    /// Proof has nodes that are not used to compute the roots
+    #[cfg_attr(feature = "std", error("Proof has unused nodes"))]
+    ExtraNodes,
+}
+
+impl ConsistencyProof {
+    /// Collect the old tree's complete subtrees from its incremental branch
+    pub fn old_branch(old: &IncrementalMerkle) -> Vec<H256> {
+        let count = old.count();
+        old.branch()
+            .iter()
+            .enumerate()
+            .filter(|(height, _)| (count >> height) & 1 == 1)
+            .map(|(_, node)| *node)
+            .collect()
+    }
+
+    /// Calculate the old and new roots produced by evaluating the proof
+    pub fn roots(&self) -> Result<(H256, H256), ConsistencyProofError> {
+        let (old_count, new_count) = (self.old_count, self.new_count);
+        if old_count > new_count {
+            return Err(ConsistencyProofError::OldTreeLarger {
+                old_count,
+                new_count,
+            });
+        }
+        if new_count > u32::MAX as usize {
+            return Err(ConsistencyProofError::CountTooHigh(new_count));
+        }
+
+        let mut old_branch = self.old_branch.iter();
+        let mut new_nodes = self.new_nodes.iter();
+
+        let mut old_node = H256::zero();
+        let mut new_node = if new_count > old_count {
+            *new_nodes
+                .next()
+                .ok_or(ConsistencyProofError::MissingNodes)?
+        } else {
+            H256::zero()
+        };
+
+        // Node indices can reach 2^32, so work in u64
+        let (old_count, new_count) = (old_count as u64, new_count as u64);
+        for (height, zero) in ZERO_HASHES.iter().enumerate().take(TREE_DEPTH) {
+            let index = old_count >> height;
+            if index & 1 == 1 {
+                let left = old_branch
+                    .next()
+                    .ok_or(ConsistencyProofError::MissingNodes)?;
+                old_node = hash_concat(left, old_node);
+                new_node = hash_concat(left, new_node);
+            } else {
+                old_node = hash_concat(old_node, zero);
+                let right = if (index + 1) << height < new_count {
+                    *new_nodes
+                        .next()
+                        .ok_or(ConsistencyProofError::MissingNodes)?
+                } else {
+                    *zero
+                };
+                new_node = hash_concat(new_node, right);
+            }
+        }
+
+        if old_branch.next().is_some() || new_nodes.next().is_some() {
+            return Err(ConsistencyProofError::ExtraNodes);
+        }
+        Ok((old_node, new_node))
+    }
+
+    /// Verify that the tree with root `new_root` extends the tree with root
+    /// `old_root`
+    pub fn verify(&self, old_root: H256, new_root: H256) -> bool {
+        self.roots() == Ok((old_root, new_root))
+    }
+}
+
+impl Encode for ConsistencyProof {
+    fn write_to<W>(&self, writer: &mut W) -> io::Result<usize>
+    where
+        W: io::Write,
+    {
+        let mut written = 0;
+        written += (self.old_count as u32).write_to(writer)?;
+        written += (self.new_count as u32).write_to(writer)?;
+        written += (self.old_branch.len() as u32).write_to(writer)?;
+        for node in self.old_branch.iter() {
+            written += node.write_to(writer)?;
+        }
+        written += (self.new_nodes.len() as u32).write_to(writer)?;
+        for node in self.new_nodes.iter() {
+            written += node.write_to(writer)?;
+        }
+        Ok(written)
+    }
+}
+
+impl Decode for ConsistencyProof {
+    fn read_from<R>(reader: &mut R) -> io::Result<Self>
+    where
+        R: io::Read,
+        Self: Sized,
+    {
+        let old_count = u32::read_from(reader)? as usize;
+        let new_count = u32::read_from(reader)? as usize;
+
+        let mut old_branch = vec![];
+        for _ in 0..u32::read_from(reader)? {
+            old_branch.push(H256::read_from(reader)?);
+        }
+        let mut new_nodes = vec![];
+        for _ in 0..u32::read_from(reader)? {
+            new_nodes.push(H256::read_from(reader)?);
+        }
+
+        Ok(Self {
+            old_count,
+            new_count,
+            old_branch,
+            new_nodes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_proves_a_tree_consistent_with_itself() {
+        let mut tree = IncrementalMerkle::default();
+        for i in 0..11u64 {
+            let proof = ConsistencyProof {
+                old_count: tree.count(),
+                new_count: tree.count(),
+                old_branch: ConsistencyProof::old_branch(&tree),
+                new_nodes: vec![],
+            };
+            assert!(proof.verify(tree.root(), tree.root()));
+
+            let decoded = ConsistencyProof::read_from(&mut proof.to_vec().as_slice()).unwrap();
+            assert_eq!(decoded, proof);
+
+            tree.ingest(H256::from_low_u64_be(i));
+        }
+    }
+
+    #[test]
+    fn it_rejects_malformed_proofs() {
+        let proof = ConsistencyProof {
+            old_count: 3,
+            new_count: 2,
+            old_branch: vec![],
+            new_nodes: vec![],
+        };
+        assert_eq!(
+            proof.roots(),
+            Err(ConsistencyProofError::OldTreeLarger {
+                old_count: 3,
+                new_count: 2
+            })
+        );
+
+        let proof = ConsistencyProof {
+            old_count: 3,
+            new_count: 4,
+            old_branch: vec![H256::zero()],
+            new_nodes: vec![H256::zero()],
+        };
+        assert_eq!(proof.roots(), Err(ConsistencyProofError::MissingNodes));
+    }
+}
@@ -1,4 +1,4 @@
-use ethers::core::types::H256;
+use primitive_types::H256;
 
 use crate::accumulator::{
     hash_concat,
@@ -25,6 +25,11 @@ impl Default for IncrementalMerkle {
 }
 
 impl IncrementalMerkle {
+    /// Instantiate a tree from its leading-edge branch and leaf count
+    pub fn new(branch: [H256; TREE_DEPTH], count: usize) -> Self {
+        Self { branch, count }
+    }
+
     /// Ingest a leaf into the tree.
     pub fn ingest(&mut self, element: H256) {
         let mut node = element;
@@ -82,10 +87,8 @@ impl IncrementalMerkle {
 
 #[cfg(test)]
 mod test {
-    use ethers::utils::hash_message;
-
     use super::*;
-    use crate::test_utils;
+    use crate::{test_utils, utils::hash_message};
 
     #[test]
     fn it_computes_branch_roots() {
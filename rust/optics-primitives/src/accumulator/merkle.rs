@@ -1,10 +1,10 @@
-use ethers::core::types::H256;
+use alloc::{boxed::Box, vec, vec::Vec};
 use lazy_static::lazy_static;
-use thiserror::Error;
+use primitive_types::H256;
 
 use crate::{
     accumulator::{hash_concat, EMPTY_SLICE, TREE_DEPTH, ZERO_HASHES},
-    Decode, Encode,
+    io, Decode, Encode,
 };
 
 // Some code has been derived from
@@ -41,7 +41,8 @@ pub enum MerkleTree {
 
 /// A merkle proof object. The leaf, its path to the root, and its index in the
 /// tree.
-#[derive(Debug, Clone, Copy, serde::Deserialize, serde::Serialize, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub struct Proof {
     /// The leaf
     pub leaf: H256,
@@ -59,12 +60,12 @@ impl Proof {
 }
 
 impl Encode for Proof {
-    fn write_to<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    fn write_to<W>(&self, writer: &mut W) -> io::Result<usize>
     where
-        W: std::io::Write,
+        W: io::Write,
     {
         writer.write_all(self.leaf.as_bytes())?;
-        writer.write_all(&self.index.to_be_bytes())?;
+        writer.write_all(&(self.index as u64).to_be_bytes())?;
         for hash in self.path.iter() {
             writer.write_all(hash.as_bytes())?;
         }
@@ -73,9 +74,9 @@ impl Encode for Proof {
 }
 
 impl Decode for Proof {
-    fn read_from<R>(reader: &mut R) -> Result<Self, crate::OpticsError>
+    fn read_from<R>(reader: &mut R) -> io::Result<Self>
     where
-        R: std::io::Read,
+        R: io::Read,
         Self: Sized,
     {
         let mut leaf = H256::default();
@@ -95,19 +96,20 @@ impl Decode for Proof {
 }
 
 /// Error type for merkle tree ops.
-#[derive(Debug, PartialEq, Clone, Error)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "std", derive(thiserror::Error))]
 pub enum MerkleTreeError {
     /// Trying to push in a leaf
-    #[error("Trying to push in a leaf")]
+    #[cfg_attr(feature = "std", error("Trying to push in a leaf"))]
     LeafReached,
     /// No more space in the MerkleTree
-    #[error("No more space in the MerkleTree")]
+    #[cfg_attr(feature = "std", error("No more space in the MerkleTree"))]
     MerkleTreeFull,
     /// MerkleTree is invalid
-    #[error("MerkleTree is invalid")]
+    #[cfg_attr(feature = "std", error("MerkleTree is invalid"))]
     Invalid,
     /// Incorrect Depth provided
-    #[error("Incorrect Depth provided")]
+    #[cfg_attr(feature = "std", error("Incorrect Depth provided"))]
     DepthTooSmall,
 }
 
@@ -292,7 +294,7 @@ pub fn merkle_root_from_branch(leaf: H256, branch: &[H256], depth: usize, index:
 
 #[cfg(test)]
 mod tests {
-    use crate::accumulator::incremental;
+    use crate::{accumulator::incremental, test_utils};
 
     use super::*;
 
@@ -480,6 +482,15 @@ mod tests {
             });
     }
 
+    #[test]
+    fn it_verifies_the_proof_test_vector() {
+        let vector = test_utils::load_proof_test_json();
+        assert_eq!(vector.proof.root(), vector.root);
+
+        let decoded = Proof::read_from(&mut vector.proof.to_vec().as_slice()).unwrap();
+        assert_eq!(decoded, vector.proof);
+    }
+
     #[test]
     fn it_is_compatible_with_incremental_merkle() {
         let leaf = H256::repeat_byte(1);
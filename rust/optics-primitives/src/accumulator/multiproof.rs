@@ -0,0 +1,317 @@
+use alloc::{vec, vec::Vec};
+use primitive_types::H256;
+
+use crate::{
+    accumulator::{hash_concat, merkle::Proof, TREE_DEPTH},
+    io, Decode, Encode,
+};
+
+/// A merkle proof of several leaves under one root.
+///
+/// Siblings shared by the leaves' paths, and siblings that can be computed
+/// from other proven leaves, are only included once (or not at all).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct MultiProof {
+    /// The indices of the proven leaves, in ascending order
+    pub indices: Vec<usize>,
+    /// The proven leaves, in the order of `indices`
+    pub leaves: Vec<H256>,
+    /// The sibling hashes needed to compute the root, in the order they are
+    /// consumed, layer by layer from the leaves up
+    pub siblings: Vec<H256>,
+}
+
+/// Error type for multi-leaf proof ops.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "std", derive(thiserror::Error))]
+pub enum MultiProofError {
+    /// No leaves to prove
+    #[cfg_attr(feature = "std", error("No leaves to prove"))]
+    Empty,
+    /// Different numbers of indices and leaves
+    #[cfg_attr(
+        feature = "std",
+        error("Proof has {indices} indices but {leaves} leaves")
+    )]
+    MismatchedLengths {
+        /// The number of indices
+        indices: usize,
+        /// The number of leaves
+        leaves: usize,
+    },
+    /// Indices are not strictly ascending
+    #[cfg_attr(feature = "std", error("Indices are not strictly ascending"))]
+    UnsortedIndices,
+    /// Index is outside the tree
+    #[cfg_attr(feature = "std", error("Index {0} is outside the tree"))]
+    IndexTooHigh(usize),
+    /// Proof has too few siblings to compute a root
+    #[cfg_attr(feature = "std", error("Proof has too few siblings"))]
+    MissingSiblings,
+    /// Proof has siblings that are not used to compute the root
+    #[cfg_attr(feature = "std", error("Proof has unused siblings"))]
+    ExtraSiblings,
+    /// The single proofs are not all under the same root
+    #[cfg_attr(feature = "std", error("Proofs are under different roots"))]
+    MismatchedRoots,
+    /// The single proofs prove different leaves at the same index
+    #[cfg_attr(feature = "std", error("Proofs prove different leaves at index {0}"))]
+    ConflictingLeaves(usize),
+}
+
+/// The index of the parent of the node at `index` in its layer
+fn parent(index: usize) -> usize {
+    index / 2
+}
+
+/// True if the node at position `i` of `layer` and the next node are
+/// siblings
+fn pairs_with_next<T>(layer: &[(usize, T)], i: usize) -> bool {
+    layer[i].0 % 2 == 0 && layer.get(i + 1).map(|next| next.0) == Some(layer[i].0 + 1)
+}
+
+impl MultiProof {
+    /// Combine proofs of single leaves under the same root
+    pub fn from_proofs(proofs: &[Proof]) -> Result<Self, MultiProofError> {
+        let first = proofs.first().ok_or(MultiProofError::Empty)?;
+        let root = first.root();
+
+        let mut sorted: Vec<&Proof> = proofs.iter().collect();
+        sorted.sort_by_key(|proof| proof.index);
+        let mut layer: Vec<(usize, &Proof)> = vec![];
+        for proof in sorted {
+            if proof.index as u64 >= 1 << TREE_DEPTH {
+                return Err(MultiProofError::IndexTooHigh(proof.index));
+            }
+            if proof.root() != root {
+                return Err(MultiProofError::MismatchedRoots);
+            }
+            match layer.last() {
+                Some((index, last)) if *index == proof.index => {
+                    if last.leaf != proof.leaf {
+                        return Err(MultiProofError::ConflictingLeaves(proof.index));
+                    }
+                }
+                _ => layer.push((proof.index, proof)),
+            }
+        }
+
+        let indices = layer.iter().map(|(index, _)| *index).collect();
+        let leaves = layer.iter().map(|(_, proof)| proof.leaf).collect();
+
+        // Walk the layers as `root` does, taking a sibling from a single
+        // proof whenever the sibling is not itself computed
+        let mut siblings = vec![];
+        for height in 0..TREE_DEPTH {
+            let mut next = Vec::with_capacity(layer.len());
+            let mut i = 0;
+            while i < layer.len() {
+                let (index, proof) = layer[i];
+                if pairs_with_next(&layer, i) {
+                    i += 2;
+                } else {
+                    siblings.push(proof.path[height]);
+                    i += 1;
+                }
+                next.push((parent(index), proof));
+            }
+            layer = next;
+        }
+
+        Ok(Self {
+            indices,
+            leaves,
+            siblings,
+        })
+    }
+
+    /// Calculate the merkle root produced by evaluating the proof
+    pub fn root(&self) -> Result<H256, MultiProofError> {
+        if self.indices.len() != self.leaves.len() {
+            return Err(MultiProofError::MismatchedLengths {
+                indices: self.indices.len(),
+                leaves: self.leaves.len(),
+            });
+        }
+        if self.indices.is_empty() {
+            return Err(MultiProofError::Empty);
+        }
+        if self.indices.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(MultiProofError::UnsortedIndices);
+        }
+        if let Some(index) = self
+            .indices
+            .iter()
+            .find(|index| **index as u64 >= 1 << TREE_DEPTH)
+        {
+            return Err(MultiProofError::IndexTooHigh(*index));
+        }
+
+        let mut layer: Vec<(usize, H256)> = self
+            .indices
+            .iter()
+            .copied()
+            .zip(self.leaves.iter().copied())
+            .collect();
+        let mut siblings = self.siblings.iter();
+
+        for _ in 0..TREE_DEPTH {
+            let mut next = Vec::with_capacity(layer.len());
+            let mut i = 0;
+            while i < layer.len() {
+                let (index, node) = layer[i];
+                let parent_node = if pairs_with_next(&layer, i) {
+                    let right = layer[i + 1].1;
+                    i += 2;
+                    hash_concat(node, right)
+                } else {
+                    i += 1;
+                    let sibling = siblings.next().ok_or(MultiProofError::MissingSiblings)?;
+                    if index % 2 == 0 {
+                        hash_concat(node, sibling)
+                    } else {
+                        hash_concat(sibling, node)
+                    }
+                };
+                next.push((parent(index), parent_node));
+            }
+            layer = next;
+        }
+
+        if siblings.next().is_some() {
+            return Err(MultiProofError::ExtraSiblings);
+        }
+        Ok(layer[0].1)
+    }
+
+    /// Verify the proof against `root`
+    pub fn verify(&self, root: H256) -> bool {
+        self.root().map(|actual| actual == root).unwrap_or(false)
+    }
+}
+
+impl Encode for MultiProof {
+    fn write_to<W>(&self, writer: &mut W) -> io::Result<usize>
+    where
+        W: io::Write,
+    {
+        let mut written = 0;
+        written += (self.indices.len() as u32).write_to(writer)?;
+        for (index, leaf) in self.indices.iter().zip(self.leaves.iter()) {
+            written += (*index as u64).write_to(writer)?;
+            written += leaf.write_to(writer)?;
+        }
+        written += (self.siblings.len() as u32).write_to(writer)?;
+        for sibling in self.siblings.iter() {
+            written += sibling.write_to(writer)?;
+        }
+        Ok(written)
+    }
+}
+
+impl Decode for MultiProof {
+    fn read_from<R>(reader: &mut R) -> io::Result<Self>
+    where
+        R: io::Read,
+        Self: Sized,
+    {
+        let num_leaves = u32::read_from(reader)?;
+        let mut indices = vec![];
+        let mut leaves = vec![];
+        for _ in 0..num_leaves {
+            indices.push(u64::read_from(reader)? as usize);
+            leaves.push(H256::read_from(reader)?);
+        }
+
+        let num_siblings = u32::read_from(reader)?;
+        let mut siblings = vec![];
+        for _ in 0..num_siblings {
+            siblings.push(H256::read_from(reader)?);
+        }
+
+        Ok(Self {
+            indices,
+            leaves,
+            siblings,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::accumulator::merkle::MerkleTree;
+    use crate::test_utils;
+
+    fn tree(count: u64) -> MerkleTree {
+        let leaves: Vec<_> = (0..count).map(H256::from_low_u64_be).collect();
+        MerkleTree::create(&leaves, TREE_DEPTH)
+    }
+
+    fn prove(tree: &MerkleTree, index: usize) -> Proof {
+        let (leaf, hashes) = tree.generate_proof(index, TREE_DEPTH);
+        let mut path = [H256::zero(); TREE_DEPTH];
+        path.copy_from_slice(&hashes);
+        Proof { leaf, index, path }
+    }
+
+    #[test]
+    fn it_combines_and_verifies_proofs() {
+        let tree = tree(13);
+        let root = tree.hash();
+
+        for indices in [vec![0], vec![3, 4], vec![0, 1, 2, 3], vec![12, 1, 6, 7, 6]].iter() {
+            let proofs: Vec<_> = indices.iter().map(|i| prove(&tree, *i)).collect();
+            let multi = MultiProof::from_proofs(&proofs).unwrap();
+            assert_eq!(multi.root().unwrap(), root);
+            assert!(multi.verify(root));
+            assert!(multi.indices.windows(2).all(|pair| pair[0] < pair[1]));
+
+            let decoded = MultiProof::read_from(&mut multi.to_vec().as_slice()).unwrap();
+            assert_eq!(decoded, multi);
+        }
+
+        // Siblings are deduplicated
+        let proofs: Vec<_> = (0..8).map(|i| prove(&tree, i)).collect();
+        let multi = MultiProof::from_proofs(&proofs).unwrap();
+        assert_eq!(multi.siblings.len(), TREE_DEPTH - 3);
+    }
+
+    #[test]
+    fn it_rejects_bad_proofs() {
+        let tree = tree(4);
+        let root = tree.hash();
+        let proofs = [prove(&tree, 1), prove(&tree, 2)];
+        let multi = MultiProof::from_proofs(&proofs).unwrap();
+
+        let mut bad = multi.clone();
+        bad.leaves[0] = H256::repeat_byte(1);
+        assert!(!bad.verify(root));
+
+        let mut bad = multi.clone();
+        bad.indices.reverse();
+        assert_eq!(bad.root(), Err(MultiProofError::UnsortedIndices));
+
+        let mut bad = multi.clone();
+        bad.siblings.pop();
+        assert_eq!(bad.root(), Err(MultiProofError::MissingSiblings));
+
+        let mut bad = multi;
+        bad.siblings.push(H256::zero());
+        assert_eq!(bad.root(), Err(MultiProofError::ExtraSiblings));
+
+        let other = MerkleTree::create(&[H256::zero()], TREE_DEPTH);
+        assert_eq!(
+            MultiProof::from_proofs(&[prove(&tree, 0), prove(&other, 0)]),
+            Err(MultiProofError::MismatchedRoots)
+        );
+    }
+
+    #[test]
+    fn it_verifies_the_test_vector() {
+        let vector = test_utils::load_multi_proof_test_json();
+        assert!(vector.proof.verify(vector.root));
+        assert_eq!(vector.proof.to_vec(), vector.encoded);
+    }
+}
@@ -0,0 +1,166 @@
+//! The subset of `std::io` the encodings use, for builds without `std`
+
+use alloc::vec::Vec;
+use core::{cmp, fmt};
+
+/// The kind of an I/O error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The data read was not valid
+    InvalidData,
+    /// The value to write was not valid
+    InvalidInput,
+    /// The reader ran out of bytes
+    UnexpectedEof,
+    /// The writer stopped accepting bytes
+    WriteZero,
+}
+
+/// An I/O error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Error {
+    kind: ErrorKind,
+    message: &'static str,
+}
+
+impl Error {
+    /// Instantiate a new error of `kind`
+    pub fn new(kind: ErrorKind, message: &'static str) -> Self {
+        Self { kind, message }
+    }
+
+    /// The kind of the error
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}", self.kind, self.message)
+    }
+}
+
+/// The result of an I/O operation
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// A source of bytes
+pub trait Read {
+    /// Read some bytes into `buf`. Returns the number read, 0 once
+    /// exhausted
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+    /// Fill `buf`, or fail if the source runs out first
+    fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.read(buf)? {
+                0 => {
+                    return Err(Error::new(
+                        ErrorKind::UnexpectedEof,
+                        "failed to fill whole buffer",
+                    ))
+                }
+                n => buf = &mut buf[n..],
+            }
+        }
+        Ok(())
+    }
+
+    /// Read every remaining byte onto the end of `buf`
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> Result<usize> {
+        let start = buf.len();
+        let mut chunk = [0u8; 256];
+        loop {
+            match self.read(&mut chunk)? {
+                0 => return Ok(buf.len() - start),
+                n => buf.extend_from_slice(&chunk[..n]),
+            }
+        }
+    }
+
+    /// Borrow the reader
+    fn by_ref(&mut self) -> &mut Self
+    where
+        Self: Sized,
+    {
+        self
+    }
+
+    /// A reader of at most `limit` bytes from this one
+    fn take(self, limit: u64) -> Take<Self>
+    where
+        Self: Sized,
+    {
+        Take { inner: self, limit }
+    }
+}
+
+impl<R: Read + ?Sized> Read for &mut R {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        (**self).read(buf)
+    }
+}
+
+impl Read for &[u8] {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let n = cmp::min(buf.len(), self.len());
+        let (read, rest) = self.split_at(n);
+        buf[..n].copy_from_slice(read);
+        *self = rest;
+        Ok(n)
+    }
+}
+
+/// A reader limited to a number of bytes. See `Read::take`
+#[derive(Debug)]
+pub struct Take<R> {
+    inner: R,
+    limit: u64,
+}
+
+impl<R: Read> Read for Take<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let max = cmp::min(buf.len() as u64, self.limit) as usize;
+        if max == 0 {
+            return Ok(0);
+        }
+        let n = self.inner.read(&mut buf[..max])?;
+        self.limit -= n as u64;
+        Ok(n)
+    }
+}
+
+/// A sink for bytes
+pub trait Write {
+    /// Write some of `buf`. Returns the number written
+    fn write(&mut self, buf: &[u8]) -> Result<usize>;
+
+    /// Write all of `buf`
+    fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.write(buf)? {
+                0 => {
+                    return Err(Error::new(
+                        ErrorKind::WriteZero,
+                        "failed to write whole buffer",
+                    ))
+                }
+                n => buf = &buf[n..],
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write + ?Sized> Write for &mut W {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        (**self).write(buf)
+    }
+}
+
+impl Write for Vec<u8> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+}
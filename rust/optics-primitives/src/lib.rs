@@ -0,0 +1,51 @@
+//! Optics primitives.
+//!
+//! Message and update encoding, signing hashes, and merkle accumulators.
+//! These have no dependency on a chain, DB or runtime, and build without
+//! `std` (disable default features) and for `wasm32-unknown-unknown`.
+//!
+//! Most users should depend on `optics-core`, which re-exports this crate.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+#![warn(missing_docs)]
+#![warn(unused_extern_crates)]
+#![forbid(unsafe_code)]
+#![forbid(where_clauses_object_safety)]
+
+extern crate alloc;
+
+/// `std::io`, or a minimal equivalent when built without `std`
+#[cfg(not(feature = "std"))]
+pub mod io;
+/// `std::io`, or a minimal equivalent when built without `std`
+#[cfg(feature = "std")]
+pub mod io {
+    pub use std::io::*;
+}
+
+/// Accumulator management
+pub mod accumulator;
+
+/// Canonical encoding
+mod encode;
+pub use encode::*;
+
+/// Optics messages
+mod message;
+pub use message::*;
+
+/// Updater attestations
+mod update;
+pub use update::*;
+
+/// Utilities to match contract values
+pub mod utils;
+
+/// Typed bodies of the bridge and governance xApp messages
+pub mod xapps;
+
+/// Testing utilities
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+
+pub use primitive_types::{H256, U256};
@@ -0,0 +1,276 @@
+use alloc::{vec, vec::Vec};
+use core::convert::TryFrom;
+use primitive_types::H256;
+
+use crate::{accumulator::hash, io, utils, Decode, Encode};
+
+/// Length of the fixed-size fields that precede an encoded message's body
+pub const OPTICS_MESSAGE_PREFIX_LEN: usize = 76;
+
+/// A full Optics message between chains
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct OpticsMessage {
+    /// 4   SLIP-44 ID
+    pub origin: u32,
+    /// 32  Address in home convention
+    pub sender: H256,
+    /// 4   Count of all previous messages to destination
+    pub nonce: u32,
+    /// 4   SLIP-44 ID
+    pub destination: u32,
+    /// 32  Address in destination convention
+    pub recipient: H256,
+    /// 0+  Message contents
+    pub body: Vec<u8>,
+}
+
+/// A partial Optics message between chains
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Message {
+    /// 4   SLIP-44 ID
+    pub destination: u32,
+    /// 32  Address in destination convention
+    pub recipient: H256,
+    /// 0+  Message contents
+    pub body: Vec<u8>,
+}
+
+impl Encode for OpticsMessage {
+    fn write_to<W>(&self, writer: &mut W) -> io::Result<usize>
+    where
+        W: io::Write,
+    {
+        writer.write_all(&self.origin.to_be_bytes())?;
+        writer.write_all(self.sender.as_ref())?;
+        writer.write_all(&self.nonce.to_be_bytes())?;
+        writer.write_all(&self.destination.to_be_bytes())?;
+        writer.write_all(self.recipient.as_ref())?;
+        writer.write_all(&self.body)?;
+        Ok(OPTICS_MESSAGE_PREFIX_LEN + self.body.len())
+    }
+}
+
+impl Decode for OpticsMessage {
+    fn read_from<R>(reader: &mut R) -> io::Result<Self>
+    where
+        R: io::Read,
+    {
+        let mut origin = [0u8; 4];
+        reader.read_exact(&mut origin)?;
+
+        let mut sender = H256::zero();
+        reader.read_exact(sender.as_mut())?;
+
+        let mut nonce = [0u8; 4];
+        reader.read_exact(&mut nonce)?;
+
+        let mut destination = [0u8; 4];
+        reader.read_exact(&mut destination)?;
+
+        let mut recipient = H256::zero();
+        reader.read_exact(recipient.as_mut())?;
+
+        let mut body = vec![];
+        reader.read_to_end(&mut body)?;
+
+        Ok(Self {
+            origin: u32::from_be_bytes(origin),
+            sender,
+            destination: u32::from_be_bytes(destination),
+            recipient,
+            nonce: u32::from_be_bytes(nonce),
+            body,
+        })
+    }
+}
+
+impl OpticsMessage {
+    /// Convert the message to a leaf
+    pub fn to_leaf(&self) -> H256 {
+        hash(self.to_vec())
+    }
+
+    /// Get the encoded destination + nonce
+    pub fn destination_and_nonce(&self) -> u64 {
+        utils::destination_and_nonce(self.destination, self.nonce)
+    }
+}
+
+impl core::fmt::Display for OpticsMessage {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "OpticsMessage {}->{}:{}",
+            self.origin, self.destination, self.nonce,
+        )
+    }
+}
+
+/// Error type for message views
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "std", derive(thiserror::Error))]
+pub enum OpticsMessageViewError {
+    /// Fewer bytes than the fixed-size prefix
+    #[cfg_attr(
+        feature = "std",
+        error("Message is {0} bytes, shorter than its 76-byte prefix")
+    )]
+    TooShort(usize),
+}
+
+/// A borrowed view of an encoded Optics message.
+///
+/// The length of the fixed-size prefix is checked once, on creation. Fields
+/// are read from the underlying bytes as they are accessed, and the body is
+/// never copied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpticsMessageView<'a>(&'a [u8]);
+
+impl<'a> OpticsMessageView<'a> {
+    /// Create a view over an encoded message
+    pub fn new(buf: &'a [u8]) -> Result<Self, OpticsMessageViewError> {
+        if buf.len() < OPTICS_MESSAGE_PREFIX_LEN {
+            return Err(OpticsMessageViewError::TooShort(buf.len()));
+        }
+        Ok(Self(buf))
+    }
+
+    fn u32_at(&self, offset: usize) -> u32 {
+        let mut buf = [0u8; 4];
+        buf.copy_from_slice(&self.0[offset..offset + 4]);
+        u32::from_be_bytes(buf)
+    }
+
+    fn h256_at(&self, offset: usize) -> H256 {
+        H256::from_slice(&self.0[offset..offset + 32])
+    }
+
+    /// SLIP-44 ID of the origin
+    pub fn origin(&self) -> u32 {
+        self.u32_at(0)
+    }
+
+    /// Address in home convention
+    pub fn sender(&self) -> H256 {
+        self.h256_at(4)
+    }
+
+    /// Count of all previous messages to destination
+    pub fn nonce(&self) -> u32 {
+        self.u32_at(36)
+    }
+
+    /// SLIP-44 ID of the destination
+    pub fn destination(&self) -> u32 {
+        self.u32_at(40)
+    }
+
+    /// Address in destination convention
+    pub fn recipient(&self) -> H256 {
+        self.h256_at(44)
+    }
+
+    /// Message contents
+    pub fn body(&self) -> &'a [u8] {
+        &self.0[OPTICS_MESSAGE_PREFIX_LEN..]
+    }
+
+    /// The encoded message
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.0
+    }
+
+    /// Convert the message to a leaf
+    pub fn to_leaf(&self) -> H256 {
+        hash(self.0)
+    }
+
+    /// Get the encoded destination + nonce
+    pub fn destination_and_nonce(&self) -> u64 {
+        utils::destination_and_nonce(self.destination(), self.nonce())
+    }
+
+    /// Copy the message into an owned `OpticsMessage`
+    pub fn to_message(&self) -> OpticsMessage {
+        OpticsMessage {
+            origin: self.origin(),
+            sender: self.sender(),
+            nonce: self.nonce(),
+            destination: self.destination(),
+            recipient: self.recipient(),
+            body: self.body().to_vec(),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for OpticsMessageView<'a> {
+    type Error = OpticsMessageViewError;
+
+    fn try_from(buf: &'a [u8]) -> Result<Self, Self::Error> {
+        Self::new(buf)
+    }
+}
+
+impl From<OpticsMessageView<'_>> for OpticsMessage {
+    fn from(view: OpticsMessageView<'_>) -> Self {
+        view.to_message()
+    }
+}
+
+impl core::fmt::Display for OpticsMessageView<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "OpticsMessage {}->{}:{}",
+            self.origin(),
+            self.destination(),
+            self.nonce(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils;
+
+    #[test]
+    fn it_matches_the_message_test_vectors() {
+        for test_case in test_utils::load_message_test_json() {
+            let message = OpticsMessage {
+                origin: test_case.origin,
+                sender: test_case.sender,
+                nonce: test_case.nonce,
+                destination: test_case.destination,
+                recipient: test_case.recipient,
+                body: test_case.body,
+            };
+            assert_eq!(message.to_leaf(), test_case.message_hash);
+
+            let encoded = message.to_vec();
+            let decoded = OpticsMessage::read_from(&mut encoded.as_slice()).unwrap();
+            assert_eq!(decoded, message);
+
+            let view = OpticsMessageView::new(&encoded).unwrap();
+            assert_eq!(view.to_leaf(), test_case.message_hash);
+            assert_eq!(view.sender(), message.sender);
+            assert_eq!(
+                view.destination_and_nonce(),
+                message.destination_and_nonce()
+            );
+            assert_eq!(view.body(), message.body.as_slice());
+            assert_eq!(view.to_message(), message);
+        }
+    }
+
+    #[test]
+    fn it_rejects_truncated_views() {
+        let encoded = OpticsMessage::default().to_vec();
+        assert_eq!(encoded.len(), OPTICS_MESSAGE_PREFIX_LEN);
+        assert!(OpticsMessageView::new(&encoded).unwrap().body().is_empty());
+        assert_eq!(
+            OpticsMessageView::new(&encoded[..75]),
+            Err(OpticsMessageViewError::TooShort(75))
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use crate::{
+    accumulator::{merkle::Proof, multiproof::MultiProof},
+    xapps::{bridge::BridgeMessage, governance::GovernanceMessage},
+};
+use primitive_types::H256;
+use serde::de::DeserializeOwned;
+use std::{fs::File, io::Read, path::PathBuf};
+
+/// Struct representing a single merkle test case
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MerkleTestCase {
+    /// Test case name
+    pub test_name: String,
+    /// Leaves of merkle tree
+    pub leaves: Vec<String>,
+    /// Proofs for leaves in tree
+    pub proofs: Vec<Proof>,
+    /// Root of tree
+    pub expected_root: H256,
+}
+
+/// Struct representing the multi-leaf proof test case
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct MultiProofTestCase {
+    /// The proof
+    pub proof: MultiProof,
+    /// Root the proof is under
+    pub root: H256,
+    /// The encoded proof
+    #[serde(with = "hex_bytes")]
+    pub encoded: Vec<u8>,
+}
+
+/// Struct representing a single message test case
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageTestCase {
+    /// SLIP-44 ID of the origin
+    pub origin: u32,
+    /// Sender address
+    pub sender: H256,
+    /// Count of all previous messages to destination
+    pub nonce: u32,
+    /// SLIP-44 ID of the destination
+    pub destination: u32,
+    /// Recipient address
+    pub recipient: H256,
+    /// Message contents
+    pub body: Vec<u8>,
+    /// Leaf of the message
+    pub message_hash: H256,
+}
+
+/// Struct representing the single-leaf proof test case
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct ProofTestCase {
+    /// The proof
+    pub proof: Proof,
+    /// Root the proof is under
+    pub root: H256,
+}
+
+/// Struct representing a single home domain hash test case
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HomeDomainHashTestCase {
+    /// The home domain
+    pub home_domain: u32,
+    /// Its hash
+    pub expected_domain_hash: H256,
+}
+
+/// Struct representing a single destination and nonce test case
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DestinationNonceTestCase {
+    /// The destination
+    pub destination: u32,
+    /// The nonce
+    pub nonce: u32,
+    /// The combined destination and nonce
+    pub expected_destination_and_nonce: u64,
+}
+
+/// Struct representing a single bridge message test case
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BridgeMessageTestCase {
+    /// Test case name
+    pub test_name: String,
+    /// The message
+    pub message: BridgeMessage,
+    /// The encoded token id
+    #[serde(with = "hex_bytes")]
+    pub token_id: Vec<u8>,
+    /// The encoded action
+    #[serde(with = "hex_bytes")]
+    pub action: Vec<u8>,
+    /// The encoded message
+    #[serde(with = "hex_bytes")]
+    pub encoded: Vec<u8>,
+}
+
+/// Struct representing a single governance message test case
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GovernanceMessageTestCase {
+    /// Test case name
+    pub test_name: String,
+    /// The message
+    pub message: GovernanceMessage,
+    /// The encoded message
+    #[serde(with = "hex_bytes")]
+    pub encoded: Vec<u8>,
+}
+
+/// Serde for 0x-prefixed hex strings
+mod hex_bytes {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("0x{}", hex::encode(bytes)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        hex::decode(s.strip_prefix("0x").unwrap_or(&s)).map_err(D::Error::custom)
+    }
+}
+
+/// Find a vector file assuming that a git checkout exists
+// TODO: look instead for the workspace `Cargo.toml`? use a cargo env var?
+pub fn find_vector(final_component: &str) -> PathBuf {
+    let cwd = std::env::current_dir().expect("no cwd?");
+    let git_dir = cwd
+        .ancestors() // . ; ../ ; ../../ ; ...
+        .find(|d| d.join(".git").is_dir())
+        .expect("could not find .git somewhere! confused about workspace layout");
+
+    git_dir.join("vectors").join(final_component)
+}
+
+fn load_vector<T: DeserializeOwned>(final_component: &str) -> T {
+    let mut file = File::open(find_vector(final_component)).unwrap();
+    let mut data = String::new();
+    file.read_to_string(&mut data).unwrap();
+    serde_json::from_str(&data).unwrap()
+}
+
+/// Reads merkle test case json file and returns a vector of `MerkleTestCase`s
+pub fn load_merkle_test_json() -> Vec<MerkleTestCase> {
+    load_vector("merkle.json")
+}
+
+/// Reads the multi-leaf proof test case json file
+pub fn load_multi_proof_test_json() -> MultiProofTestCase {
+    load_vector("multiProof.json")
+}
+
+/// Reads the message test case json file
+pub fn load_message_test_json() -> Vec<MessageTestCase> {
+    load_vector("message.json")
+}
+
+/// Reads the single-leaf proof test case json file
+pub fn load_proof_test_json() -> ProofTestCase {
+    load_vector("proof.json")
+}
+
+/// Reads the home domain hash test case json file
+pub fn load_home_domain_hash_test_json() -> Vec<HomeDomainHashTestCase> {
+    load_vector("homeDomainHash.json")
+}
+
+/// Reads the destination and nonce test case json file
+pub fn load_destination_nonce_test_json() -> Vec<DestinationNonceTestCase> {
+    load_vector("destinationNonce.json")
+}
+
+/// Reads the bridge message test case json file
+pub fn load_bridge_message_test_json() -> Vec<BridgeMessageTestCase> {
+    load_vector("bridgeMessage.json")
+}
+
+/// Reads the governance message test case json file
+pub fn load_governance_message_test_json() -> Vec<GovernanceMessageTestCase> {
+    load_vector("governanceMessage.json")
+}
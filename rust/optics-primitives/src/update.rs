@@ -0,0 +1,87 @@
+use primitive_types::H256;
+use sha3::{Digest, Keccak256};
+
+use crate::{
+    io,
+    utils::{hash_message, home_domain_hash},
+    Decode, Encode,
+};
+
+/// An Optics update message
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Update {
+    /// The home chain
+    pub home_domain: u32,
+    /// The previous root
+    pub previous_root: H256,
+    /// The new root
+    pub new_root: H256,
+}
+
+impl core::fmt::Display for Update {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "Update(domain {} moved from {} to {})",
+            self.home_domain, self.previous_root, self.new_root
+        )
+    }
+}
+
+impl Encode for Update {
+    fn write_to<W>(&self, writer: &mut W) -> io::Result<usize>
+    where
+        W: io::Write,
+    {
+        writer.write_all(&self.home_domain.to_be_bytes())?;
+        writer.write_all(self.previous_root.as_ref())?;
+        writer.write_all(self.new_root.as_ref())?;
+        Ok(4 + 32 + 32)
+    }
+}
+
+impl Decode for Update {
+    fn read_from<R>(reader: &mut R) -> io::Result<Self>
+    where
+        R: io::Read,
+        Self: Sized,
+    {
+        let mut home_domain = [0u8; 4];
+        reader.read_exact(&mut home_domain)?;
+
+        let mut previous_root = H256::zero();
+        reader.read_exact(previous_root.as_mut())?;
+
+        let mut new_root = H256::zero();
+        reader.read_exact(new_root.as_mut())?;
+
+        Ok(Self {
+            home_domain: u32::from_be_bytes(home_domain),
+            previous_root,
+            new_root,
+        })
+    }
+}
+
+impl Update {
+    /// The hash the updater signs
+    pub fn signing_hash(&self) -> H256 {
+        // sign:
+        // domain(home_domain) || previous_root || new_root
+        H256::from_slice(
+            Keccak256::new()
+                .chain(home_domain_hash(self.home_domain))
+                .chain(self.previous_root)
+                .chain(self.new_root)
+                .finalize()
+                .as_slice(),
+        )
+    }
+
+    /// The signing hash, prefixed as Ethereum signers do. Signatures over the
+    /// update recover against this hash
+    pub fn prepended_hash(&self) -> H256 {
+        hash_message(self.signing_hash())
+    }
+}
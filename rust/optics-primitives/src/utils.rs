@@ -0,0 +1,62 @@
+use alloc::string::ToString;
+use primitive_types::H256;
+use sha3::{Digest, Keccak256};
+
+/// Computes hash of home domain concatenated with "OPTICS"
+pub fn home_domain_hash(home_domain: u32) -> H256 {
+    H256::from_slice(
+        Keccak256::new()
+            .chain(home_domain.to_be_bytes())
+            .chain("OPTICS".as_bytes())
+            .finalize()
+            .as_slice(),
+    )
+}
+
+/// Destination and destination-specific nonce combined in single field (
+/// (destination << 32) & nonce)
+pub fn destination_and_nonce(destination: u32, nonce: u32) -> u64 {
+    assert!(destination < u32::MAX);
+    assert!(nonce < u32::MAX);
+    ((destination as u64) << 32) | nonce as u64
+}
+
+/// Hash a message according to EIP-191, as Ethereum signers do before
+/// signing it
+pub fn hash_message(message: impl AsRef<[u8]>) -> H256 {
+    let message = message.as_ref();
+    H256::from_slice(
+        Keccak256::new()
+            .chain("\x19Ethereum Signed Message:\n".as_bytes())
+            .chain(message.len().to_string().as_bytes())
+            .chain(message)
+            .finalize()
+            .as_slice(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils;
+
+    #[test]
+    fn it_matches_the_home_domain_hash_test_vectors() {
+        for test_case in test_utils::load_home_domain_hash_test_json() {
+            assert_eq!(
+                home_domain_hash(test_case.home_domain),
+                test_case.expected_domain_hash
+            );
+        }
+    }
+
+    #[test]
+    fn it_matches_the_destination_and_nonce_test_vectors() {
+        for test_case in test_utils::load_destination_nonce_test_json() {
+            assert_eq!(
+                destination_and_nonce(test_case.destination, test_case.nonce),
+                test_case.expected_destination_and_nonce
+            );
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use primitive_types::{H160, H256, U256};
+
+use crate::{
+    io,
+    xapps::{ensure_exhausted, invalid},
+    Decode, Encode,
+};
+
+/// Message type identifiers, from `BridgeMessage.Types`. Only actions carry
+/// their identifier in the encoded message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum BridgeMessageType {
+    /// Invalid
+    Invalid = 0,
+    /// A token identifier
+    TokenId = 1,
+    /// A token identifier followed by an action
+    Message = 2,
+    /// Transfer action
+    Transfer = 3,
+    /// Details action
+    Details = 4,
+    /// Request details action
+    RequestDetails = 5,
+}
+
+/// The canonical identifier of a token: the domain it originates on, and its
+/// address there
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct TokenId {
+    /// 4   SLIP-44 ID of the token's home domain
+    pub domain: u32,
+    /// 32  Token address on its home domain
+    pub id: H256,
+}
+
+impl TokenId {
+    /// The token address, for tokens originating on EVM chains
+    pub fn evm_id(&self) -> H160 {
+        H160::from_slice(&self.id[12..])
+    }
+}
+
+/// An action the receiving `BridgeRouter` takes for a token
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "camelCase"))]
+pub enum BridgeAction {
+    /// Mint or release tokens to the recipient
+    Transfer {
+        /// 32  Recipient address in destination convention
+        recipient: H256,
+        /// 32  Amount of tokens
+        amount: U256,
+    },
+    /// Set the details of the token's representation
+    Details {
+        /// 32  Token name
+        name: H256,
+        /// 32  Token symbol
+        symbol: H256,
+        /// 1   Token decimals
+        decimals: u8,
+    },
+    /// Ask the token's home domain to send its details
+    RequestDetails,
+}
+
+impl BridgeAction {
+    /// The identifier that prefixes the encoded action
+    pub fn message_type(&self) -> BridgeMessageType {
+        match self {
+            BridgeAction::Transfer { .. } => BridgeMessageType::Transfer,
+            BridgeAction::Details { .. } => BridgeMessageType::Details,
+            BridgeAction::RequestDetails => BridgeMessageType::RequestDetails,
+        }
+    }
+}
+
+/// The body of a message between `BridgeRouter`s
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct BridgeMessage {
+    /// 36  The token the action applies to
+    pub token_id: TokenId,
+    /// 1+  The action
+    pub action: BridgeAction,
+}
+
+impl Encode for TokenId {
+    fn write_to<W>(&self, writer: &mut W) -> io::Result<usize>
+    where
+        W: io::Write,
+    {
+        let mut written = 0;
+        written += self.domain.write_to(writer)?;
+        written += self.id.write_to(writer)?;
+        Ok(written)
+    }
+}
+
+impl Decode for TokenId {
+    fn read_from<R>(reader: &mut R) -> io::Result<Self>
+    where
+        R: io::Read,
+        Self: Sized,
+    {
+        Ok(Self {
+            domain: u32::read_from(reader)?,
+            id: H256::read_from(reader)?,
+        })
+    }
+}
+
+impl Encode for BridgeAction {
+    fn write_to<W>(&self, writer: &mut W) -> io::Result<usize>
+    where
+        W: io::Write,
+    {
+        let mut written = (self.message_type() as u8).write_to(writer)?;
+        match self {
+            BridgeAction::Transfer { recipient, amount } => {
+                written += recipient.write_to(writer)?;
+                written += amount.write_to(writer)?;
+            }
+            BridgeAction::Details {
+                name,
+                symbol,
+                decimals,
+            } => {
+                written += name.write_to(writer)?;
+                written += symbol.write_to(writer)?;
+                written += decimals.write_to(writer)?;
+            }
+            BridgeAction::RequestDetails => {}
+        }
+        Ok(written)
+    }
+}
+
+impl Decode for BridgeAction {
+    fn read_from<R>(reader: &mut R) -> io::Result<Self>
+    where
+        R: io::Read,
+        Self: Sized,
+    {
+        let message_type = u8::read_from(reader)?;
+        match message_type {
+            t if t == BridgeMessageType::Transfer as u8 => Ok(BridgeAction::Transfer {
+                recipient: H256::read_from(reader)?,
+                amount: U256::read_from(reader)?,
+            }),
+            t if t == BridgeMessageType::Details as u8 => Ok(BridgeAction::Details {
+                name: H256::read_from(reader)?,
+                symbol: H256::read_from(reader)?,
+                decimals: u8::read_from(reader)?,
+            }),
+            t if t == BridgeMessageType::RequestDetails as u8 => Ok(BridgeAction::RequestDetails),
+            _ => Err(invalid("unknown bridge action type")),
+        }
+    }
+}
+
+impl Encode for BridgeMessage {
+    fn write_to<W>(&self, writer: &mut W) -> io::Result<usize>
+    where
+        W: io::Write,
+    {
+        let mut written = 0;
+        written += self.token_id.write_to(writer)?;
+        written += self.action.write_to(writer)?;
+        Ok(written)
+    }
+}
+
+impl Decode for BridgeMessage {
+    /// Read a message from the rest of the reader. Like
+    /// `BridgeMessage.tryAsMessage`, rejects bodies with bytes after the
+    /// action
+    fn read_from<R>(reader: &mut R) -> io::Result<Self>
+    where
+        R: io::Read,
+        Self: Sized,
+    {
+        let token_id = TokenId::read_from(reader)?;
+        let action = BridgeAction::read_from(reader)?;
+        ensure_exhausted(reader)?;
+        Ok(Self { token_id, action })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils;
+
+    #[test]
+    fn it_matches_the_bridge_message_test_vectors() {
+        for test_case in test_utils::load_bridge_message_test_json() {
+            let message = test_case.message;
+            assert_eq!(message.token_id.to_vec(), test_case.token_id);
+            assert_eq!(message.action.to_vec(), test_case.action);
+            assert_eq!(message.to_vec(), test_case.encoded);
+
+            let decoded = BridgeMessage::read_from(&mut test_case.encoded.as_slice()).unwrap();
+            assert_eq!(decoded, message);
+        }
+    }
+
+    #[test]
+    fn it_rejects_malformed_messages() {
+        let message = BridgeMessage {
+            token_id: TokenId {
+                domain: 1,
+                id: H256::repeat_byte(0x11),
+            },
+            action: BridgeAction::Transfer {
+                recipient: H256::repeat_byte(0x22),
+                amount: 0xffff.into(),
+            },
+        };
+        let encoded = message.to_vec();
+        assert_eq!(encoded.len(), 36 + 65);
+
+        // truncated action
+        assert!(BridgeMessage::read_from(&mut &encoded[..100]).is_err());
+
+        // trailing bytes
+        let mut extended = encoded.clone();
+        extended.push(0);
+        assert!(BridgeMessage::read_from(&mut extended.as_slice()).is_err());
+
+        // unknown action type
+        let mut retyped = encoded;
+        retyped[36] = BridgeMessageType::Message as u8;
+        assert!(BridgeMessage::read_from(&mut retyped.as_slice()).is_err());
+    }
+
+    #[test]
+    fn it_trims_evm_ids() {
+        let token_id = TokenId {
+            domain: 1,
+            id: H256::from(H160::repeat_byte(0x33)),
+        };
+        assert_eq!(token_id.evm_id(), H160::repeat_byte(0x33));
+    }
+}
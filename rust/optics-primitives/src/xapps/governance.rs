@@ -0,0 +1,251 @@
+use alloc::{vec, vec::Vec};
+use primitive_types::{H256, U256};
+
+use crate::{
+    io::{self, Read},
+    xapps::{ensure_exhausted, invalid},
+    Decode, Encode,
+};
+
+/// Message type identifiers, from `GovernanceMessage.Types`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum GovernanceMessageType {
+    /// Invalid
+    Invalid = 0,
+    /// A batch of calls
+    Call = 1,
+    /// Transfer governorship
+    TransferGovernor = 2,
+    /// Set the router for a domain
+    SetRouter = 3,
+    /// Call data
+    Data = 4,
+}
+
+/// A call for the receiving `GovernanceRouter` to make
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct Call {
+    /// 32  Address to call
+    pub to: H256,
+    /// 32+ Call data, prefixed by its length as a uint256
+    pub data: Vec<u8>,
+}
+
+/// The body of a message between `GovernanceRouter`s
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "camelCase"))]
+pub enum GovernanceMessage {
+    /// Make a batch of calls. Encoded with a 1-byte count, so a batch holds
+    /// at most 255 calls
+    Calls {
+        /// The calls, in order
+        calls: Vec<Call>,
+    },
+    /// Transfer governorship to a new governor
+    TransferGovernor {
+        /// 4   SLIP-44 ID of the new governor's domain
+        domain: u32,
+        /// 32  Address of the new governor
+        governor: H256,
+    },
+    /// Set the router for a domain
+    SetRouter {
+        /// 4   SLIP-44 ID of the router's domain
+        domain: u32,
+        /// 32  Address of the router
+        router: H256,
+    },
+}
+
+impl GovernanceMessage {
+    /// The identifier that prefixes the encoded message
+    pub fn message_type(&self) -> GovernanceMessageType {
+        match self {
+            GovernanceMessage::Calls { .. } => GovernanceMessageType::Call,
+            GovernanceMessage::TransferGovernor { .. } => GovernanceMessageType::TransferGovernor,
+            GovernanceMessage::SetRouter { .. } => GovernanceMessageType::SetRouter,
+        }
+    }
+}
+
+impl Encode for Call {
+    fn write_to<W>(&self, writer: &mut W) -> io::Result<usize>
+    where
+        W: io::Write,
+    {
+        let mut written = 0;
+        written += self.to.write_to(writer)?;
+        written += U256::from(self.data.len()).write_to(writer)?;
+        writer.write_all(&self.data)?;
+        Ok(written + self.data.len())
+    }
+}
+
+impl Decode for Call {
+    fn read_from<R>(reader: &mut R) -> io::Result<Self>
+    where
+        R: io::Read,
+        Self: Sized,
+    {
+        let to = H256::read_from(reader)?;
+
+        let len = U256::read_from(reader)?;
+        if len > U256::from(u32::MAX) {
+            return Err(invalid("call data length too large"));
+        }
+        let len = len.as_u64();
+
+        // Read through `take` so that a bad length can't force a large
+        // allocation up front
+        let mut data = vec![];
+        reader.by_ref().take(len).read_to_end(&mut data)?;
+        if data.len() as u64 != len {
+            return Err(invalid("call data shorter than its length"));
+        }
+
+        Ok(Self { to, data })
+    }
+}
+
+impl Encode for GovernanceMessage {
+    /// Write the message.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `InvalidInput` error for batches of more than 255 calls,
+    /// which the Solidity encoding can't represent
+    fn write_to<W>(&self, writer: &mut W) -> io::Result<usize>
+    where
+        W: io::Write,
+    {
+        let mut written = (self.message_type() as u8).write_to(writer)?;
+        match self {
+            GovernanceMessage::Calls { calls } => {
+                if calls.len() > u8::MAX as usize {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "more than 255 calls",
+                    ));
+                }
+                written += (calls.len() as u8).write_to(writer)?;
+                for call in calls.iter() {
+                    written += call.write_to(writer)?;
+                }
+            }
+            GovernanceMessage::TransferGovernor { domain, governor } => {
+                written += domain.write_to(writer)?;
+                written += governor.write_to(writer)?;
+            }
+            GovernanceMessage::SetRouter { domain, router } => {
+                written += domain.write_to(writer)?;
+                written += router.write_to(writer)?;
+            }
+        }
+        Ok(written)
+    }
+}
+
+impl Decode for GovernanceMessage {
+    /// Read a message from the rest of the reader. Rejects bodies with bytes
+    /// after the message, and empty call batches, which
+    /// `GovernanceMessage.isValidCall` rejects as too short
+    fn read_from<R>(reader: &mut R) -> io::Result<Self>
+    where
+        R: io::Read,
+        Self: Sized,
+    {
+        let message_type = u8::read_from(reader)?;
+        let message = match message_type {
+            t if t == GovernanceMessageType::Call as u8 => {
+                let num_calls = u8::read_from(reader)?;
+                if num_calls == 0 {
+                    return Err(invalid("empty call batch"));
+                }
+                let mut calls = Vec::with_capacity(num_calls as usize);
+                for _ in 0..num_calls {
+                    calls.push(Call::read_from(reader)?);
+                }
+                GovernanceMessage::Calls { calls }
+            }
+            t if t == GovernanceMessageType::TransferGovernor as u8 => {
+                GovernanceMessage::TransferGovernor {
+                    domain: u32::read_from(reader)?,
+                    governor: H256::read_from(reader)?,
+                }
+            }
+            t if t == GovernanceMessageType::SetRouter as u8 => GovernanceMessage::SetRouter {
+                domain: u32::read_from(reader)?,
+                router: H256::read_from(reader)?,
+            },
+            _ => return Err(invalid("unknown governance message type")),
+        };
+        ensure_exhausted(reader)?;
+        Ok(message)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils;
+
+    #[test]
+    fn it_matches_the_governance_message_test_vectors() {
+        for test_case in test_utils::load_governance_message_test_json() {
+            let message = test_case.message;
+            assert_eq!(message.to_vec(), test_case.encoded);
+
+            let decoded = GovernanceMessage::read_from(&mut test_case.encoded.as_slice()).unwrap();
+            assert_eq!(decoded, message);
+        }
+    }
+
+    #[test]
+    fn it_rejects_malformed_messages() {
+        let message = GovernanceMessage::Calls {
+            calls: vec![Call {
+                to: H256::repeat_byte(0x11),
+                data: vec![0x12, 0x34],
+            }],
+        };
+        let encoded = message.to_vec();
+        assert_eq!(encoded.len(), 2 + 64 + 2);
+
+        // truncated call data
+        assert!(GovernanceMessage::read_from(&mut &encoded[..67]).is_err());
+
+        // trailing bytes
+        let mut extended = encoded.clone();
+        extended.push(0);
+        assert!(GovernanceMessage::read_from(&mut extended.as_slice()).is_err());
+
+        // empty batch
+        let mut recounted = encoded.clone();
+        recounted[1] = 0;
+        assert!(GovernanceMessage::read_from(&mut recounted.as_slice()).is_err());
+
+        // more calls than the count
+        let mut doubled = encoded.clone();
+        doubled.extend_from_slice(&encoded[2..]);
+        assert!(GovernanceMessage::read_from(&mut doubled.as_slice()).is_err());
+        doubled[1] = 2;
+        assert!(GovernanceMessage::read_from(&mut doubled.as_slice()).is_ok());
+
+        // unknown message type
+        let mut retyped = encoded;
+        retyped[0] = GovernanceMessageType::Data as u8;
+        assert!(GovernanceMessage::read_from(&mut retyped.as_slice()).is_err());
+    }
+
+    #[test]
+    fn it_refuses_to_encode_oversized_batches() {
+        let message = GovernanceMessage::Calls {
+            calls: vec![Call::default(); 256],
+        };
+        let mut buf = vec![];
+        assert!(message.write_to(&mut buf).is_err());
+    }
+}
@@ -0,0 +1,24 @@
+/// Messages sent between `BridgeRouter`s
+pub mod bridge;
+/// Messages sent between `GovernanceRouter`s
+pub mod governance;
+
+use crate::io;
+
+/// Invalid data error, for bodies that the Solidity library would reject
+fn invalid(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// xApp bodies are the remainder of an Optics message, so a body with bytes
+/// left over after its last field is malformed
+fn ensure_exhausted<R>(reader: &mut R) -> io::Result<()>
+where
+    R: io::Read,
+{
+    let mut buf = [0u8; 1];
+    if reader.read(&mut buf)? != 0 {
+        return Err(invalid("trailing bytes after message"));
+    }
+    Ok(())
+}
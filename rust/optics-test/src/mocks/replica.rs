@@ -7,6 +7,8 @@ use ethers::core::types::H256;
 
 use optics_core::{accumulator::merkle::Proof, *};
 
+use tracing::{instrument::Instrumented, Instrument};
+
 mock! {
     pub ReplicaContract {
         // Replica
@@ -97,6 +99,16 @@ impl Replica for MockReplicaContract {
     async fn acceptable_root(&self, root: H256) -> Result<bool, ChainCommunicationError> {
         self._acceptable_root(root)
     }
+
+    fn index(
+        &self,
+        _from_height: u32,
+        _chunk_size: u32,
+        _confirmations: u32,
+        _indexed_height: prometheus::IntGauge,
+    ) -> Instrumented<tokio::task::JoinHandle<color_eyre::Result<()>>> {
+        tokio::spawn(async move { Ok(()) }).in_current_span()
+    }
 }
 
 #[async_trait]
@@ -1,5 +1,6 @@
+use ethers::core::types::H256;
 use futures_util::FutureExt;
-use optics_core::db::DB;
+use optics_core::{db::DB, Encode, OpticsMessage, RawCommittedMessage};
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
 use std::{future::Future, panic};
@@ -14,7 +15,17 @@ pub fn setup_db(db_path: String) -> DB {
         .into()
 }
 
+/// Run `test` against an empty in-memory DB
 pub async fn run_test_db<T, Fut>(test: T)
+where
+    T: FnOnce(DB) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    test(DB::in_memory()).await
+}
+
+/// Run `test` against an empty RocksDB DB, destroyed afterwards
+pub async fn run_test_rocks_db<T, Fut>(test: T)
 where
     T: FnOnce(DB) -> Fut + panic::UnwindSafe,
     Fut: Future<Output = ()>,
@@ -37,14 +48,204 @@ where
     assert!(result.is_ok())
 }
 
+/// A committed message from domain 1000 to domain 2000, with leaf index
+/// `nonce` and a body unique to the nonce
+pub fn raw_message(nonce: u32) -> RawCommittedMessage {
+    RawCommittedMessage {
+        leaf_index: nonce,
+        committed_root: H256::zero(),
+        message: OpticsMessage {
+            origin: 1000,
+            sender: H256::from_low_u64_be(1),
+            nonce,
+            destination: 2000,
+            recipient: H256::from_low_u64_be(2),
+            body: vec![nonce as u8],
+        }
+        .to_vec(),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use ethers::types::H256;
+    use ethers::signers::{LocalWallet, Signer};
+    use ethers::types::{H256, U256};
     use optics_core::{
-        accumulator::merkle::Proof, db::HomeDB, Encode, OpticsMessage, RawCommittedMessage,
+        accumulator::{
+            incremental::IncrementalMerkle, merkle::Proof, persistent::PersistentMerkleError,
+        },
+        db::{
+            DbError, GasAccount, HomeDB, HomeEvent, ProcessedMessage, RelayedUpdate, ReplicaDB,
+            SigningDB, SigningError, SigningHistory, SkippedMessage, WriteBatch, SCHEMA_VERSION,
+        },
+        DoubleUpdate, Encode, OpticsMessage, RawCommittedMessage, SignUpdate, Update, UpdateMeta,
     };
 
+    fn check_store(db: DB) {
+        let mut batch = WriteBatch::default();
+        batch.put("a_1", [1]);
+        batch.put("a_2", [2]);
+        batch.put("b_1", [3]);
+        batch.delete("a_2");
+        db.write_batch(batch).unwrap();
+
+        db.store_encodable("a_", "3", &H256::repeat_byte(4))
+            .unwrap();
+        assert_eq!(
+            db.retrieve_decodable("a_", "3").unwrap(),
+            Some(H256::repeat_byte(4))
+        );
+        db.prefix_delete("a_", "3").unwrap();
+        assert_eq!(db.retrieve_decodable::<H256>("a_", "3").unwrap(), None);
+
+        let pairs: Vec<_> = db.prefix_iterator("a_").collect();
+        assert_eq!(pairs, vec![(b"a_1".to_vec(), vec![1])]);
+        let pairs: Vec<_> = db.prefix_iterator("b_").collect();
+        assert_eq!(pairs, vec![(b"b_1".to_vec(), vec![3])]);
+        assert_eq!(db.prefix_iterator("c_").count(), 0);
+    }
+
+    #[tokio::test]
+    async fn stores_behave_the_same() {
+        run_test_db(|db| async move { check_store(db) }).await;
+        run_test_rocks_db(|db| async move { check_store(db) }).await;
+    }
+
+    #[tokio::test]
+    async fn home_db_proves_under_past_roots() {
+        run_test_db(|db| async move {
+            let home_db = HomeDB::new(db, "home_1".to_owned());
+            let leaves: Vec<_> = (0..5u64).map(H256::from_low_u64_be).collect();
+
+            let mut tree = home_db.merkle().unwrap();
+            tree.ingest_all(leaves[..3].iter().copied()).unwrap();
+            let old_root = tree.root();
+            tree.ingest_all(leaves[3..].iter().copied()).unwrap();
+
+            let proof = home_db.prove_under_root(1, old_root).unwrap().unwrap();
+            assert_eq!(proof.leaf, leaves[1]);
+            assert_eq!(proof.root(), old_root);
+            assert_eq!(home_db.proof_under_root(1, old_root).unwrap(), Some(proof));
+
+            // Leaf 3 was ingested after the old root
+            assert_eq!(home_db.prove_under_root(3, old_root).unwrap(), None);
+            assert!(matches!(
+                home_db.prove_under_root(1, H256::repeat_byte(9)),
+                Err(PersistentMerkleError::UnknownRoot(_))
+            ));
+
+            // Proofs stored by leaf index are also stored by root
+            let latest = tree.prove(4).unwrap();
+            home_db.store_proof(4, &latest).unwrap();
+            assert_eq!(
+                home_db.proof_under_root(4, tree.root()).unwrap(),
+                Some(latest)
+            );
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn db_migrates_legacy_leaf_keys() {
+        run_test_db(|db| async move {
+            let leaf = H256::repeat_byte(1);
+            let mut legacy_index_key = b"home_1_leaf_".to_vec();
+            legacy_index_key.extend(3u32.to_vec());
+            let mut nonce_key = b"home_1_leaf_".to_vec();
+            nonce_key.extend(7u64.to_vec());
+            db.store_encodable("", &legacy_index_key, &leaf).unwrap();
+            db.store_encodable("", &nonce_key, &leaf).unwrap();
+            assert_eq!(db.schema_version().unwrap(), 0);
+
+            // A dry run reports the migration without writing
+            let reports = db.migrate(true).unwrap();
+            assert_eq!(reports.len(), 3);
+            assert_eq!(reports[0].writes, 2);
+            assert_eq!(db.schema_version().unwrap(), 0);
+
+            db.migrate(false).unwrap();
+            assert_eq!(db.schema_version().unwrap(), SCHEMA_VERSION);
+            assert_eq!(
+                db.retrieve_decodable::<H256>("", &legacy_index_key)
+                    .unwrap(),
+                None
+            );
+
+            let home_db = HomeDB::new(db.clone(), "home_1".to_owned());
+            assert_eq!(home_db.leaf_by_leaf_index(3).unwrap(), Some(leaf));
+            assert_eq!(home_db.leaf_by_nonce(0, 7).unwrap(), Some(leaf));
+            assert_eq!(home_db.leaf_iterator().collect::<Vec<_>>(), vec![leaf]);
+
+            // Migrating again is a no-op
+            assert!(db.migrate(false).unwrap().is_empty());
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn db_migration_builds_persistent_trees() {
+        run_test_db(|db| async move {
+            let home_db = HomeDB::new(db.clone(), "home_1".to_owned());
+            let mut tree = IncrementalMerkle::default();
+            for nonce in 0..3 {
+                let message = RawCommittedMessage {
+                    leaf_index: nonce,
+                    committed_root: tree.root(),
+                    message: OpticsMessage {
+                        origin: 1,
+                        sender: H256::from_low_u64_be(4),
+                        nonce,
+                        destination: 2,
+                        recipient: H256::from_low_u64_be(5),
+                        body: vec![1, 2, 3],
+                    }
+                    .to_vec(),
+                };
+                home_db.store_raw_committed_message(&message).unwrap();
+                tree.ingest(message.leaf());
+            }
+            // leaves after a gap are left for the prover to sync
+            let mut gap_key = b"home_1_leaf_index_".to_vec();
+            gap_key.extend(5u32.to_vec());
+            db.store_encodable("", &gap_key, &H256::repeat_byte(5))
+                .unwrap();
+
+            // a DB from before the persistent tree
+            db.store_encodable("", "optics_schema_version", &1u32)
+                .unwrap();
+            assert_eq!(home_db.merkle().unwrap().count(), 0);
+
+            let reports = db.migrate(false).unwrap();
+            assert_eq!(reports.len(), 2);
+            assert_eq!(reports[0].version, 2);
+
+            let merkle = home_db.merkle().unwrap();
+            assert_eq!(merkle.count(), 3);
+            assert_eq!(merkle.root(), tree.root());
+            assert_eq!(merkle.count_at_root(tree.root()).unwrap(), Some(3));
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn db_refuses_newer_schema() {
+        run_test_db(|db| async move {
+            // An empty DB is created at the current version
+            assert!(db.migrate(false).unwrap().is_empty());
+            assert_eq!(db.schema_version().unwrap(), SCHEMA_VERSION);
+
+            db.store_encodable("", "optics_schema_version", &(SCHEMA_VERSION + 1))
+                .unwrap();
+            assert!(matches!(
+                db.migrate(false),
+                Err(DbError::UnsupportedSchema { found, supported })
+                    if found == SCHEMA_VERSION + 1 && supported == SCHEMA_VERSION
+            ));
+        })
+        .await;
+    }
+
     #[tokio::test]
     async fn home_db_stores_and_retrieves_messages() {
         run_test_db(|db| async move {
@@ -86,6 +287,140 @@ mod test {
         .await;
     }
 
+    #[tokio::test]
+    async fn home_db_stores_and_lists_skipped_messages() {
+        run_test_db(|db| async move {
+            let home_db = HomeDB::new(db, "home_1".to_owned());
+
+            let skipped = |leaf_index: u32, deferred: bool| SkippedMessage {
+                leaf_index,
+                rule: format!("rule_{}", leaf_index),
+                deferred,
+                timestamp: 1_600_000_000,
+            };
+            home_db
+                .store_skipped_message(12, 3, &skipped(7, true))
+                .unwrap();
+            home_db
+                .store_skipped_message(12, 1, &skipped(5, false))
+                .unwrap();
+            home_db
+                .store_skipped_message(13, 2, &skipped(6, true))
+                .unwrap();
+
+            assert_eq!(
+                home_db.skipped_message(12, 3).unwrap(),
+                Some(skipped(7, true))
+            );
+            assert_eq!(
+                home_db.skipped_messages(12).unwrap(),
+                vec![(1, skipped(5, false)), (3, skipped(7, true))]
+            );
+
+            home_db.remove_skipped_message(12, 3).unwrap();
+            assert_eq!(home_db.skipped_message(12, 3).unwrap(), None);
+            assert_eq!(
+                home_db.skipped_messages(12).unwrap(),
+                vec![(1, skipped(5, false))]
+            );
+            assert_eq!(
+                home_db.skipped_messages(13).unwrap(),
+                vec![(2, skipped(6, true))]
+            );
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn home_db_tracks_pushed_leaf_index_per_sink() {
+        run_test_db(|db| async move {
+            let home_db = HomeDB::new(db, "home_1".to_owned());
+
+            assert_eq!(
+                home_db.retrieve_pushed_leaf_index("s3_bucket").unwrap(),
+                None
+            );
+            home_db.store_pushed_leaf_index("s3_bucket", 4).unwrap();
+            home_db.store_pushed_leaf_index("file_proofs", 2).unwrap();
+            home_db.store_pushed_leaf_index("s3_bucket", 5).unwrap();
+
+            assert_eq!(
+                home_db.retrieve_pushed_leaf_index("s3_bucket").unwrap(),
+                Some(5)
+            );
+            assert_eq!(
+                home_db.retrieve_pushed_leaf_index("file_proofs").unwrap(),
+                Some(2)
+            );
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn home_db_sums_gas_spent_over_a_window() {
+        run_test_db(|db| async move {
+            let home_db = HomeDB::new(db, "home_1".to_owned());
+            let sender = H256::repeat_byte(1);
+            let recipient = H256::repeat_byte(2);
+
+            for (nonce, timestamp) in [(0, 100), (1, 200), (2, 300)].iter() {
+                for (account, address) in [
+                    (GasAccount::Sender, sender),
+                    (GasAccount::Recipient, recipient),
+                ]
+                .iter()
+                {
+                    home_db
+                        .store_gas_spent(
+                            2000,
+                            *nonce,
+                            *account,
+                            *address,
+                            *timestamp,
+                            U256::from(1000 * (nonce + 1)),
+                        )
+                        .unwrap();
+                }
+            }
+            // other destinations and addresses are tracked separately
+            home_db
+                .store_gas_spent(3000, 0, GasAccount::Sender, sender, 300, 7.into())
+                .unwrap();
+            home_db
+                .store_gas_spent(2000, 3, GasAccount::Sender, recipient, 300, 7.into())
+                .unwrap();
+
+            let spent = |account, address, since| {
+                home_db
+                    .gas_spent_since(2000, account, address, since)
+                    .unwrap()
+            };
+            assert_eq!(spent(GasAccount::Sender, sender, 0), 6000.into());
+            assert_eq!(spent(GasAccount::Recipient, recipient, 0), 6000.into());
+            assert_eq!(spent(GasAccount::Sender, sender, 200), 5000.into());
+            assert_eq!(spent(GasAccount::Recipient, sender, 0), U256::zero());
+
+            // reading does not remove records
+            assert_eq!(spent(GasAccount::Sender, sender, 0), 6000.into());
+
+            // pruning removes the account's records before the cutoff, for
+            // every address
+            home_db
+                .prune_gas_spent_before(2000, GasAccount::Sender, 200)
+                .unwrap();
+            assert_eq!(spent(GasAccount::Sender, sender, 0), 5000.into());
+            assert_eq!(spent(GasAccount::Sender, recipient, 0), 7.into());
+            assert_eq!(spent(GasAccount::Recipient, recipient, 0), 6000.into());
+            assert_eq!(
+                home_db
+                    .gas_spent_since(3000, GasAccount::Sender, sender, 0)
+                    .unwrap(),
+                7.into()
+            );
+        })
+        .await;
+    }
+
     #[tokio::test]
     async fn home_db_stores_and_retrieves_proofs() {
         run_test_db(|db| async move {
@@ -103,4 +438,552 @@ mod test {
         })
         .await;
     }
+
+    #[tokio::test]
+    async fn home_db_rolls_back_to_block() {
+        run_test_db(|db| async move {
+            let home_db = HomeDB::new(db, "home_1".to_owned());
+            let signer: LocalWallet =
+                "1111111111111111111111111111111111111111111111111111111111111111"
+                    .parse()
+                    .unwrap();
+
+            // one message and one update per block, at blocks 10, 11 and 12
+            let mut roots = vec![H256::zero()];
+            for i in 0..3u32 {
+                let m = OpticsMessage {
+                    origin: 10,
+                    sender: H256::from_low_u64_be(4),
+                    nonce: i,
+                    destination: 12,
+                    recipient: H256::from_low_u64_be(5),
+                    body: vec![1, 2, 3],
+                };
+                let message = RawCommittedMessage {
+                    leaf_index: i,
+                    committed_root: roots[i as usize],
+                    message: m.to_vec(),
+                };
+                home_db.store_raw_committed_message(&message).unwrap();
+                home_db.store_leaf_block_number(i, 10 + i as u64).unwrap();
+
+                let new_root = H256::repeat_byte(i as u8 + 1);
+                let update = Update {
+                    home_domain: 1,
+                    previous_root: roots[i as usize],
+                    new_root,
+                }
+                .sign_with(&signer)
+                .await
+                .unwrap();
+                home_db.store_latest_update(&update).unwrap();
+                home_db
+                    .store_update_metadata(
+                        new_root,
+                        UpdateMeta {
+                            block_number: 10 + i as u64,
+                        },
+                    )
+                    .unwrap();
+                home_db
+                    .store_update_timestamp(new_root, 1_600_000_000 + i as u64)
+                    .unwrap();
+                roots.push(new_root);
+            }
+            assert_eq!(home_db.retrieve_rollback_count().unwrap(), 0);
+
+            home_db.rollback_to_block(10).unwrap();
+
+            assert_eq!(home_db.retrieve_rollback_count().unwrap(), 1);
+            assert_eq!(home_db.retrieve_latest_leaf_index().unwrap(), Some(0));
+            assert!(home_db.message_by_leaf_index(0).unwrap().is_some());
+            assert!(home_db.message_by_leaf_index(1).unwrap().is_none());
+            assert!(home_db.message_by_nonce(12, 2).unwrap().is_none());
+
+            assert_eq!(home_db.retrieve_latest_root().unwrap(), Some(roots[1]));
+            assert!(home_db.update_by_previous_root(roots[0]).unwrap().is_some());
+            assert!(home_db.update_by_previous_root(roots[1]).unwrap().is_none());
+            assert!(home_db.update_by_new_root(roots[2]).unwrap().is_none());
+            assert!(home_db
+                .retrieve_update_metadata(roots[3])
+                .unwrap()
+                .is_none());
+            assert_eq!(
+                home_db.update_timestamp(roots[1]).unwrap(),
+                Some(1_600_000_000)
+            );
+            assert_eq!(home_db.update_timestamp(roots[3]).unwrap(), None);
+
+            // rolling back past the first update clears the latest root
+            home_db.rollback_to_block(9).unwrap();
+            assert_eq!(home_db.retrieve_latest_leaf_index().unwrap(), None);
+            assert_eq!(home_db.retrieve_latest_root().unwrap(), None);
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn home_db_notifies_waiters() {
+        run_test_db(|db| async move {
+            let home_db = HomeDB::new(db.clone(), "home_1".to_owned());
+            // a separate handle to the same home shares its notifications
+            let waiter = HomeDB::new(db, "home_1".to_owned());
+            let mut events = home_db.subscribe();
+
+            let leaf_waiter = tokio::spawn(async move { waiter.wait_for_leaf(0).await.unwrap() });
+
+            let m = OpticsMessage {
+                origin: 10,
+                sender: H256::from_low_u64_be(4),
+                nonce: 0,
+                destination: 12,
+                recipient: H256::from_low_u64_be(5),
+                body: vec![1, 2, 3],
+            };
+            let message = RawCommittedMessage {
+                leaf_index: 0,
+                committed_root: H256::zero(),
+                message: m.to_vec(),
+            };
+            home_db.store_raw_committed_message(&message).unwrap();
+
+            assert_eq!(leaf_waiter.await.unwrap(), message.leaf());
+            assert_eq!(
+                events.recv().await.unwrap(),
+                HomeEvent::Leaf {
+                    leaf_index: 0,
+                    leaf: message.leaf(),
+                }
+            );
+
+            // already-stored data is returned without waiting
+            let by_nonce = home_db.wait_for_message_by_nonce(12, 0).await.unwrap();
+            assert_eq!(by_nonce, message);
+
+            let proof = Proof {
+                leaf: message.leaf(),
+                index: 0,
+                path: Default::default(),
+            };
+            home_db.store_proof(0, &proof).unwrap();
+            assert_eq!(
+                events.recv().await.unwrap(),
+                HomeEvent::Proof { leaf_index: 0 }
+            );
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn home_db_batches_commit_atomically() {
+        run_test_db(|db| async move {
+            let home_db = HomeDB::new(db, "home_1".to_owned());
+            let mut events = home_db.subscribe();
+            let signer: LocalWallet =
+                "1111111111111111111111111111111111111111111111111111111111111111"
+                    .parse()
+                    .unwrap();
+
+            let m = OpticsMessage {
+                origin: 10,
+                sender: H256::from_low_u64_be(4),
+                nonce: 0,
+                destination: 12,
+                recipient: H256::from_low_u64_be(5),
+                body: vec![1, 2, 3],
+            };
+            let message = RawCommittedMessage {
+                leaf_index: 0,
+                committed_root: H256::zero(),
+                message: m.to_vec(),
+            };
+            let update = Update {
+                home_domain: 1,
+                previous_root: H256::zero(),
+                new_root: H256::repeat_byte(1),
+            }
+            .sign_with(&signer)
+            .await
+            .unwrap();
+
+            // an uncommitted batch writes nothing
+            let batch = home_db.batch();
+            batch.store_raw_committed_message(&message).unwrap();
+            drop(batch);
+            assert_eq!(home_db.message_by_leaf_index(0).unwrap(), None);
+
+            let batch = home_db.batch();
+            batch.store_raw_committed_message(&message).unwrap();
+            batch.store_latest_update(&update).unwrap();
+            batch
+                .store_encodable("", "checkpoint", &H256::repeat_byte(2))
+                .unwrap();
+
+            // the batch reads its own writes, and nothing else sees them
+            assert_eq!(batch.retrieve_latest_leaf_index().unwrap(), Some(0));
+            assert_eq!(
+                batch.retrieve_latest_root().unwrap(),
+                Some(H256::repeat_byte(1))
+            );
+            assert_eq!(home_db.retrieve_latest_leaf_index().unwrap(), None);
+            assert_eq!(home_db.retrieve_latest_root().unwrap(), None);
+            assert!(events.try_recv().is_err());
+
+            batch.commit().unwrap();
+            assert_eq!(
+                home_db.message_by_leaf_index(0).unwrap(),
+                Some(message.clone())
+            );
+            assert_eq!(
+                home_db.update_by_previous_root(H256::zero()).unwrap(),
+                Some(update)
+            );
+            assert_eq!(
+                home_db.retrieve_decodable("", "checkpoint").unwrap(),
+                Some(H256::repeat_byte(2))
+            );
+            assert_eq!(
+                events.recv().await.unwrap(),
+                HomeEvent::Leaf {
+                    leaf_index: 0,
+                    leaf: message.leaf(),
+                }
+            );
+            assert_eq!(
+                events.recv().await.unwrap(),
+                HomeEvent::Update {
+                    previous_root: H256::zero(),
+                    new_root: H256::repeat_byte(1),
+                }
+            );
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn signing_db_refuses_conflicts_and_round_trips() {
+        run_test_db(|db| async move {
+            let signing_db = SigningDB::new(db);
+            let signer: LocalWallet =
+                "1111111111111111111111111111111111111111111111111111111111111111"
+                    .parse()
+                    .unwrap();
+
+            let previous_root = H256::repeat_byte(1);
+            let update = Update {
+                home_domain: 1,
+                previous_root,
+                new_root: H256::repeat_byte(2),
+            }
+            .sign_with(&signer)
+            .await
+            .unwrap();
+            let conflicting = Update {
+                home_domain: 1,
+                previous_root,
+                new_root: H256::repeat_byte(3),
+            }
+            .sign_with(&signer)
+            .await
+            .unwrap();
+
+            signing_db.record(&update).unwrap();
+            // re-recording the same update is fine
+            signing_db.record(&update).unwrap();
+            assert!(matches!(
+                signing_db.record(&conflicting),
+                Err(SigningError::Conflict { .. })
+            ));
+            assert_eq!(
+                signing_db
+                    .signed_update(signer.address(), 1, previous_root)
+                    .unwrap(),
+                Some(update.clone())
+            );
+
+            // the same previous root on another domain is independent
+            let other_domain = Update {
+                home_domain: 2,
+                previous_root,
+                new_root: H256::repeat_byte(3),
+            }
+            .sign_with(&signer)
+            .await
+            .unwrap();
+            signing_db.record(&other_domain).unwrap();
+
+            let history = signing_db.export(signer.address()).unwrap();
+            assert_eq!(history.signed_updates.len(), 2);
+            let json = serde_json::to_string(&history).unwrap();
+            assert_eq!(
+                serde_json::from_str::<SigningHistory>(&json).unwrap(),
+                history
+            );
+
+            // importing our own history adds nothing
+            assert_eq!(signing_db.import(&history).unwrap(), 0);
+
+            // importing a conflicting history writes nothing
+            let mut bad = history.clone();
+            let new_domain = Update {
+                home_domain: 3,
+                previous_root,
+                new_root: H256::repeat_byte(2),
+            }
+            .sign_with(&signer)
+            .await
+            .unwrap();
+            bad.signed_updates.push((&new_domain).into());
+            bad.signed_updates.push((&conflicting).into());
+            assert!(matches!(
+                signing_db.import(&bad),
+                Err(SigningError::Conflict { .. })
+            ));
+            assert_eq!(
+                signing_db
+                    .signed_update(signer.address(), 3, previous_root)
+                    .unwrap(),
+                None
+            );
+
+            // signatures must come from the exported updater
+            let mut forged = history;
+            forged.updater = Default::default();
+            assert!(matches!(
+                signing_db.import(&forged),
+                Err(SigningError::OpticsError(_))
+            ));
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn signing_db_keeps_a_history_per_updater() {
+        run_test_db(|db| async move {
+            let signing_db = SigningDB::new(db.clone());
+            let old_key: LocalWallet =
+                "1111111111111111111111111111111111111111111111111111111111111111"
+                    .parse()
+                    .unwrap();
+            let new_key: LocalWallet =
+                "2222222222222222222222222222222222222222222222222222222222222222"
+                    .parse()
+                    .unwrap();
+
+            let update = Update {
+                home_domain: 1,
+                previous_root: H256::repeat_byte(1),
+                new_root: H256::repeat_byte(2),
+            };
+            let old_signed = update.sign_with(&old_key).await.unwrap();
+            signing_db.record(&old_signed).unwrap();
+
+            // a rotated key has none of the old key's signatures
+            assert_eq!(
+                signing_db
+                    .signed_update(new_key.address(), 1, update.previous_root)
+                    .unwrap(),
+                None
+            );
+            let new_signed = update.sign_with(&new_key).await.unwrap();
+            signing_db.record(&new_signed).unwrap();
+            assert_eq!(
+                signing_db
+                    .signed_update(new_key.address(), 1, update.previous_root)
+                    .unwrap(),
+                Some(new_signed)
+            );
+
+            // each key exports only its own history
+            let history = signing_db.export(new_key.address()).unwrap();
+            assert_eq!(history.signed_updates.len(), 1);
+
+            // records from before the history was keyed by updater are
+            // moved under the updater that signed them
+            let mut legacy_key = b"signing_history_signed_update_".to_vec();
+            legacy_key.extend(2u32.to_be_bytes());
+            legacy_key.extend(b"_");
+            legacy_key.extend(H256::repeat_byte(3).as_bytes());
+            let legacy = Update {
+                home_domain: 2,
+                previous_root: H256::repeat_byte(3),
+                new_root: H256::repeat_byte(4),
+            }
+            .sign_with(&old_key)
+            .await
+            .unwrap();
+            db.store_encodable("", &legacy_key, &legacy).unwrap();
+            db.store_encodable("", "optics_schema_version", &2u32)
+                .unwrap();
+
+            let reports = db.migrate(false).unwrap();
+            assert_eq!(reports.len(), 1);
+            assert_eq!(reports[0].writes, 2);
+            assert_eq!(
+                signing_db
+                    .signed_update(old_key.address(), 2, H256::repeat_byte(3))
+                    .unwrap(),
+                Some(legacy)
+            );
+            assert_eq!(signing_db.signed_updates(old_key.address()).len(), 2);
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn replica_db_stores_and_retrieves_history() {
+        run_test_db(|db| async move {
+            let replica_db = ReplicaDB::new(db, "replica_1".to_owned());
+            let signer: LocalWallet =
+                "1111111111111111111111111111111111111111111111111111111111111111"
+                    .parse()
+                    .unwrap();
+
+            let previous_root = H256::repeat_byte(1);
+            let new_root = H256::repeat_byte(2);
+            let update = Update {
+                home_domain: 1,
+                previous_root,
+                new_root,
+            }
+            .sign_with(&signer)
+            .await
+            .unwrap();
+            let meta = UpdateMeta { block_number: 20 };
+
+            replica_db.store_update(&update, meta).unwrap();
+            assert_eq!(
+                replica_db.update_by_previous_root(previous_root).unwrap(),
+                Some(update.clone())
+            );
+            assert_eq!(
+                replica_db.update_by_new_root(new_root).unwrap(),
+                Some(update.clone())
+            );
+            assert_eq!(replica_db.update_metadata(new_root).unwrap(), Some(meta));
+            assert_eq!(replica_db.update_by_new_root(previous_root).unwrap(), None);
+
+            let conflicting = Update {
+                home_domain: 1,
+                previous_root,
+                new_root: H256::repeat_byte(3),
+            }
+            .sign_with(&signer)
+            .await
+            .unwrap();
+            let double = DoubleUpdate(update, conflicting);
+            replica_db.store_double_update(&double, 20).unwrap();
+            assert_eq!(
+                replica_db
+                    .double_update_by_previous_root(previous_root)
+                    .unwrap(),
+                Some(double)
+            );
+
+            let leaf = H256::repeat_byte(4);
+            let processed = ProcessedMessage {
+                success: true,
+                block_number: 21,
+            };
+            assert_eq!(replica_db.processed(leaf).unwrap(), None);
+            replica_db.store_processed(leaf, processed).unwrap();
+            assert_eq!(replica_db.processed(leaf).unwrap(), Some(processed));
+
+            let seen = RelayedUpdate {
+                seen_at: 100,
+                submitted_at: None,
+                submission_block: None,
+            };
+            let submitted = RelayedUpdate {
+                submitted_at: Some(130),
+                submission_block: Some(22),
+                ..seen
+            };
+            replica_db.store_relayed_update(new_root, &seen).unwrap();
+            replica_db
+                .store_relayed_update(previous_root, &submitted)
+                .unwrap();
+            assert_eq!(replica_db.relayed_update(new_root).unwrap(), Some(seen));
+            assert_eq!(
+                replica_db.relayed_updates().unwrap(),
+                vec![(previous_root, submitted), (new_root, seen)]
+            );
+            replica_db.remove_relayed_update(previous_root).unwrap();
+            assert_eq!(
+                replica_db.relayed_updates().unwrap(),
+                vec![(new_root, seen)]
+            );
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn replica_db_rolls_back_to_block() {
+        run_test_db(|db| async move {
+            let replica_db = ReplicaDB::new(db, "replica_1".to_owned());
+            let signer: LocalWallet =
+                "1111111111111111111111111111111111111111111111111111111111111111"
+                    .parse()
+                    .unwrap();
+
+            let roots: Vec<_> = (0..3).map(H256::repeat_byte).collect();
+            let mut updates = vec![];
+            for (i, pair) in roots.windows(2).enumerate() {
+                let update = Update {
+                    home_domain: 1,
+                    previous_root: pair[0],
+                    new_root: pair[1],
+                }
+                .sign_with(&signer)
+                .await
+                .unwrap();
+                let meta = UpdateMeta {
+                    block_number: 10 * (i as u64 + 1),
+                };
+                replica_db.store_update(&update, meta).unwrap();
+                updates.push(update);
+            }
+
+            let conflicting = Update {
+                home_domain: 1,
+                previous_root: roots[1],
+                new_root: H256::repeat_byte(9),
+            }
+            .sign_with(&signer)
+            .await
+            .unwrap();
+            let double = DoubleUpdate(updates[1].clone(), conflicting);
+            replica_db.store_double_update(&double, 20).unwrap();
+
+            let (kept, reorged) = (H256::repeat_byte(4), H256::repeat_byte(5));
+            for (leaf, block_number) in [(kept, 10), (reorged, 20)].iter() {
+                let processed = ProcessedMessage {
+                    success: true,
+                    block_number: *block_number,
+                };
+                replica_db.store_processed(*leaf, processed).unwrap();
+            }
+
+            // nothing is removed until the batch is committed
+            let batch = replica_db.batch();
+            batch.rollback_to_block(15).unwrap();
+            assert!(replica_db.processed(reorged).unwrap().is_some());
+            batch.commit().unwrap();
+
+            assert_eq!(
+                replica_db.update_by_new_root(roots[1]).unwrap(),
+                Some(updates[0].clone())
+            );
+            assert_eq!(replica_db.update_by_previous_root(roots[1]).unwrap(), None);
+            assert_eq!(replica_db.update_by_new_root(roots[2]).unwrap(), None);
+            assert_eq!(replica_db.update_metadata(roots[2]).unwrap(), None);
+            assert_eq!(
+                replica_db.double_update_by_previous_root(roots[1]).unwrap(),
+                None
+            );
+            assert!(replica_db.processed(kept).unwrap().is_some());
+            assert_eq!(replica_db.processed(reorged).unwrap(), None);
+        })
+        .await;
+    }
 }
@@ -148,6 +148,8 @@ async fn mainnet_works() {
                 }),
                 address: "0xcEc158A719d11005Bd9339865965bed938BEafA3".into(),
                 disabled: None,
+                index: Default::default(),
+                tx: Default::default(),
             }],
         },
         Duration::from_secs(120),
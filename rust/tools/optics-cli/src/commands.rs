@@ -1,6 +1,9 @@
 use structopt::StructOpt;
 
-use crate::subcommands::{db_state::DbStateCommand, prove::ProveCommand};
+use crate::subcommands::{
+    consistency::ConsistencyCommand, db::DbCommand, db_state::DbStateCommand, dlq::DlqCommand,
+    prove::ProveCommand, signing_history::SigningHistoryCommand, skipped::SkippedCommand,
+};
 
 #[derive(StructOpt)]
 pub enum Commands {
@@ -8,4 +11,17 @@ pub enum Commands {
     Prove(ProveCommand),
     /// Print the processor's db state
     DbState(DbStateCommand),
+    /// Import or export an updater's signing history
+    SigningHistory(SigningHistoryCommand),
+    /// Manage an agent's db schema
+    Db(DbCommand),
+    /// Check that a home's tree at one root extends its tree at an earlier
+    /// root
+    Consistency(ConsistencyCommand),
+    /// List, inspect, retry or drop messages in the processor's dead-letter
+    /// queue
+    Dlq(DlqCommand),
+    /// List the messages the processor's rules skipped, or replay denied
+    /// ones
+    Skipped(SkippedCommand),
 }
@@ -15,5 +15,10 @@ async fn main() -> Result<()> {
     match command {
         Commands::Prove(prove) => prove.run().await,
         Commands::DbState(db_state) => db_state.run().await,
+        Commands::SigningHistory(signing_history) => signing_history.run().await,
+        Commands::Db(db) => db.run().await,
+        Commands::Consistency(consistency) => consistency.run().await,
+        Commands::Dlq(dlq) => dlq.run().await,
+        Commands::Skipped(skipped) => skipped.run().await,
     }
 }
@@ -0,0 +1,120 @@
+use std::convert::TryFrom;
+
+use color_eyre::{eyre::bail, Result};
+use ethers::{
+    prelude::{Http, Middleware, Provider, H160},
+    types::{transaction::eip2718::TypedTransaction, Filter, TransactionRequest, H256},
+    utils::id,
+};
+use structopt::StructOpt;
+
+use optics_core::{
+    accumulator::INITIAL_ROOT,
+    db::{HomeDB, DB},
+};
+
+/// The home's update event. The new root is its third indexed topic
+const UPDATE_EVENT: &str = "Update(uint32,bytes32,bytes32,bytes)";
+
+#[derive(StructOpt, Debug)]
+pub struct ConsistencyCommand {
+    /// The name of the home chain, used to lookup keys in the db
+    #[structopt(long)]
+    home_name: String,
+
+    /// Path to db containing the home's updates
+    #[structopt(long)]
+    db_path: String,
+
+    /// Home contract address
+    #[structopt(long)]
+    address: H160,
+
+    /// RPC connection details for the home chain
+    #[structopt(long)]
+    rpc: String,
+
+    /// The block to search for update events from
+    #[structopt(long, default_value = "0")]
+    from_block: u64,
+
+    /// The earlier root
+    #[structopt(long)]
+    old_root: H256,
+
+    /// The later root. Defaults to the home's committed root
+    #[structopt(long)]
+    new_root: Option<H256>,
+
+    /// Print the consistency proof as JSON
+    #[structopt(long)]
+    print_proof: bool,
+}
+
+impl ConsistencyCommand {
+    pub async fn run(&self) -> Result<()> {
+        let db = DB::from_path(&self.db_path)?;
+        let home_db = HomeDB::new(db, self.home_name.clone());
+        let provider = Provider::<Http>::try_from(self.rpc.as_str())?;
+
+        // The roots come from the chain, so that the tree in the db is
+        // checked against the home rather than against itself
+        let new_root = match self.new_root {
+            Some(root) => root,
+            None => self.committed_root(&provider).await?,
+        };
+        // The home's first update is from the zero root
+        let empty_if_zero = |root: H256| if root.is_zero() { *INITIAL_ROOT } else { root };
+        let (old_root, new_root) = (empty_if_zero(self.old_root), empty_if_zero(new_root));
+
+        for root in [old_root, new_root].iter() {
+            if *root != *INITIAL_ROOT && !self.is_update_root(&provider, *root).await? {
+                bail!("No update to root {:?} on home {:?}", root, self.address);
+            }
+        }
+
+        let tree = home_db.merkle()?;
+        let proof = tree.prove_consistency_between_roots(old_root, new_root)?;
+        if !proof.verify(old_root, new_root) {
+            bail!(
+                "Tree at root {:?} ({} leaves) does not extend tree at root {:?} ({} leaves)",
+                new_root,
+                proof.new_count,
+                old_root,
+                proof.old_count
+            );
+        }
+
+        println!(
+            "Tree at root {:?} ({} leaves) extends tree at root {:?} ({} leaves)",
+            new_root, proof.new_count, old_root, proof.old_count
+        );
+        if self.print_proof {
+            println!("{}", serde_json::to_string_pretty(&proof)?);
+        }
+        Ok(())
+    }
+
+    /// Fetch the home's committed root
+    async fn committed_root(&self, provider: &Provider<Http>) -> Result<H256> {
+        let tx: TypedTransaction = TransactionRequest::new()
+            .to(self.address)
+            .data(id("committedRoot()").to_vec())
+            .into();
+        let output = provider.call(&tx, None).await?;
+        if output.len() != 32 {
+            bail!("Unexpected committedRoot output {:?}", output);
+        }
+        Ok(H256::from_slice(&output))
+    }
+
+    /// Check whether the home emitted an update to `root`
+    async fn is_update_root(&self, provider: &Provider<Http>, root: H256) -> Result<bool> {
+        let filter = Filter::new()
+            .address(self.address)
+            .event(UPDATE_EVENT)
+            .topic3(root)
+            .from_block(self.from_block);
+        Ok(!provider.get_logs(&filter).await?.is_empty())
+    }
+}
@@ -0,0 +1,48 @@
+use color_eyre::Result;
+use structopt::StructOpt;
+
+use optics_core::db::{DB, SCHEMA_VERSION};
+
+#[derive(StructOpt, Debug)]
+pub enum DbCommand {
+    /// Migrate an agent db to the current schema version
+    Migrate {
+        /// Path to agent db
+        #[structopt(long)]
+        db_path: String,
+
+        /// Report the pending migrations without writing anything
+        #[structopt(long)]
+        dry_run: bool,
+    },
+}
+
+impl DbCommand {
+    pub async fn run(&self) -> Result<()> {
+        match self {
+            Self::Migrate { db_path, dry_run } => {
+                let db = DB::open(db_path)?;
+                let found = db.schema_version()?;
+                println!(
+                    "Schema version {}, current version {}",
+                    found, SCHEMA_VERSION
+                );
+
+                let reports = db.migrate(*dry_run)?;
+                if reports.is_empty() {
+                    println!("Nothing to migrate");
+                }
+                for report in reports {
+                    println!(
+                        "{} migration {}: {} ({} writes)",
+                        if *dry_run { "Would run" } else { "Ran" },
+                        report.version,
+                        report.description,
+                        report.writes
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
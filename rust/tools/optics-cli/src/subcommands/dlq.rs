@@ -0,0 +1,147 @@
+use color_eyre::{eyre::bail, Result};
+use serde_json::json;
+use std::{
+    convert::TryInto,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use structopt::StructOpt;
+
+use optics_core::{
+    db::{DeadLetter, HomeDB, DB},
+    CommittedMessage,
+};
+
+#[derive(StructOpt, Debug)]
+pub struct DlqCommand {
+    /// The name of the home chain, used to lookup keys in the db
+    #[structopt(long)]
+    home_name: String,
+
+    /// Path to processor db. The processor must be stopped
+    #[structopt(long)]
+    db_path: String,
+
+    /// The destination domain of the replica
+    #[structopt(long)]
+    destination: u32,
+
+    #[structopt(subcommand)]
+    action: DlqAction,
+}
+
+#[derive(StructOpt, Debug)]
+pub enum DlqAction {
+    /// List the messages in the queue
+    List,
+    /// Print a queued message and its failures
+    Inspect {
+        /// The message's nonce
+        #[structopt(long)]
+        nonce: u32,
+    },
+    /// Retry a queued message as soon as the processor starts
+    Retry {
+        /// The message's nonce
+        #[structopt(long)]
+        nonce: u32,
+    },
+    /// Remove a message from the queue without processing it. A deferred
+    /// message is also removed from the skipped messages, so it is never
+    /// replayed
+    Drop {
+        /// The message's nonce
+        #[structopt(long)]
+        nonce: u32,
+    },
+}
+
+impl DlqCommand {
+    pub async fn run(&self) -> Result<()> {
+        let db = DB::from_path(&self.db_path)?;
+        let home_db = HomeDB::new(db, self.home_name.clone());
+
+        match self.action {
+            DlqAction::List => {
+                let dead_letters = home_db.dead_letters(self.destination)?;
+                if dead_letters.is_empty() {
+                    println!("No messages to {} in the queue", self.destination);
+                }
+                for (nonce, dead_letter) in dead_letters {
+                    println!(
+                        "Nonce {}: leaf index {}, {} attempts, {}. {}",
+                        nonce,
+                        dead_letter.leaf_index,
+                        dead_letter.attempts,
+                        match dead_letter.retry_at {
+                            Some(at) => format!("retrying at {}", at),
+                            None => "not retrying".to_owned(),
+                        },
+                        dead_letter.reason
+                    );
+                }
+            }
+            DlqAction::Inspect { nonce } => {
+                let dead_letter = self.dead_letter(&home_db, nonce)?;
+                let message: CommittedMessage =
+                    match home_db.message_by_nonce(self.destination, nonce)? {
+                        Some(message) => message.try_into()?,
+                        None => bail!("No message {}:{} in db", self.destination, nonce),
+                    };
+
+                let output = json!({
+                    "nonce": nonce,
+                    "leafIndex": dead_letter.leaf_index,
+                    "leaf": message.to_leaf(),
+                    "reason": dead_letter.reason,
+                    "attempts": dead_letter.attempts,
+                    "firstFailed": dead_letter.first_failed,
+                    "lastFailed": dead_letter.last_failed,
+                    "retryAt": dead_letter.retry_at,
+                    "deferred": dead_letter.deferred,
+                    "message": {
+                        "origin": message.message.origin,
+                        "sender": message.message.sender,
+                        "destination": message.message.destination,
+                        "recipient": message.message.recipient,
+                        "body": format!("0x{}", hex::encode(&message.message.body)),
+                    },
+                });
+                println!("{}", serde_json::to_string_pretty(&output)?);
+            }
+            DlqAction::Retry { nonce } => {
+                let mut dead_letter = self.dead_letter(&home_db, nonce)?;
+                dead_letter.retry_at = Some(
+                    SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .expect("system time before unix epoch")
+                        .as_secs(),
+                );
+                home_db.store_dead_letter(self.destination, nonce, &dead_letter)?;
+                println!(
+                    "Message {}:{} will be retried when the processor starts",
+                    self.destination, nonce
+                );
+            }
+            DlqAction::Drop { nonce } => {
+                let dead_letter = self.dead_letter(&home_db, nonce)?;
+                // A deferred message would be replayed from its skipped
+                // message record, so drop that too
+                let batch = home_db.batch();
+                batch.remove_dead_letter(self.destination, nonce)?;
+                if dead_letter.deferred {
+                    batch.remove_skipped_message(self.destination, nonce)?;
+                }
+                batch.commit()?;
+                println!("Dropped message {}:{}", self.destination, nonce);
+            }
+        }
+        Ok(())
+    }
+
+    fn dead_letter(&self, home_db: &HomeDB, nonce: u32) -> Result<DeadLetter> {
+        match home_db.dead_letter(self.destination, nonce)? {
+            Some(dead_letter) => Ok(dead_letter),
+            None => bail!("Message {}:{} is not in the queue", self.destination, nonce),
+        }
+    }
+}
@@ -1,5 +1,15 @@
+pub mod consistency;
+pub mod db;
 pub mod db_state;
+pub mod dlq;
 pub mod prove;
+pub mod signing_history;
+pub mod skipped;
 
+pub use consistency::*;
+pub use db::*;
 pub use db_state::*;
+pub use dlq::*;
 pub use prove::*;
+pub use signing_history::*;
+pub use skipped::*;
@@ -4,11 +4,10 @@ use structopt::StructOpt;
 use crate::{replicas, rpc};
 
 use optics_core::{
-    accumulator::merkle::Proof,
     db::{HomeDB, DB},
-    ContractLocator, Decode, MessageStatus, OpticsMessage, Replica, Signers,
+    Common, ContractLocator, MessageStatus, RawCommittedMessage, Replica, Signers,
 };
-use optics_ethereum::EthereumReplica;
+use optics_ethereum::{EthereumReplica, TxManager};
 
 use ethers::{
     prelude::{Http, Middleware, Provider, SignerMiddleware, H160},
@@ -44,6 +43,11 @@ pub struct ProveCommand {
     #[structopt(long)]
     db_path: String,
 
+    /// Root to prove the leaf under. Defaults to the replica's committed
+    /// root
+    #[structopt(long)]
+    root: Option<H256>,
+
     /// HexKey to use (please be careful)
     #[structopt(long)]
     key: Option<String>,
@@ -67,10 +71,26 @@ pub struct ProveCommand {
 
 impl ProveCommand {
     pub async fn run(&self) -> Result<()> {
-        let (message, proof) = self.fetch_proof()?;
-        let replica = self.replica(message.origin, message.destination).await?;
+        let db = DB::from_path(&self.db_path)?;
+        let home_db = HomeDB::new(db.clone(), self.home_name.clone());
+        let (leaf_index, raw) = self.fetch_message(&home_db)?;
+        let view = raw.view()?;
+        let replica = self.replica(view.origin(), view.destination(), db).await?;
+
+        let root = match self.root {
+            Some(root) => root,
+            None => replica.committed_root().await?,
+        };
+        if !replica.acceptable_root(root).await? {
+            bail!("Root {:?} is not yet acceptable on the replica", root);
+        }
+        let proof = match home_db.prove_under_root(leaf_index, root)? {
+            Some(proof) => proof,
+            None => bail!("Leaf {} is not under root {:?}", leaf_index, root),
+        };
 
-        let status = replica.message_status(message.to_leaf()).await?;
+        let message = view.to_message();
+        let status = replica.message_status(view.to_leaf()).await?;
         let outcome = match status {
             MessageStatus::None => replica.prove_and_process(&message, &proof).await?,
             MessageStatus::Proven => replica.process(&message).await?,
@@ -109,9 +129,7 @@ impl ProveCommand {
         }
     }
 
-    fn fetch_proof(&self) -> Result<(OpticsMessage, Proof)> {
-        let db = HomeDB::new(DB::from_path(&self.db_path)?, self.home_name.clone());
-
+    fn fetch_message(&self, db: &HomeDB) -> Result<(u32, RawCommittedMessage)> {
         let idx = match (self.leaf_index, self.leaf) {
             (Some(idx), _) => idx,
             (None, Some(digest)) => match db.message_by_leaf(digest)? {
@@ -121,14 +139,13 @@ impl ProveCommand {
             (None, None) => bail!("Must provide leaf index or leaf hash"),
         };
 
-        let proof = db.proof_by_leaf_index(idx)?.expect("no proof");
-        let message = db.message_by_leaf_index(idx)?.expect("no message");
-        let message = OpticsMessage::read_from(&mut message.message.as_slice())?;
-
-        Ok((message, proof))
+        match db.message_by_leaf_index(idx)? {
+            Some(message) => Ok((idx, message)),
+            None => bail!("No message at leaf index {}", idx),
+        }
     }
 
-    async fn replica(&self, origin: u32, destination: u32) -> Result<ConcreteReplica> {
+    async fn replica(&self, origin: u32, destination: u32, db: DB) -> Result<ConcreteReplica> {
         // bit ugly. Tries passed-in rpc first, then defaults to lookup by
         // domain
         let provider = self
@@ -158,6 +175,8 @@ impl ProveCommand {
                 domain: 0,
                 address: address.into(),
             },
+            db.clone(),
+            TxManager::new(db, "prove", Default::default(), None),
         ))
     }
 }
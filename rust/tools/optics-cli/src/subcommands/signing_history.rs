@@ -0,0 +1,68 @@
+use color_eyre::Result;
+use std::fs::File;
+use structopt::StructOpt;
+
+use optics_core::db::{SigningDB, SigningHistory, DB};
+
+use ethers::types::Address;
+
+#[derive(StructOpt, Debug)]
+pub enum SigningHistoryCommand {
+    /// Export an updater's signing history to a JSON file
+    Export {
+        /// Path to updater db
+        #[structopt(long)]
+        db_path: String,
+
+        /// Address of the updater
+        #[structopt(long)]
+        updater: Address,
+
+        /// JSON file to write
+        #[structopt(long)]
+        file: String,
+    },
+    /// Import an updater's signing history from a JSON file
+    Import {
+        /// Path to updater db
+        #[structopt(long)]
+        db_path: String,
+
+        /// JSON file to read
+        #[structopt(long)]
+        file: String,
+    },
+}
+
+impl SigningHistoryCommand {
+    pub async fn run(&self) -> Result<()> {
+        match self {
+            Self::Export {
+                db_path,
+                updater,
+                file,
+            } => {
+                let db = SigningDB::new(DB::from_path(db_path)?);
+                let history = db.export(*updater)?;
+                serde_json::to_writer_pretty(File::create(file)?, &history)?;
+                println!(
+                    "Exported {} signed updates to {}",
+                    history.signed_updates.len(),
+                    file
+                );
+            }
+            Self::Import { db_path, file } => {
+                let history: SigningHistory = serde_json::from_reader(File::open(file)?)?;
+                let db = SigningDB::new(DB::from_path(db_path)?);
+                let imported = db.import(&history)?;
+                println!(
+                    "Imported {} new signed updates of {} in {}",
+                    imported,
+                    history.signed_updates.len(),
+                    file
+                );
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,87 @@
+use color_eyre::{eyre::bail, Result};
+use structopt::StructOpt;
+
+use optics_core::db::{HomeDB, DB};
+
+#[derive(StructOpt, Debug)]
+pub struct SkippedCommand {
+    /// The name of the home chain, used to lookup keys in the db
+    #[structopt(long)]
+    home_name: String,
+
+    /// Path to processor db. The processor must be stopped
+    #[structopt(long)]
+    db_path: String,
+
+    /// The destination domain of the replica
+    #[structopt(long)]
+    destination: u32,
+
+    #[structopt(subcommand)]
+    action: SkippedAction,
+}
+
+#[derive(StructOpt, Debug)]
+pub enum SkippedAction {
+    /// List the messages skipped by rules
+    List,
+    /// Check a denied message against the rules again when the processor
+    /// starts. It is processed if they now allow it
+    Replay {
+        /// The message's nonce
+        #[structopt(long)]
+        nonce: u32,
+    },
+}
+
+impl SkippedCommand {
+    pub async fn run(&self) -> Result<()> {
+        let db = DB::from_path(&self.db_path)?;
+        let home_db = HomeDB::new(db, self.home_name.clone());
+
+        match self.action {
+            SkippedAction::List => {
+                let skipped = home_db.skipped_messages(self.destination)?;
+                if skipped.is_empty() {
+                    println!("No skipped messages to {}", self.destination);
+                }
+                for (nonce, skipped) in skipped {
+                    println!(
+                        "Nonce {}: leaf index {}, {} by rule {} at {}",
+                        nonce,
+                        skipped.leaf_index,
+                        if skipped.deferred {
+                            "deferred"
+                        } else {
+                            "denied"
+                        },
+                        skipped.rule,
+                        skipped.timestamp
+                    );
+                }
+            }
+            SkippedAction::Replay { nonce } => {
+                let mut skipped = match home_db.skipped_message(self.destination, nonce)? {
+                    Some(skipped) => skipped,
+                    None => bail!("Message {}:{} was not skipped", self.destination, nonce),
+                };
+                if skipped.deferred {
+                    bail!(
+                        "Message {}:{} is deferred, and is already checked against the rules",
+                        self.destination,
+                        nonce
+                    );
+                }
+                // The processor checks deferred messages against the rules
+                // each time it starts
+                skipped.deferred = true;
+                home_db.store_skipped_message(self.destination, nonce, &skipped)?;
+                println!(
+                    "Message {}:{} will be checked against the rules when the processor starts",
+                    self.destination, nonce
+                );
+            }
+        }
+        Ok(())
+    }
+}
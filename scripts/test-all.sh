@@ -31,6 +31,10 @@ set -xe
     echo '+cargo run --bin utils_test_output --features output'
     cargo run --bin utils_test_output --features output
 
+    echo "+Running xApps vector generation"
+    echo '+cargo run --bin xapps_test_output --features output'
+    cargo run --bin xapps_test_output --features output
+
     cd ..
 
     # Run rust tests, clippy, and formatting
@@ -0,0 +1,31 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+pragma solidity >=0.6.11;
+pragma experimental ABIEncoderV2;
+
+import "../governance/GovernanceMessage.sol";
+
+contract TestGovernanceMessage {
+    function formatCalls(GovernanceMessage.Call[] memory _calls)
+        external
+        view
+        returns (bytes memory)
+    {
+        return GovernanceMessage.formatCalls(_calls);
+    }
+
+    function formatTransferGovernor(uint32 _domain, bytes32 _governor)
+        external
+        view
+        returns (bytes memory)
+    {
+        return GovernanceMessage.formatTransferGovernor(_domain, _governor);
+    }
+
+    function formatSetRouter(uint32 _domain, bytes32 _router)
+        external
+        view
+        returns (bytes memory)
+    {
+        return GovernanceMessage.formatSetRouter(_domain, _router);
+    }
+}
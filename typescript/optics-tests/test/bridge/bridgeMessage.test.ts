@@ -12,6 +12,8 @@ import {
 } from '@optics-xyz/ts-interface/dist/optics-xapps';
 import { TokenIdentifier } from '@optics-xyz/multi-provider/dist/optics';
 
+const bridgeMessageTestCases = require('../../../../vectors/bridgeMessage.json');
+
 const stringToBytes32 = (s: string): string => {
   const str = Buffer.from(s.slice(0, 32), 'utf-8');
   const result = Buffer.alloc(32);
@@ -301,6 +303,58 @@ describe('BridgeMessage', async () => {
     expect(action).to.equal(transferBytes);
   });
 
+  it('matches Rust-output BridgeMessage test cases', async () => {
+    for (const testCase of bridgeMessageTestCases) {
+      const { tokenId, action } = testCase.message;
+
+      const newTokenId = await bridgeMessage.testFormatTokenId(
+        tokenId.domain,
+        tokenId.id,
+      );
+      expect(newTokenId).to.equal(testCase.tokenId);
+
+      let newAction: string, actionType: number;
+      switch (action.type) {
+        case 'transfer':
+          newAction = await bridgeMessage.testFormatTransfer(
+            action.recipient,
+            action.amount,
+          );
+          actionType = BridgeMessageTypes.TRANSFER;
+          break;
+        case 'details':
+          newAction = await bridgeMessage.testFormatDetails(
+            action.name,
+            action.symbol,
+            action.decimals,
+          );
+          actionType = BridgeMessageTypes.DETAILS;
+          break;
+        case 'requestDetails':
+          newAction = await bridgeMessage.testFormatRequestDetails();
+          actionType = BridgeMessageTypes.REQUEST_DETAILS;
+          break;
+        default:
+          throw new Error(`Unknown action type ${action.type}`);
+      }
+      expect(newAction).to.equal(testCase.action);
+
+      const newMessage = await bridgeMessage.testFormatMessage(
+        newTokenId,
+        newAction,
+        BridgeMessageTypes.TOKEN_ID,
+        actionType,
+      );
+      expect(newMessage).to.equal(testCase.encoded);
+
+      const [splitTokenId, splitAction] = await bridgeMessage.testSplitMessage(
+        testCase.encoded,
+      );
+      expect(splitTokenId).to.equal(testCase.tokenId);
+      expect(splitAction).to.equal(testCase.action);
+    }
+  });
+
   it('fails if message type is not valid', async () => {
     const revertMsg = 'Validity assertion failed';
 
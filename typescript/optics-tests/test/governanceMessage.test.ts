@@ -0,0 +1,52 @@
+import { ethers } from 'hardhat';
+import { expect } from 'chai';
+import {
+  TestGovernanceMessage,
+  TestGovernanceMessage__factory,
+} from '@optics-xyz/ts-interface/dist/optics-core';
+
+const governanceMessageTestCases = require('../../../vectors/governanceMessage.json');
+
+describe('GovernanceMessage', async () => {
+  let governanceMessage: TestGovernanceMessage;
+
+  before(async () => {
+    const [signer] = await ethers.getSigners();
+
+    const governanceMessageFactory = new TestGovernanceMessage__factory(signer);
+    governanceMessage = await governanceMessageFactory.deploy();
+  });
+
+  it('Matches Rust-output GovernanceMessage test cases', async () => {
+    for (const testCase of governanceMessageTestCases) {
+      const { message } = testCase;
+
+      let encoded: string;
+      switch (message.type) {
+        case 'calls':
+          encoded = await governanceMessage.formatCalls(
+            message.calls.map((call: { to: string; data: number[] }) => ({
+              to: call.to,
+              data: ethers.utils.hexlify(call.data),
+            })),
+          );
+          break;
+        case 'transferGovernor':
+          encoded = await governanceMessage.formatTransferGovernor(
+            message.domain,
+            message.governor,
+          );
+          break;
+        case 'setRouter':
+          encoded = await governanceMessage.formatSetRouter(
+            message.domain,
+            message.router,
+          );
+          break;
+        default:
+          throw new Error(`Unknown message type ${message.type}`);
+      }
+      expect(encoded).to.equal(testCase.encoded);
+    }
+  });
+});